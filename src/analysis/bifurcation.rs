@@ -0,0 +1,322 @@
+// src/analysis/bifurcation.rs
+
+use nalgebra::{Complex, DMatrix, DVector};
+use plotters::prelude::*;
+use crate::draw_figure;
+use crate::models::model::{ModelState, NeuronModel};
+use crate::optim::lqr::{mean_field_fixed_point, mean_field_jacobian};
+use crate::plotting::PlotStyle;
+
+/// Settings of the continuation
+#[derive(Debug, Clone, Copy)]
+pub struct ContinuationSettings {
+    /// Parameter interval to cover
+    pub from: f64,
+    pub to: f64,
+    /// Initial and maximal arclength steps
+    pub step: f64,
+    pub max_step: f64,
+    pub max_points: usize,
+}
+
+/// Point of a branch of mean-field equilibria
+#[derive(Debug, Clone)]
+pub struct BranchPoint<S: ModelState> {
+    pub parameter: f64,
+    pub state: S,
+    pub eigenvalues: Vec<Complex<f64>>,
+}
+
+impl<S: ModelState> BranchPoint<S> {
+    /// Number of eigenvalues with positive real part
+    pub fn unstable_dimension(&self) -> usize {
+        self.eigenvalues.iter().filter(|e| e.re > 0.0).count()
+    }
+
+    pub fn is_stable(&self) -> bool {
+        self.unstable_dimension() == 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BifurcationKind {
+    /// A real eigenvalue crosses zero: two equilibria collide (fold of the branch)
+    SaddleNode,
+    /// A complex pair crosses the imaginary axis: oscillations are born or die
+    Hopf,
+}
+
+/// Bifurcation detected between two consecutive points of the branch, located by
+/// linear interpolation of the crossing eigenvalue
+#[derive(Debug, Clone)]
+pub struct BifurcationPoint<S: ModelState> {
+    pub kind: BifurcationKind,
+    pub parameter: f64,
+    pub state: S,
+    /// Angular frequency of the oscillations at a Hopf point, zero otherwise
+    pub frequency: f64,
+}
+
+/// Mean-field drift with the parameter set to p
+fn residual<N: NeuronModel>(neuron: &N, name: &str, state: &N::State, p: f64) -> DVector<f64> {
+    let mut neuron = neuron.clone();
+    neuron.set_parameter(name, p).expect("checked by continue_equilibria");
+    let drift = neuron.drift(state, neuron.coupling_observable(state));
+    DVector::from_fn(N::State::DIM, |k, _| drift[k])
+}
+
+/// Jacobian [F_x | F_p] of the mean-field drift w.r.t. the state and the parameter
+fn extended_jacobian<N: NeuronModel>(neuron: &N, name: &str, state: &N::State, p: f64) -> DMatrix<f64> {
+    let n = N::State::DIM;
+    let mut neuron_p = neuron.clone();
+    neuron_p.set_parameter(name, p).expect("checked by continue_equilibria");
+    let h = 1e-6 * p.abs().max(1.0);
+    let dfdp = (residual(neuron, name, state, p + h) - residual(neuron, name, state, p - h)) / (2.0 * h);
+
+    let mut jacobian = DMatrix::zeros(n, n + 1);
+    jacobian.view_mut((0, 0), (n, n)).copy_from(&mean_field_jacobian(&neuron_p, state));
+    jacobian.set_column(n, &dfdp);
+    jacobian
+}
+
+/// Unit tangent of the branch, oriented along `previous`
+fn tangent(jacobian: &DMatrix<f64>, previous: &DVector<f64>) -> Option<DVector<f64>> {
+    let n = jacobian.nrows();
+    let mut system = DMatrix::zeros(n + 1, n + 1);
+    system.view_mut((0, 0), (n, n + 1)).copy_from(jacobian);
+    system.set_row(n, &previous.transpose());
+    let mut rhs = DVector::zeros(n + 1);
+    rhs[n] = 1.0;
+    let t = system.lu().solve(&rhs)?;
+    Some(t.normalize())
+}
+
+fn to_state<S: ModelState>(z: &DVector<f64>) -> S {
+    let mut state = S::default();
+    for k in 0..S::DIM {
+        state[k] = z[k];
+    }
+    state
+}
+
+/// Pseudo-arclength continuation of the equilibria of the mean-field system in the parameter
+/// `name`, starting from the equilibrium reached by Newton's method from `guess` at `from`
+/// and moving towards `to`. Each step predicts along the tangent of the branch and corrects
+/// with Newton's method on the drift plus the arclength condition, so that folds are passed.
+pub fn continue_equilibria<N: NeuronModel>(
+    neuron: &N,
+    name: &str,
+    guess: N::State,
+    settings: &ContinuationSettings,
+) -> Result<Vec<BranchPoint<N::State>>, String> {
+    let n = N::State::DIM;
+    let mut start = neuron.clone();
+    start.set_parameter(name, settings.from)?;
+    let x0 = mean_field_fixed_point(&start, guess)?;
+
+    let (low, high) = (settings.from.min(settings.to), settings.from.max(settings.to));
+    let point = |z: &DVector<f64>| -> BranchPoint<N::State> {
+        let state = to_state(z);
+        let mut neuron = neuron.clone();
+        neuron.set_parameter(name, z[n]).expect("checked above");
+        BranchPoint {
+            parameter: z[n],
+            state,
+            eigenvalues: mean_field_jacobian(&neuron, &state).complex_eigenvalues().iter().cloned().collect(),
+        }
+    };
+
+    let mut z = DVector::from_fn(n + 1, |k, _| if k < n { x0[k] } else { settings.from });
+    let mut direction = DVector::zeros(n + 1);
+    direction[n] = (settings.to - settings.from).signum();
+    let mut ds = settings.step;
+    let mut branch = vec![point(&z)];
+
+    while branch.len() < settings.max_points {
+        let t = tangent(&extended_jacobian(neuron, name, &to_state(&z), z[n]), &direction)
+            .ok_or_else(|| format!("singular continuation system at {} = {}", name, z[n]))?;
+
+        // Predictor along the tangent, Newton corrector on F(z) = 0, t·(z - z_pred) = 0
+        let mut corrected = None;
+        while corrected.is_none() && ds >= 1e-8 {
+            let predicted = &z + &t * ds;
+            let mut y = predicted.clone();
+            for iteration in 0..20 {
+                let state: N::State = to_state(&y);
+                let f = residual(neuron, name, &state, y[n]);
+                let arclength = t.dot(&(&y - &predicted));
+                if f.norm() < 1e-10 && arclength.abs() < 1e-10 {
+                    corrected = Some((y.clone(), iteration));
+                    break;
+                }
+                let mut system = DMatrix::zeros(n + 1, n + 1);
+                system.view_mut((0, 0), (n, n + 1)).copy_from(&extended_jacobian(neuron, name, &state, y[n]));
+                system.set_row(n, &t.transpose());
+                let mut rhs = DVector::zeros(n + 1);
+                rhs.rows_mut(0, n).copy_from(&f);
+                rhs[n] = arclength;
+                match system.lu().solve(&rhs) {
+                    Some(step) => y -= step,
+                    None => break,
+                }
+            }
+            if corrected.is_none() {
+                ds *= 0.5;
+            }
+        }
+        let (next, iterations) = corrected.ok_or_else(|| format!("the continuation stalled at {} = {}", name, z[n]))?;
+
+        // Longer steps while the corrector converges quickly
+        if iterations <= 3 {
+            ds = (ds * 1.5).min(settings.max_step);
+        }
+        direction = t;
+        z = next;
+        branch.push(point(&z));
+        if z[n] < low || z[n] > high {
+            break;
+        }
+    }
+
+    Ok(branch)
+}
+
+
+/// Saddle-node and Hopf points between consecutive points of a branch: the sign of a real
+/// eigenvalue, or of the real part of a complex pair, changes
+pub fn detect_bifurcations<S: ModelState>(branch: &[BranchPoint<S>]) -> Vec<BifurcationPoint<S>> {
+    let mut points = Vec::new();
+    let determinant = |p: &BranchPoint<S>| p.eigenvalues.iter().fold(Complex::new(1.0, 0.0), |acc, e| acc * e).re;
+    // Largest real part among the complex eigenvalues, if any
+    let oscillating = |p: &BranchPoint<S>| {
+        p.eigenvalues
+            .iter()
+            .filter(|e| e.im.abs() > 1e-9)
+            .max_by(|a, b| a.re.total_cmp(&b.re))
+            .cloned()
+    };
+    let interpolate = |a: &BranchPoint<S>, b: &BranchPoint<S>, fa: f64, fb: f64| -> (f64, S) {
+        let s = fa / (fa - fb);
+        let mut state = a.state;
+        for k in 0..S::DIM {
+            state[k] += s * (b.state[k] - a.state[k]);
+        }
+        (a.parameter + s * (b.parameter - a.parameter), state)
+    };
+
+    for pair in branch.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        let (da, db) = (determinant(a), determinant(b));
+        if da * db < 0.0 {
+            let (parameter, state) = interpolate(a, b, da, db);
+            points.push(BifurcationPoint { kind: BifurcationKind::SaddleNode, parameter, state, frequency: 0.0 });
+        } else if let (Some(ea), Some(eb)) = (oscillating(a), oscillating(b)) {
+            if ea.re * eb.re < 0.0 {
+                let (parameter, state) = interpolate(a, b, ea.re, eb.re);
+                let frequency = 0.5 * (ea.im.abs() + eb.im.abs());
+                points.push(BifurcationPoint { kind: BifurcationKind::Hopf, parameter, state, frequency });
+            }
+        }
+    }
+
+    points
+}
+
+
+/// Writes the branch, one row per point, with the number of unstable eigenvalues
+pub fn save_branch_to_csv<S: ModelState>(
+    branch: &[BranchPoint<S>],
+    name: &str,
+    path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut wtr = csv::Writer::from_path(path)?;
+
+    let mut header = vec![name];
+    header.extend_from_slice(S::COMPONENTS);
+    header.extend_from_slice(&["unstable", "max_real_part"]);
+    wtr.write_record(&header)?;
+
+    for point in branch {
+        let mut row = vec![point.parameter.to_string()];
+        row.extend((0..S::DIM).map(|k| point.state[k].to_string()));
+        row.push(point.unstable_dimension().to_string());
+        row.push(point.eigenvalues.iter().map(|e| e.re).fold(f64::NEG_INFINITY, f64::max).to_string());
+        wtr.write_record(&row)?;
+    }
+
+    wtr.flush()?;
+    Ok(())
+}
+
+
+/// Writes the detected bifurcations
+pub fn save_bifurcations_to_csv<S: ModelState>(
+    points: &[BifurcationPoint<S>],
+    name: &str,
+    path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    wtr.write_record(["kind", name, "v", "frequency"])?;
+    for point in points {
+        let kind = match point.kind {
+            BifurcationKind::SaddleNode => "saddle-node",
+            BifurcationKind::Hopf => "hopf",
+        };
+        wtr.write_record([kind.to_string(), point.parameter.to_string(), point.state.potential().to_string(), point.frequency.to_string()])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+
+/// Bifurcation diagram: potential of the equilibria against the parameter, stable parts in
+/// black and unstable parts in the first color of the palette, with the saddle-node and Hopf points
+pub fn plot_bifurcation_diagram<S: ModelState>(
+    branch: &[BranchPoint<S>],
+    points: &[BifurcationPoint<S>],
+    name: &str,
+    style: &PlotStyle,
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let p_min = branch.iter().map(|p| p.parameter).fold(f64::INFINITY, f64::min);
+    let p_max = branch.iter().map(|p| p.parameter).fold(f64::NEG_INFINITY, f64::max);
+    let v_min = branch.iter().map(|p| p.state.potential()).fold(f64::INFINITY, f64::min);
+    let v_max = branch.iter().map(|p| p.state.potential()).fold(f64::NEG_INFINITY, f64::max);
+    let margin = if v_max > v_min { 0.05 * (v_max - v_min) } else { 0.5 };
+    let caption = format!("Bifurcation Diagram in {}", name);
+
+    draw_figure!(style, filename, |root| {
+        let mut chart = style.chart(&root, &caption).build_cartesian_2d(p_min..p_max, (v_min - margin)..(v_max + margin))?;
+
+        chart.configure_mesh().x_desc(name).y_desc("v*").label_style(style.label_font()).axis_desc_style(style.label_font()).draw()?;
+
+        // One segment per pair of consecutive points, colored by the stability of the first
+        let width = style.line_width + 1;
+        for (stable, color, label) in [(true, BLACK, "stable"), (false, style.color(0), "unstable")] {
+            chart
+                .draw_series(branch.windows(2).filter(|pair| pair[0].is_stable() == stable).map(|pair| {
+                    PathElement::new(
+                        vec![(pair[0].parameter, pair[0].state.potential()), (pair[1].parameter, pair[1].state.potential())],
+                        color.stroke_width(width),
+                    )
+                }))?
+                .label(label)
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(width)));
+        }
+
+        for (kind, color, label) in [(BifurcationKind::SaddleNode, style.color(1), "saddle-node"), (BifurcationKind::Hopf, style.color(3), "Hopf")] {
+            chart
+                .draw_series(
+                    points
+                        .iter()
+                        .filter(|p| p.kind == kind)
+                        .map(|p| Circle::new((p.parameter, p.state.potential()), 6, color.filled())),
+                )?
+                .label(label)
+                .legend(move |(x, y)| Circle::new((x + 10, y), 5, color.filled()));
+        }
+
+        chart.configure_series_labels().label_font(style.label_font()).border_style(BLACK).draw()?;
+        Ok(())
+    })
+}
@@ -0,0 +1,171 @@
+// src/analysis/ensemble.rs
+
+use plotters::prelude::*;
+use crate::draw_figure;
+use crate::plotting::PlotStyle;
+
+/// Statistics of a quantity over independent noise realizations
+#[derive(Debug, Clone, Copy)]
+pub struct EnsembleStatistics {
+    pub mean: f64,
+    pub std: f64,
+    /// Lower and upper quantiles of the band
+    pub lower: f64,
+    pub upper: f64,
+}
+
+/// Quantile q of sorted values, interpolated linearly between order statistics
+pub fn quantile(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+    let position = q.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let (below, above) = (position.floor() as usize, position.ceil() as usize);
+    sorted[below] + (position - below as f64) * (sorted[above] - sorted[below])
+}
+
+/// Mean, standard deviation and central band holding the fraction `level` of the values
+/// (e.g. 0.9 for the 5% and 95% quantiles); NaN values are left out
+pub fn summarize(values: &[f64], level: f64) -> EnsembleStatistics {
+    let mut sorted: Vec<f64> = values.iter().cloned().filter(|x| !x.is_nan()).collect();
+    sorted.sort_by(f64::total_cmp);
+    let n = sorted.len() as f64;
+    let mean = sorted.iter().sum::<f64>() / n;
+    let std = (sorted.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n).sqrt();
+    let tail = 0.5 * (1.0 - level);
+    EnsembleStatistics { mean, std, lower: quantile(&sorted, tail), upper: quantile(&sorted, 1.0 - tail) }
+}
+
+/// Statistics at each time of a quantity recorded in every realization
+pub fn ensemble_band(series: &[Vec<f64>], level: f64) -> Vec<EnsembleStatistics> {
+    let m = series.iter().map(|s| s.len()).min().unwrap_or(0);
+    (0..m).map(|t| summarize(&series.iter().map(|s| s[t]).collect::<Vec<f64>>(), level)).collect()
+}
+
+
+/// Writes the statistics of scalar quantities, one row each
+pub fn save_ensemble_summary_to_csv(
+    quantities: &[(String, EnsembleStatistics)],
+    path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    wtr.write_record(["quantity", "mean", "std", "lower", "upper"])?;
+    for (name, s) in quantities {
+        wtr.write_record([name.clone(), s.mean.to_string(), s.std.to_string(), s.lower.to_string(), s.upper.to_string()])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+
+/// Writes a band sampled every `dt`, one row per time
+pub fn save_ensemble_band_to_csv(
+    band: &[EnsembleStatistics],
+    dt: f64,
+    path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    wtr.write_record(["time", "mean", "std", "lower", "upper"])?;
+    for (t, s) in band.iter().enumerate() {
+        wtr.write_record([(t as f64 * dt).to_string(), s.mean.to_string(), s.std.to_string(), s.lower.to_string(), s.upper.to_string()])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+
+/// Plots the mean of a band sampled every `dt` with its quantile band shaded and ± one
+/// standard deviation, against named references sampled on the same times
+pub fn plot_ensemble_band(
+    band: &[EnsembleStatistics],
+    dt: f64,
+    references: &[(&str, &[f64])],
+    labels: (&str, &str),
+    style: &PlotStyle,
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (caption, y_desc) = labels;
+    let time = |t: usize| t as f64 * dt;
+    let values = band
+        .iter()
+        .flat_map(|s| [s.lower, s.upper, s.mean - s.std, s.mean + s.std])
+        .chain(references.iter().flat_map(|(_, r)| r.iter().cloned()))
+        .filter(|x| x.is_finite());
+    let (y_min, y_max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), x| (lo.min(x), hi.max(x)));
+    let margin = if y_max > y_min { 0.05 * (y_max - y_min) } else { 0.5 };
+    // The band takes the second color of the palette, the references the others
+    let color = style.color(1);
+    let reference_line = |k: usize| style.line(if k == 0 { 0 } else { k + 1 }, 1);
+
+    draw_figure!(style, filename, |root| {
+        let mut chart = style.chart(&root, caption).build_cartesian_2d(0.0..time(band.len()), y_min - margin..y_max + margin)?;
+
+        chart.configure_mesh().x_desc("t").y_desc(y_desc).label_style(style.label_font()).axis_desc_style(style.label_font()).draw()?;
+
+        // Quantile band: upper edge forward, lower edge backward
+        let outline: Vec<(f64, f64)> = band
+            .iter()
+            .enumerate()
+            .map(|(t, s)| (time(t), s.upper))
+            .chain(band.iter().enumerate().rev().map(|(t, s)| (time(t), s.lower)))
+            .collect();
+        chart
+            .draw_series(std::iter::once(Polygon::new(outline, color.mix(0.2).filled())))?
+            .label("quantile band")
+            .legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 20, y + 5)], color.mix(0.2).filled()));
+
+        let std_line = color.mix(0.5).stroke_width(style.line_width);
+        for sign in [-1.0, 1.0] {
+            let series = chart.draw_series(LineSeries::new(
+                band.iter().enumerate().map(move |(t, s)| (time(t), s.mean + sign * s.std)),
+                std_line,
+            ))?;
+            if sign > 0.0 {
+                series.label("mean ± std").legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], std_line));
+            }
+        }
+
+        let mean_line = color.stroke_width(style.line_width + 1);
+        chart
+            .draw_series(LineSeries::new(band.iter().enumerate().map(|(t, s)| (time(t), s.mean)), mean_line))?
+            .label("mean")
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], mean_line));
+
+        for (k, (name, reference)) in references.iter().enumerate() {
+            let line = reference_line(k);
+            chart
+                .draw_series(LineSeries::new(reference.iter().enumerate().map(|(t, r)| (time(t), *r)), line))?
+                .label(*name)
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], line));
+        }
+
+        chart.configure_series_labels().label_font(style.label_font()).border_style(BLACK).draw()?;
+        Ok(())
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantiles_interpolate_between_order_statistics() {
+        assert_eq!(quantile(&[0.0, 1.0], 0.5), 0.5);
+        assert_eq!(quantile(&[0.0, 1.0, 3.0], 0.75), 2.0);
+        assert_eq!(quantile(&[0.0, 1.0, 3.0], 1.5), 3.0);
+        assert!(quantile(&[], 0.5).is_nan());
+    }
+
+    #[test]
+    fn summaries_leave_out_missing_values() {
+        let s = summarize(&[3.0, f64::NAN, 1.0, 2.0, 4.0], 0.5);
+        assert_eq!(s.mean, 2.5);
+        assert!((s.std - 1.25f64.sqrt()).abs() < 1e-12);
+        assert_eq!((s.lower, s.upper), (1.75, 3.25));
+
+        let band = ensemble_band(&[vec![0.0, 1.0, 5.0], vec![2.0, 3.0]], 0.9);
+        assert_eq!(band.len(), 2);
+        assert_eq!(band[1].mean, 2.0);
+    }
+}
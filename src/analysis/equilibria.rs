@@ -0,0 +1,246 @@
+// src/analysis/equilibria.rs
+
+use nalgebra::{Complex, DMatrix};
+use plotters::prelude::*;
+use crate::analysis::phase_plane::Curve;
+use crate::draw_figure;
+use crate::models::model::NeuronModel;
+use crate::models::neuron::{FhnParameters, NeuronState};
+use crate::optim::lqr::mean_field_jacobian;
+use crate::plotting::PlotStyle;
+
+/// Potentials scanned for equilibria: the cubic term of the drift dominates beyond
+const V_RANGE: f64 = 6.0;
+const SCAN_POINTS: usize = 12_000;
+
+/// Deterministic FHN system under analysis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum System {
+    /// Uncoupled neuron, without synaptic input
+    SingleNeuron,
+    /// All-to-all population in the mean-field limit: every neuron feels the gate of its own state
+    MeanField,
+}
+
+impl System {
+    pub fn name(&self) -> &'static str {
+        match self {
+            System::SingleNeuron => "single",
+            System::MeanField => "mean-field",
+        }
+    }
+
+    /// Synaptic input felt in the state `state`
+    fn input(&self, state: &NeuronState) -> f64 {
+        match self {
+            System::SingleNeuron => 0.0,
+            System::MeanField => state.y,
+        }
+    }
+}
+
+/// Linear stability of an equilibrium, from the eigenvalues of its Jacobian
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stability {
+    StableNode,
+    StableFocus,
+    UnstableNode,
+    UnstableFocus,
+    Saddle,
+    /// Saddle whose unstable (or stable) directions rotate
+    SaddleFocus,
+    /// Some eigenvalue on the imaginary axis
+    NonHyperbolic,
+}
+
+impl Stability {
+    pub fn classify(eigenvalues: &[Complex<f64>]) -> Self {
+        let tol = 1e-9;
+        let oscillating = eigenvalues.iter().any(|e| e.im.abs() > tol);
+        if eigenvalues.iter().any(|e| e.re.abs() <= tol) {
+            Stability::NonHyperbolic
+        } else if eigenvalues.iter().all(|e| e.re < 0.0) {
+            if oscillating { Stability::StableFocus } else { Stability::StableNode }
+        } else if eigenvalues.iter().all(|e| e.re > 0.0) {
+            if oscillating { Stability::UnstableFocus } else { Stability::UnstableNode }
+        } else if oscillating {
+            Stability::SaddleFocus
+        } else {
+            Stability::Saddle
+        }
+    }
+
+    pub fn is_stable(&self) -> bool {
+        matches!(self, Stability::StableNode | Stability::StableFocus)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Stability::StableNode => "stable node",
+            Stability::StableFocus => "stable focus",
+            Stability::UnstableNode => "unstable node",
+            Stability::UnstableFocus => "unstable focus",
+            Stability::Saddle => "saddle",
+            Stability::SaddleFocus => "saddle-focus",
+            Stability::NonHyperbolic => "non-hyperbolic",
+        }
+    }
+}
+
+/// Equilibrium with its linearization
+#[derive(Debug, Clone)]
+pub struct Equilibrium {
+    pub state: NeuronState,
+    pub eigenvalues: Vec<Complex<f64>>,
+    pub stability: Stability,
+}
+
+/// State with potential v on the w- and y-nullclines: w = (v + a)/b, y = y∞(v)
+pub fn state_on_nullclines(params: &FhnParameters, v: f64) -> NeuronState {
+    NeuronState { v, w: (v + params.a) / params.b, y: params.gate_steady_state(v) }
+}
+
+/// Jacobian of the system at the given state
+pub fn jacobian(params: &FhnParameters, system: System, state: &NeuronState) -> DMatrix<f64> {
+    match system {
+        System::SingleNeuron => params.drift_jacobian(state, 0.0),
+        System::MeanField => mean_field_jacobian(params, state),
+    }
+}
+
+/// Linearization of the system at an equilibrium state
+pub fn linearize(params: &FhnParameters, system: System, state: NeuronState) -> Equilibrium {
+    let eigenvalues: Vec<Complex<f64>> = jacobian(params, system, &state).complex_eigenvalues().iter().cloned().collect();
+    let stability = Stability::classify(&eigenvalues);
+    Equilibrium { state, eigenvalues, stability }
+}
+
+/// Drift of v along the w- and y-nullclines, whose zeros are the equilibria
+fn reduced_drift(params: &FhnParameters, system: System, v: f64) -> f64 {
+    let state = state_on_nullclines(params, v);
+    params.drift(&state, system.input(&state)).v
+}
+
+/// All equilibria of the system, by increasing potential: the drift of v along the
+/// other nullclines is scanned for sign changes, which are refined by bisection
+pub fn equilibria(params: &FhnParameters, system: System) -> Vec<Equilibrium> {
+    let g = |v: f64| reduced_drift(params, system, v);
+    let dv = 2.0 * V_RANGE / SCAN_POINTS as f64;
+    let mut roots = Vec::new();
+
+    for k in 0..SCAN_POINTS {
+        let (mut lo, mut hi) = (-V_RANGE + k as f64 * dv, -V_RANGE + (k + 1) as f64 * dv);
+        let (g_lo, g_hi) = (g(lo), g(hi));
+        if g_lo == 0.0 {
+            roots.push(lo);
+            continue;
+        }
+        if g_lo * g_hi > 0.0 {
+            continue;
+        }
+        for _ in 0..60 {
+            let mid = 0.5 * (lo + hi);
+            if g(lo) * g(mid) <= 0.0 { hi = mid } else { lo = mid }
+        }
+        roots.push(0.5 * (lo + hi));
+    }
+
+    roots.dedup_by(|a, b| (*a - *b).abs() < 1e-9);
+    roots
+        .into_iter()
+        .map(|v| linearize(params, system, state_on_nullclines(params, v)))
+        .collect()
+}
+
+/// v-nullcline projected on the (v, w) plane, with the gate at y∞(v) for the mean field:
+/// w = v - v³/3 + Iext - J (v - Vrev) y
+pub fn v_nullcline(params: &FhnParameters, system: System, v: f64) -> f64 {
+    let state = state_on_nullclines(params, v);
+    v - v.powi(3) / 3.0 + params.Iext - params.J * (v - params.Vrev) * system.input(&state)
+}
+
+/// w-nullcline: w = (v + a)/b
+pub fn w_nullcline(params: &FhnParameters, v: f64) -> f64 {
+    (v + params.a) / params.b
+}
+
+/// v- and w-nullclines of the system, sampled at `points` + 1 potentials over `v_range`
+pub fn nullcline_curves(params: &FhnParameters, system: System, v_range: (f64, f64), points: usize) -> Vec<Curve> {
+    let vs: Vec<f64> = (0..=points).map(|k| v_range.0 + (v_range.1 - v_range.0) * k as f64 / points as f64).collect();
+    vec![
+        ("v-nullcline".to_string(), vs.iter().map(|&v| (v, v_nullcline(params, system, v))).collect()),
+        ("w-nullcline".to_string(), vs.iter().map(|&v| (v, w_nullcline(params, v))).collect()),
+    ]
+}
+
+
+/// Writes one row per equilibrium of each system, with its eigenvalues
+pub fn save_equilibria_to_csv(
+    systems: &[(System, Vec<Equilibrium>)],
+    path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    wtr.write_record(["system", "v", "w", "y", "stability", "eigenvalues"])?;
+
+    for (system, equilibria) in systems {
+        for eq in equilibria {
+            let eigenvalues: Vec<String> = eq.eigenvalues.iter().map(|e| format!("{}{:+}i", e.re, e.im)).collect();
+            wtr.write_record([
+                system.name().to_string(),
+                eq.state.v.to_string(),
+                eq.state.w.to_string(),
+                eq.state.y.to_string(),
+                eq.stability.label().to_string(),
+                eigenvalues.join(" "),
+            ])?;
+        }
+    }
+
+    wtr.flush()?;
+    Ok(())
+}
+
+
+/// Plots the v- and w-nullclines of the system on the (v, w) plane, with its equilibria
+/// (filled when stable, hollow otherwise)
+pub fn plot_phase_plane(
+    params: &FhnParameters,
+    system: System,
+    equilibria: &[Equilibrium],
+    style: &PlotStyle,
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (v_min, v_max) = (-2.5, 2.5);
+    let curves = nullcline_curves(params, system, (v_min, v_max), 500);
+    let w_min = curves.iter().flat_map(|(_, points)| points.iter().map(|p| p.1)).fold(f64::INFINITY, f64::min).max(-3.0);
+    let w_max = curves.iter().flat_map(|(_, points)| points.iter().map(|p| p.1)).fold(f64::NEG_INFINITY, f64::max).min(3.0);
+
+    let caption = match system {
+        System::SingleNeuron => "Phase Plane of a Single Neuron",
+        System::MeanField => "Mean-Field Phase Plane (y = y∞(v))",
+    };
+
+    draw_figure!(style, filename, |root| {
+        let mut chart = style.chart(&root, caption).build_cartesian_2d(v_min..v_max, w_min..w_max)?;
+
+        chart.configure_mesh().x_desc("v").y_desc("w").label_style(style.label_font()).axis_desc_style(style.label_font()).draw()?;
+
+        for (k, (name, points)) in curves.iter().enumerate() {
+            let line = style.line(k, 0);
+            chart
+                .draw_series(LineSeries::new(points.iter().cloned(), line))?
+                .label(name.clone())
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], line));
+        }
+
+        chart.draw_series(equilibria.iter().map(|eq| {
+            let marker = if eq.stability.is_stable() { BLACK.filled() } else { BLACK.stroke_width(2) };
+            Circle::new((eq.state.v, eq.state.w), 6, marker)
+        }))?
+        .label("equilibria")
+        .legend(|(x, y)| Circle::new((x + 10, y), 5, BLACK.filled()));
+
+        chart.configure_series_labels().label_font(style.label_font()).border_style(BLACK).draw()?;
+        Ok(())
+    })
+}
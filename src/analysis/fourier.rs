@@ -0,0 +1,74 @@
+// src/analysis/fourier.rs
+
+use nalgebra::Complex;
+use std::f64::consts::PI;
+
+/// In-place radix-2 fast Fourier transform; the length must be a power of two.
+/// The inverse transform includes the 1/n normalization.
+pub fn fft(data: &mut [Complex<f64>], inverse: bool) {
+    let n = data.len();
+    assert!(n.is_power_of_two(), "FFT length {} is not a power of two", n);
+
+    // Bit-reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let angle = sign * 2.0 * PI / len as f64;
+        let root = Complex::new(angle.cos(), angle.sin());
+        for start in (0..n).step_by(len) {
+            let mut w = Complex::new(1.0, 0.0);
+            for k in 0..len / 2 {
+                let a = data[start + k];
+                let b = data[start + k + len / 2] * w;
+                data[start + k] = a + b;
+                data[start + k + len / 2] = a - b;
+                w *= root;
+            }
+        }
+        len <<= 1;
+    }
+
+    if inverse {
+        let scale = 1.0 / n as f64;
+        data.iter_mut().for_each(|x| *x *= scale);
+    }
+}
+
+/// Analytic signal x + i H[x] of a real signal, by zeroing its negative frequencies.
+/// The signal is zero-padded to avoid the wrap-around of the circular transform.
+pub fn analytic_signal(signal: &[f64]) -> Vec<Complex<f64>> {
+    let m = signal.len();
+    let n = (2 * m).next_power_of_two();
+    let mut data: Vec<Complex<f64>> = signal
+        .iter()
+        .map(|&x| Complex::new(x, 0.0))
+        .chain(std::iter::repeat(Complex::new(0.0, 0.0)))
+        .take(n)
+        .collect();
+
+    fft(&mut data, false);
+    for (k, x) in data.iter_mut().enumerate() {
+        if k > 0 && k < n / 2 {
+            *x *= 2.0;
+        } else if k > n / 2 {
+            *x = Complex::new(0.0, 0.0);
+        }
+    }
+    fft(&mut data, true);
+
+    data.truncate(m);
+    data
+}
@@ -0,0 +1,8 @@
+pub mod equilibria;
+pub mod bifurcation;
+pub mod spikes;
+pub mod fourier;
+pub mod synchrony;
+pub mod spectral;
+pub mod phase_plane;
+pub mod ensemble;
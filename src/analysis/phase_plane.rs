@@ -0,0 +1,245 @@
+// src/analysis/phase_plane.rs
+
+use plotters::prelude::*;
+use crate::draw_figure;
+use crate::models::model::ModelState;
+use crate::plotting::PlotStyle;
+use crate::simulations::forward::heat_color;
+
+/// Named curve of the phase plane, e.g. a nullcline
+pub type Curve = (String, Vec<(f64, f64)>);
+
+/// Range of the first two state components over the given steps of a simulation,
+/// widened by 5% on each side
+fn component_ranges<S: ModelState>(sim: &[Vec<S>], steps: &[usize]) -> [(f64, f64); 2] {
+    let mut ranges = [(f64::INFINITY, f64::NEG_INFINITY); 2];
+    for traj in sim {
+        for &t in steps {
+            for (k, range) in ranges.iter_mut().enumerate() {
+                range.0 = range.0.min(traj[t][k]);
+                range.1 = range.1.max(traj[t][k]);
+            }
+        }
+    }
+    ranges.map(|(low, high)| {
+        let margin = if high > low { 0.05 * (high - low) } else { 0.5 };
+        (low - margin, high + margin)
+    })
+}
+
+/// Population density over the plane of the first two state components at step t:
+/// counts of neurons in a grid of bins × bins cells covering `ranges`
+pub fn density_histogram<S: ModelState>(sim: &[Vec<S>], t: usize, bins: usize, ranges: &[(f64, f64); 2]) -> Vec<Vec<usize>> {
+    let mut counts = vec![vec![0; bins]; bins];
+    let cell = |x: f64, (low, high): (f64, f64)| -> Option<usize> {
+        let k = ((x - low) / (high - low) * bins as f64).floor();
+        (k >= 0.0 && k < bins as f64).then_some(k as usize)
+    };
+    for traj in sim {
+        if let (Some(i), Some(j)) = (cell(traj[t][0], ranges[0]), cell(traj[t][1], ranges[1])) {
+            counts[i][j] += 1;
+        }
+    }
+    counts
+}
+
+
+/// Trajectories of a few neurons in the plane of the first two state components, with
+/// their starting points, over the given curves (e.g. the nullclines)
+pub fn plot_phase_trajectories<S: ModelState>(
+    sim: &[Vec<S>],
+    count: usize,
+    curves: &[Curve],
+    style: &PlotStyle,
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let count = count.min(sim.len());
+    let steps: Vec<usize> = (0..sim[0].len()).collect();
+    let [x_range, y_range] = component_ranges(&sim[..count], &steps);
+
+    draw_figure!(style, filename, |root| {
+        let mut chart = style
+            .chart(&root, "Trajectories in the Phase Plane")
+            .build_cartesian_2d(x_range.0..x_range.1, y_range.0..y_range.1)?;
+
+        chart
+            .configure_mesh()
+            .x_desc(S::COMPONENTS[0])
+            .y_desc(S::COMPONENTS[1])
+            .label_style(style.label_font())
+            .axis_desc_style(style.label_font())
+            .draw()?;
+
+        // Curves clipped to the trajectories' range, in the last colors of the palette
+        let inside = |&(x, y): &(f64, f64)| x >= x_range.0 && x <= x_range.1 && y >= y_range.0 && y <= y_range.1;
+        let colors = style.palette.0.len().max(1);
+        for (k, (name, points)) in curves.iter().enumerate() {
+            let line = style.line(colors - 1 - k % colors, 1);
+            chart
+                .draw_series(LineSeries::new(points.iter().cloned().filter(inside), line))?
+                .label(name.clone())
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], line));
+        }
+
+        for (i, traj) in sim.iter().take(count).enumerate() {
+            let line = style.line(i, 0);
+            chart
+                .draw_series(LineSeries::new(traj.iter().map(|state| (state[0], state[1])), line))?
+                .label(format!("Neuron {}", i))
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], line));
+            chart.draw_series(std::iter::once(Circle::new((traj[0][0], traj[0][1]), 4, style.color(i).filled())))?;
+        }
+
+        chart.configure_series_labels().label_font(style.label_font()).border_style(BLACK).draw()?;
+        Ok(())
+    })
+}
+
+
+/// Heatmaps of the population density over the plane of the first two state components at
+/// the given steps, side by side on a common grid of bins × bins cells
+pub fn plot_density_snapshots<S: ModelState>(
+    sim: &[Vec<S>],
+    steps: &[usize],
+    bins: usize,
+    dt: f64,
+    style: &PlotStyle,
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let ranges = component_ranges(sim, steps);
+    let [x_range, y_range] = ranges;
+    let (dx, dy) = ((x_range.1 - x_range.0) / bins as f64, (y_range.1 - y_range.0) / bins as f64);
+    let histograms: Vec<Vec<Vec<usize>>> = steps.iter().map(|&t| density_histogram(sim, t, bins, &ranges)).collect();
+    let c_max = histograms.iter().flatten().flatten().copied().max().unwrap_or(1).max(1) as f64;
+
+    // As square a layout as possible
+    let columns = (steps.len() as f64).sqrt().ceil().max(1.0) as usize;
+    let rows = steps.len().div_ceil(columns).max(1);
+
+    draw_figure!(style, filename, |root| {
+        let panels = root.split_evenly((rows, columns));
+
+        for ((panel, &t), counts) in panels.iter().zip(steps.iter()).zip(histograms.iter()) {
+            let mut chart = style
+                .panel(panel, &format!("t = {:.1}", t as f64 * dt))
+                .build_cartesian_2d(x_range.0..x_range.1, y_range.0..y_range.1)?;
+
+            chart
+                .configure_mesh()
+                .x_desc(S::COMPONENTS[0])
+                .y_desc(S::COMPONENTS[1])
+                .label_style(style.label_font())
+                .axis_desc_style(style.label_font())
+                .disable_mesh()
+                .draw()?;
+
+            // Empty cells left white
+            chart.draw_series(counts.iter().enumerate().flat_map(|(i, column)| {
+                column.iter().enumerate().filter(|(_, c)| **c > 0).map(move |(j, c)| {
+                    let (x, y) = (x_range.0 + i as f64 * dx, y_range.0 + j as f64 * dy);
+                    Rectangle::new([(x, y), (x + dx, y + dy)], heat_color(*c as f64, 0.0, c_max).filled())
+                })
+            }))?;
+        }
+
+        Ok(())
+    })
+}
+
+
+/// Animated GIF of the population over `frames` evenly spaced steps: the histogram of the
+/// potential and the density over the plane of the first two state components on top, and
+/// below the given signals (e.g. the controls) with their current values. Animations are
+/// always bitmaps, whatever the format of the style.
+pub fn animate_density<S: ModelState>(
+    sim: &[Vec<S>],
+    signals: &[(String, Vec<f64>)],
+    dt: f64,
+    frames: usize,
+    bins: usize,
+    style: &PlotStyle,
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let root = BitMapBackend::gif(filename, style.size, 100)?.into_drawing_area();
+
+    let m = sim[0].len();
+    let steps: Vec<usize> = (0..frames.max(1)).map(|k| k * (m - 1) / frames.saturating_sub(1).max(1)).collect();
+
+    // Axes shared by all frames
+    let ranges = component_ranges(sim, &steps);
+    let [x_range, y_range] = ranges;
+    let (dx, dy) = ((x_range.1 - x_range.0) / bins as f64, (y_range.1 - y_range.0) / bins as f64);
+    let densities: Vec<Vec<Vec<usize>>> = steps.iter().map(|&t| density_histogram(sim, t, bins, &ranges)).collect();
+    let histograms: Vec<Vec<usize>> = densities.iter().map(|counts| counts.iter().map(|column| column.iter().sum()).collect()).collect();
+    let c_max = densities.iter().flatten().flatten().copied().max().unwrap_or(1).max(1) as f64;
+    let h_max = histograms.iter().flatten().copied().max().unwrap_or(1).max(1) as f64;
+    let values = || signals.iter().flat_map(|(_, s)| s.iter().cloned());
+    let (mut s_min, mut s_max) = (values().fold(f64::INFINITY, f64::min), values().fold(f64::NEG_INFINITY, f64::max));
+    if s_max <= s_min {
+        (s_min, s_max) = (s_min.min(0.0) - 0.5, s_max.max(0.0) + 0.5);
+    }
+    let margin = 0.05 * (s_max - s_min);
+
+    for ((&t, counts), histogram) in steps.iter().zip(densities.iter()).zip(histograms.iter()) {
+        root.fill(&WHITE)?;
+        let (top, bottom) = root.split_vertically(style.size.1 * 3 / 5);
+        let (left, right) = top.split_horizontally(style.size.0 / 2);
+
+        let mut chart = style
+            .panel(&left, &format!("{} at t = {:.1}", S::COMPONENTS[0], t as f64 * dt))
+            .build_cartesian_2d(x_range.0..x_range.1, 0.0..h_max * 1.05)?;
+        chart
+            .configure_mesh()
+            .x_desc(S::COMPONENTS[0])
+            .y_desc("neurons")
+            .label_style(style.label_font())
+            .axis_desc_style(style.label_font())
+            .draw()?;
+        chart.draw_series(histogram.iter().enumerate().filter(|(_, c)| **c > 0).map(|(i, c)| {
+            let x = x_range.0 + i as f64 * dx;
+            Rectangle::new([(x, 0.0), (x + dx, *c as f64)], style.color(1).filled())
+        }))?;
+
+        let mut chart = style
+            .panel(&right, &format!("({}, {}) density", S::COMPONENTS[0], S::COMPONENTS[1]))
+            .build_cartesian_2d(x_range.0..x_range.1, y_range.0..y_range.1)?;
+        chart
+            .configure_mesh()
+            .x_desc(S::COMPONENTS[0])
+            .y_desc(S::COMPONENTS[1])
+            .label_style(style.label_font())
+            .axis_desc_style(style.label_font())
+            .disable_mesh()
+            .draw()?;
+        chart.draw_series(counts.iter().enumerate().flat_map(|(i, column)| {
+            column.iter().enumerate().filter(|(_, c)| **c > 0).map(move |(j, c)| {
+                let (x, y) = (x_range.0 + i as f64 * dx, y_range.0 + j as f64 * dy);
+                Rectangle::new([(x, y), (x + dx, y + dy)], heat_color(*c as f64, 0.0, c_max).filled())
+            })
+        }))?;
+
+        let mut chart = style.panel(&bottom, "").y_label_area_size(50).build_cartesian_2d(0.0..m as f64 * dt, s_min - margin..s_max + margin)?;
+        chart.configure_mesh().x_desc("t").label_style(style.label_font()).axis_desc_style(style.label_font()).draw()?;
+        for (k, (name, signal)) in signals.iter().enumerate() {
+            let line = style.line(k, 0);
+            let current = signal.get(t).copied().unwrap_or(f64::NAN);
+            chart
+                .draw_series(LineSeries::new(signal.iter().enumerate().map(|(i, x)| (i as f64 * dt, *x)), line))?
+                .label(format!("{} = {:.3}", name, current))
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], line));
+            chart.draw_series(std::iter::once(Circle::new((t as f64 * dt, current), 4, style.color(k).filled())))?;
+        }
+        // Current time
+        chart.draw_series(std::iter::once(PathElement::new(
+            vec![(t as f64 * dt, s_min - margin), (t as f64 * dt, s_max + margin)],
+            BLACK.stroke_width(style.line_width),
+        )))?;
+        if !signals.is_empty() {
+            chart.configure_series_labels().label_font(style.label_font()).border_style(BLACK).draw()?;
+        }
+
+        root.present()?;
+    }
+
+    Ok(())
+}
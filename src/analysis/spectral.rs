@@ -0,0 +1,291 @@
+// src/analysis/spectral.rs
+
+use nalgebra::Complex;
+use plotters::prelude::*;
+use std::f64::consts::PI;
+use crate::analysis::fourier::fft;
+use crate::draw_figure;
+use crate::models::model::ModelState;
+use crate::plotting::PlotStyle;
+use crate::simulations::forward::heat_color;
+
+/// Power of a signal in short overlapping windows
+#[derive(Debug, Clone)]
+pub struct Spectrogram {
+    /// Center time of each window
+    pub times: Vec<f64>,
+    pub frequencies: Vec<f64>,
+    /// One power spectral density per window
+    pub power: Vec<Vec<f64>>,
+}
+
+/// Local field potential: the mean potential of the population at each step
+pub fn local_field_potential<S: ModelState>(sim: &[Vec<S>]) -> Vec<f64> {
+    let l = sim.len() as f64;
+    (0..sim[0].len()).map(|t| sim.iter().map(|traj| traj[t].potential()).sum::<f64>() / l).collect()
+}
+
+/// Zero-padding factor of the segments, which refines the grid of frequencies
+const PADDING: usize = 4;
+
+/// Length of the transform of a segment of n samples
+fn padded_length(n: usize) -> usize {
+    (PADDING * n).next_power_of_two()
+}
+
+/// Segment length actually used: at most the signal length
+fn segment_length(segment: usize, len: usize) -> usize {
+    segment.min(len).max(2)
+}
+
+/// Hann window of length n
+fn hann(n: usize) -> Vec<f64> {
+    (0..n).map(|k| 0.5 - 0.5 * (2.0 * PI * k as f64 / n as f64).cos()).collect()
+}
+
+/// Frequencies (cycles per unit time) of the one-sided spectrum of a segment of n samples
+fn frequencies(n: usize, dt: f64) -> Vec<f64> {
+    let padded = padded_length(n);
+    (0..=padded / 2).map(|k| k as f64 / (padded as f64 * dt)).collect()
+}
+
+/// One-sided power spectral density of a single segment, after removing its mean and
+/// applying the window
+fn periodogram(segment: &[f64], window: &[f64], dt: f64) -> Vec<f64> {
+    let mean = segment.iter().sum::<f64>() / segment.len() as f64;
+    let n = padded_length(segment.len());
+    let mut data: Vec<Complex<f64>> = segment
+        .iter()
+        .zip(window.iter())
+        .map(|(x, w)| Complex::new((x - mean) * w, 0.0))
+        .chain(std::iter::repeat(Complex::new(0.0, 0.0)))
+        .take(n)
+        .collect();
+    fft(&mut data, false);
+
+    let norm = dt / window.iter().map(|w| w * w).sum::<f64>();
+    (0..=n / 2)
+        .map(|k| {
+            let p = data[k].norm_sqr() * norm;
+            // Negative frequencies folded onto the positive ones
+            if k == 0 || k == n / 2 { p } else { 2.0 * p }
+        })
+        .collect()
+}
+
+/// Welch estimate of the power spectral density: periodograms of Hann-windowed segments of
+/// `segment` samples (at most the whole signal) overlapping by half, averaged.
+/// Returns the frequencies and the density, both empty for signals shorter than two samples.
+pub fn welch_psd(signal: &[f64], dt: f64, segment: usize) -> (Vec<f64>, Vec<f64>) {
+    if signal.len() < 2 {
+        return (Vec::new(), Vec::new());
+    }
+    let n = segment_length(segment, signal.len());
+    let window = hann(n);
+    let starts: Vec<usize> = (0..=signal.len() - n).step_by(n / 2).collect();
+
+    let mut psd = vec![0.0; padded_length(n) / 2 + 1];
+    for &start in &starts {
+        for (p, q) in psd.iter_mut().zip(periodogram(&signal[start..start + n], &window, dt)) {
+            *p += q / starts.len() as f64;
+        }
+    }
+    (frequencies(n, dt), psd)
+}
+
+/// Short-time Fourier transform: periodograms of Hann-windowed segments of `segment`
+/// samples, every `hop` samples (empty for signals shorter than two samples)
+pub fn spectrogram(signal: &[f64], dt: f64, segment: usize, hop: usize) -> Spectrogram {
+    if signal.len() < 2 {
+        return Spectrogram { times: Vec::new(), frequencies: Vec::new(), power: Vec::new() };
+    }
+    let n = segment_length(segment, signal.len());
+    let window = hann(n);
+    let starts: Vec<usize> = (0..=signal.len() - n).step_by(hop.max(1)).collect();
+    Spectrogram {
+        times: starts.iter().map(|&s| (s as f64 + 0.5 * n as f64) * dt).collect(),
+        frequencies: frequencies(n, dt),
+        power: starts.iter().map(|&s| periodogram(&signal[s..s + n], &window, dt)).collect(),
+    }
+}
+
+/// Frequency of the highest peak of a spectrum, leaving out the zero frequency
+pub fn dominant_frequency(frequencies: &[f64], psd: &[f64]) -> f64 {
+    frequencies
+        .iter()
+        .zip(psd.iter())
+        .skip(1)
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map_or(f64::NAN, |(f, _)| *f)
+}
+
+
+/// Writes one or several spectra sharing the same frequencies, one column each
+pub fn save_psd_to_csv(
+    frequencies: &[f64],
+    spectra: &[(String, Vec<f64>)],
+    path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    let mut header = vec!["frequency".to_string()];
+    header.extend(spectra.iter().map(|(name, _)| name.clone()));
+    wtr.write_record(&header)?;
+
+    for (k, f) in frequencies.iter().enumerate() {
+        let mut row = vec![f.to_string()];
+        row.extend(spectra.iter().map(|(_, psd)| psd[k].to_string()));
+        wtr.write_record(&row)?;
+    }
+
+    wtr.flush()?;
+    Ok(())
+}
+
+
+/// Writes a spectrogram, one row per window and frequency
+pub fn save_spectrogram_to_csv(spectrogram: &Spectrogram, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    wtr.write_record(["time", "frequency", "power"])?;
+    for (t, power) in spectrogram.times.iter().zip(spectrogram.power.iter()) {
+        for (f, p) in spectrogram.frequencies.iter().zip(power.iter()) {
+            wtr.write_record([t.to_string(), f.to_string(), p.to_string()])?;
+        }
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+
+/// Plots one or several spectra on a logarithmic power axis, up to the frequency `f_max`
+pub fn plot_psd(
+    frequencies: &[f64],
+    spectra: &[(String, Vec<f64>)],
+    f_max: f64,
+    style: &PlotStyle,
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Positive values shown, the zero frequency left out
+    let shown = |psd: &[f64]| -> Vec<(f64, f64)> {
+        frequencies.iter().zip(psd.iter()).skip(1).filter(|(f, p)| **f <= f_max && **p > 0.0).map(|(f, p)| (*f, *p)).collect()
+    };
+    let values: Vec<f64> = spectra.iter().flat_map(|(_, psd)| shown(psd)).map(|(_, p)| p).collect();
+    let p_max = values.iter().cloned().fold(f64::MIN_POSITIVE, f64::max);
+    // At most ten decades below the highest peak
+    let p_min = values.iter().cloned().fold(f64::INFINITY, f64::min).max(p_max * 1e-10).min(p_max / 10.0);
+
+    draw_figure!(style, filename, |root| {
+        let mut chart = style
+            .chart(&root, "Power Spectral Density of the LFP")
+            .y_label_area_size(60)
+            .build_cartesian_2d(0.0..f_max, (p_min..p_max * 2.0).log_scale())?;
+
+        chart.configure_mesh().x_desc("frequency").y_desc("PSD").label_style(style.label_font()).axis_desc_style(style.label_font()).draw()?;
+
+        for (k, (name, psd)) in spectra.iter().enumerate() {
+            let line = style.line(k, 0);
+            let points: Vec<(f64, f64)> = shown(psd).into_iter().map(|(f, p)| (f, p.max(p_min))).collect();
+            chart
+                .draw_series(LineSeries::new(points, line))?
+                .label(format!("{} (peak at {:.4})", name, dominant_frequency(frequencies, psd)))
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], line));
+        }
+
+        chart.configure_series_labels().label_font(style.label_font()).border_style(BLACK).draw()?;
+        Ok(())
+    })
+}
+
+
+/// Heatmap of the logarithm of the power of a spectrogram, up to the frequency `f_max`
+pub fn plot_spectrogram(
+    spectrogram: &Spectrogram,
+    f_max: f64,
+    style: &PlotStyle,
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let times = &spectrogram.times;
+    let frequencies = &spectrogram.frequencies;
+    let half_width = if times.len() > 1 { 0.5 * (times[1] - times[0]) } else { times.first().copied().unwrap_or(1.0) };
+    let df = frequencies.get(1).copied().unwrap_or(1.0);
+    let kept = frequencies.iter().take_while(|f| **f <= f_max).count();
+
+    let log_power = |p: f64| p.max(1e-300).log10();
+    let values = || spectrogram.power.iter().flat_map(|power| power[..kept].iter().cloned().map(log_power));
+    let p_max = values().fold(f64::NEG_INFINITY, f64::max);
+    // At most eight decades below the highest peak
+    let p_min = values().fold(f64::INFINITY, f64::min).max(p_max - 8.0);
+
+    let t_min = times.first().map_or(0.0, |t| t - half_width);
+    let t_max = times.last().map_or(1.0, |t| t + half_width);
+
+    draw_figure!(style, filename, |root| {
+        let mut chart = style
+            .chart(&root, "Spectrogram of the LFP (log power)")
+            .build_cartesian_2d(t_min..t_max, 0.0..frequencies[kept.max(1) - 1] + 0.5 * df)?;
+
+        chart
+            .configure_mesh()
+            .x_desc("t")
+            .y_desc("frequency")
+            .label_style(style.label_font())
+            .axis_desc_style(style.label_font())
+            .disable_mesh()
+            .draw()?;
+
+        chart.draw_series(times.iter().zip(spectrogram.power.iter()).flat_map(|(t, power)| {
+            (0..kept).map(move |k| {
+                let f = frequencies[k];
+                Rectangle::new(
+                    [(t - half_width, f - 0.5 * df), (t + half_width, f + 0.5 * df)],
+                    heat_color(log_power(power[k]).max(p_min), p_min, p_max).filled(),
+                )
+            })
+        }))?;
+
+        Ok(())
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f64 = 0.01;
+
+    /// 0.5 + sin(2π 0.8 t) + 0.5 sin(2π 3 t), of variance 1/2 + 1/8
+    fn signal() -> Vec<f64> {
+        (0..8000)
+            .map(|t| {
+                let t = t as f64 * DT;
+                0.5 + (2.0 * PI * 0.8 * t).sin() + 0.5 * (2.0 * PI * 3.0 * t).sin()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn welch_spectrum_peaks_at_the_frequency_of_the_sine() {
+        let (frequencies, psd) = welch_psd(&signal(), DT, 1000);
+        let resolution = frequencies[1];
+        assert!((dominant_frequency(&frequencies, &psd) - 0.8).abs() <= resolution);
+    }
+
+    #[test]
+    fn welch_spectrum_integrates_to_the_variance() {
+        // Parseval: the one-sided density integrates to the power of the centered signal
+        let (frequencies, psd) = welch_psd(&signal(), DT, 1000);
+        let power: f64 = psd.iter().sum::<f64>() * frequencies[1];
+        assert!((power - 0.625).abs() < 1e-3, "power {}", power);
+    }
+
+    #[test]
+    fn spectrogram_segments_fit_in_the_signal() {
+        let (frequencies, psd) = welch_psd(&[1.0], DT, 1000);
+        assert!(frequencies.is_empty() && psd.is_empty());
+        assert!(spectrogram(&[1.0], DT, 1000, 500).times.is_empty());
+
+        let short = spectrogram(&signal()[..2500], DT, 1000, 500);
+        assert_eq!(short.times, vec![5.0, 10.0, 15.0, 20.0]);
+        assert!(short.power.iter().all(|p| p.len() == short.frequencies.len()));
+    }
+}
@@ -0,0 +1,299 @@
+// src/analysis/spikes.rs
+
+use plotters::prelude::*;
+use crate::draw_figure;
+use crate::models::model::ModelState;
+use crate::plotting::PlotStyle;
+
+/// Spike detector on the membrane potential: a spike is an upward crossing of `threshold`,
+/// and the detector is rearmed only once the potential falls back below `reset`, so that
+/// noise around the threshold does not produce spurious spikes
+#[derive(Debug, Clone, Copy)]
+pub struct SpikeDetector {
+    pub threshold: f64,
+    pub reset: f64,
+}
+
+impl SpikeDetector {
+    /// Spike times of a potential trace sampled every dt, interpolated between the two
+    /// samples around each crossing
+    pub fn detect(&self, v: &[f64], dt: f64) -> Vec<f64> {
+        let mut times = Vec::new();
+        // A trace starting above the threshold is not counted as a spike at t = 0
+        let mut armed = v.first().is_none_or(|&v0| v0 < self.threshold);
+        for t in 1..v.len() {
+            if armed && v[t - 1] < self.threshold && v[t] >= self.threshold {
+                let s = (self.threshold - v[t - 1]) / (v[t] - v[t - 1]);
+                times.push((t as f64 - 1.0 + s) * dt);
+                armed = false;
+            } else if !armed && v[t] < self.reset {
+                armed = true;
+            }
+        }
+        times
+    }
+}
+
+/// Firing statistics of one neuron
+#[derive(Debug, Clone, Copy)]
+pub struct FiringStatistics {
+    pub spikes: usize,
+    /// Spikes per unit time over the whole simulation
+    pub rate: f64,
+    /// Mean and coefficient of variation of the interspike intervals (NaN with fewer than
+    /// two, resp. three, spikes)
+    pub mean_isi: f64,
+    pub cv: f64,
+}
+
+/// Spike times of every neuron of a simulation
+pub fn detect_spikes<S: ModelState>(sim: &[Vec<S>], dt: f64, detector: &SpikeDetector) -> Vec<Vec<f64>> {
+    sim.iter()
+        .map(|traj| {
+            let v: Vec<f64> = traj.iter().map(|state| state.potential()).collect();
+            detector.detect(&v, dt)
+        })
+        .collect()
+}
+
+/// Intervals between consecutive spikes of a train
+pub fn interspike_intervals(times: &[f64]) -> Vec<f64> {
+    times.windows(2).map(|pair| pair[1] - pair[0]).collect()
+}
+
+/// Coefficient of variation std/mean of a sample, NaN with fewer than two values
+pub fn coefficient_of_variation(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return f64::NAN;
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    variance.sqrt() / mean
+}
+
+/// Firing statistics of each neuron over a simulation of the given duration
+pub fn firing_statistics(trains: &[Vec<f64>], duration: f64) -> Vec<FiringStatistics> {
+    trains
+        .iter()
+        .map(|times| {
+            let isis = interspike_intervals(times);
+            let mean_isi = if isis.is_empty() { f64::NAN } else { isis.iter().sum::<f64>() / isis.len() as f64 };
+            FiringStatistics {
+                spikes: times.len(),
+                rate: times.len() as f64 / duration,
+                mean_isi,
+                cv: coefficient_of_variation(&isis),
+            }
+        })
+        .collect()
+}
+
+/// Population firing rate: spikes of all neurons in consecutive bins of width `bin`,
+/// per neuron and per unit time; empty unless the bin width is positive
+pub fn population_rate(trains: &[Vec<f64>], duration: f64, bin: f64) -> Vec<f64> {
+    if bin.is_nan() || bin <= 0.0 {
+        return Vec::new();
+    }
+    let bins = (duration / bin).ceil().max(1.0) as usize;
+    let mut rate = vec![0.0; bins];
+    for &t in trains.iter().flatten() {
+        let k = ((t / bin) as usize).min(bins - 1);
+        rate[k] += 1.0;
+    }
+    let scale = 1.0 / (trains.len().max(1) as f64 * bin);
+    rate.iter_mut().for_each(|r| *r *= scale);
+    rate
+}
+
+/// Histogram of a sample over `bins` equal bins spanning its range: (left edges, counts)
+fn histogram(values: &[f64], bins: usize) -> (Vec<f64>, Vec<usize>) {
+    let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let width = if max > min { (max - min) / bins as f64 } else { 1.0 };
+    let mut counts = vec![0; bins];
+    for &x in values {
+        counts[(((x - min) / width) as usize).min(bins - 1)] += 1;
+    }
+    ((0..bins).map(|k| min + k as f64 * width).collect(), counts)
+}
+
+
+/// Writes the spike times, one row per spike
+pub fn save_spike_times_to_csv(trains: &[Vec<f64>], path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    wtr.write_record(["neuron_id", "time"])?;
+    for (neuron_id, times) in trains.iter().enumerate() {
+        for t in times {
+            wtr.write_record([neuron_id.to_string(), t.to_string()])?;
+        }
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+
+/// Writes the firing statistics, one row per neuron
+pub fn save_firing_statistics_to_csv(statistics: &[FiringStatistics], path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    wtr.write_record(["neuron_id", "spikes", "rate", "mean_isi", "cv"])?;
+    for (neuron_id, s) in statistics.iter().enumerate() {
+        wtr.write_record([neuron_id.to_string(), s.spikes.to_string(), s.rate.to_string(), s.mean_isi.to_string(), s.cv.to_string()])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+
+/// Writes the population rate, one row per bin (start time of the bin)
+pub fn save_population_rate_to_csv(rate: &[f64], bin: f64, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    wtr.write_record(["time", "rate"])?;
+    for (k, r) in rate.iter().enumerate() {
+        wtr.write_record([(k as f64 * bin).to_string(), r.to_string()])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+
+/// Histogram of the population firing rate over time
+pub fn plot_population_rate(
+    rate: &[f64],
+    bin: f64,
+    style: &PlotStyle,
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let t_max = bin * rate.len() as f64;
+    let r_max = rate.iter().cloned().fold(0.0, f64::max);
+
+    draw_figure!(style, filename, |root| {
+        let mut chart = style.chart(&root, "Population Firing Rate").build_cartesian_2d(0.0..t_max, 0.0..(1.05 * r_max).max(1e-9))?;
+
+        chart
+            .configure_mesh()
+            .x_desc("t")
+            .y_desc("spikes per neuron per unit time")
+            .label_style(style.label_font())
+            .axis_desc_style(style.label_font())
+            .draw()?;
+
+        chart.draw_series(rate.iter().enumerate().map(|(k, r)| {
+            let t = k as f64 * bin;
+            Rectangle::new([(t, 0.0), (t + bin, *r)], style.color(1).mix(0.6).filled())
+        }))?;
+
+        Ok(())
+    })
+}
+
+
+/// Distribution of the interspike intervals pooled over the population, with their CV
+pub fn plot_isi_distribution(
+    isis: &[f64],
+    bins: usize,
+    style: &PlotStyle,
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (edges, counts) = histogram(isis, bins.max(1));
+    let width = if edges.len() > 1 { edges[1] - edges[0] } else { 1.0 };
+    let x_min = edges.first().copied().unwrap_or(0.0);
+    let x_max = edges.last().map_or(1.0, |e| e + width);
+    let c_max = counts.iter().copied().max().unwrap_or(0).max(1);
+    let caption = format!("Interspike Intervals (CV = {:.3})", coefficient_of_variation(isis));
+
+    draw_figure!(style, filename, |root| {
+        let mut chart = style.chart(&root, &caption).build_cartesian_2d(x_min..x_max, 0.0..1.05 * c_max as f64)?;
+
+        chart.configure_mesh().x_desc("ISI").y_desc("count").label_style(style.label_font()).axis_desc_style(style.label_font()).draw()?;
+
+        chart.draw_series(edges.iter().zip(counts.iter()).map(|(&e, &c)| {
+            Rectangle::new([(e, 0.0), (e + width, c as f64)], style.color(0).mix(0.6).filled())
+        }))?;
+
+        Ok(())
+    })
+}
+
+
+/// Raster plot of the spikes of the population, with the population rate beneath it.
+/// Populations larger than `max_neurons` are subsampled evenly.
+pub fn plot_raster(
+    trains: &[Vec<f64>],
+    rate: &[f64],
+    bin: f64,
+    duration: f64,
+    max_neurons: usize,
+    style: &PlotStyle,
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let l = trains.len();
+    let shown: Vec<usize> = if l > max_neurons && max_neurons > 0 {
+        (0..max_neurons).map(|k| k * l / max_neurons).collect()
+    } else {
+        (0..l).collect()
+    };
+    let caption = if shown.len() < l {
+        format!("Spike Raster ({} of {} neurons)", shown.len(), l)
+    } else {
+        "Spike Raster".to_string()
+    };
+    let r_max = rate.iter().cloned().fold(0.0, f64::max);
+
+    draw_figure!(style, filename, |root| {
+        let (upper, lower) = root.split_vertically(style.size.1 * 7 / 10);
+
+        let mut raster = style.chart(&upper, &caption).x_label_area_size(20).build_cartesian_2d(0.0..duration, -0.5..(l as f64 - 0.5))?;
+
+        raster.configure_mesh().y_desc("neuron").label_style(style.label_font()).axis_desc_style(style.label_font()).draw()?;
+
+        // One short vertical tick per spike
+        let tick = (0.4 * l as f64 / shown.len().max(1) as f64).max(0.4);
+        raster.draw_series(shown.iter().flat_map(|&i| {
+            trains[i].iter().map(move |&t| {
+                PathElement::new(vec![(t, i as f64 - tick), (t, i as f64 + tick)], BLACK.stroke_width(style.line_width))
+            })
+        }))?;
+
+        let mut rates = style.chart(&lower, "").build_cartesian_2d(0.0..duration, 0.0..(1.05 * r_max).max(1e-9))?;
+
+        rates.configure_mesh().x_desc("t").y_desc("rate").label_style(style.label_font()).axis_desc_style(style.label_font()).draw()?;
+
+        rates.draw_series(rate.iter().enumerate().map(|(k, r)| {
+            let t = k as f64 * bin;
+            Rectangle::new([(t, 0.0), ((t + bin).min(duration), *r)], style.color(1).mix(0.6).filled())
+        }))?;
+
+        Ok(())
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DETECTOR: SpikeDetector = SpikeDetector { threshold: 1.0, reset: 0.0 };
+
+    #[test]
+    fn one_spike_per_crossing_until_rearmed() {
+        // Crosses at t = 1.5, wobbles around the threshold, then falls below the reset
+        let v = [-1.0, 0.5, 1.5, 0.8, 1.2, 0.5, -0.5, 0.2];
+        assert_eq!(DETECTOR.detect(&v, 1.0), vec![1.5]);
+
+        // Rearmed below the reset, the next crossing is a new spike
+        let v = [-1.0, 0.5, 1.5, -0.5, 0.5, 1.5];
+        assert_eq!(DETECTOR.detect(&v, 0.5), vec![0.75, 2.25]);
+
+        // Starting above the threshold is not a spike
+        assert!(DETECTOR.detect(&[2.0, 1.5, 1.2], 1.0).is_empty());
+    }
+
+    #[test]
+    fn population_rate_counts_spikes_per_bin_and_neuron() {
+        let trains = vec![vec![0.5, 1.5], vec![1.2, 2.9, 3.0]];
+        // Bins [0, 1), [1, 2), [2, 3], the last one holding the spike at the end
+        assert_eq!(population_rate(&trains, 3.0, 1.0), vec![0.5, 1.0, 1.0]);
+        assert!(population_rate(&trains, 3.0, 0.0).is_empty());
+    }
+}
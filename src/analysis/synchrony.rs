@@ -0,0 +1,317 @@
+// src/analysis/synchrony.rs
+
+use plotters::prelude::*;
+use std::f64::consts::PI;
+use std::str::FromStr;
+use crate::analysis::fourier::analytic_signal;
+use crate::draw_figure;
+use crate::models::model::ModelState;
+use crate::plotting::PlotStyle;
+
+/// Phase of an oscillating potential trace from its analytic signal (Hilbert transform),
+/// after removing its mean
+pub fn hilbert_phase(v: &[f64]) -> Vec<f64> {
+    let mean = v.iter().sum::<f64>() / v.len() as f64;
+    let centered: Vec<f64> = v.iter().map(|x| x - mean).collect();
+    analytic_signal(&centered).iter().map(|z| z.im.atan2(z.re)).collect()
+}
+
+/// Phase of a neuron interpolated between its spikes: it grows linearly by 2π from one
+/// spike to the next. Undefined (NaN) before the first and after the last spike.
+pub fn spike_phase(times: &[f64], steps: usize, dt: f64) -> Vec<f64> {
+    let mut phase = vec![f64::NAN; steps];
+    for (k, pair) in times.windows(2).enumerate() {
+        let (t0, t1) = (pair[0], pair[1]);
+        let first = (t0 / dt).ceil() as usize;
+        let last = ((t1 / dt).ceil() as usize).min(steps);
+        for (t, p) in phase.iter_mut().enumerate().take(last).skip(first) {
+            *p = 2.0 * PI * (k as f64 + (t as f64 * dt - t0) / (t1 - t0));
+        }
+    }
+    phase
+}
+
+/// Hilbert phases of every neuron of a simulation
+pub fn population_phases<S: ModelState>(sim: &[Vec<S>]) -> Vec<Vec<f64>> {
+    sim.iter()
+        .map(|traj| {
+            let v: Vec<f64> = traj.iter().map(|state| state.potential()).collect();
+            hilbert_phase(&v)
+        })
+        .collect()
+}
+
+/// Kuramoto order parameter R(t) = |(1/L) Σ_i exp(iθ_i(t))| of the neurons whose phase is
+/// defined at t (NaN when none is): 1 for perfect synchrony, close to 0 for spread phases
+pub fn kuramoto_order_parameter(phases: &[Vec<f64>]) -> Vec<f64> {
+    let m = phases.iter().map(|p| p.len()).min().unwrap_or(0);
+    (0..m)
+        .map(|t| {
+            let (mut re, mut im, mut count) = (0.0, 0.0, 0usize);
+            for theta in phases.iter().map(|p| p[t]).filter(|theta| theta.is_finite()) {
+                re += theta.cos();
+                im += theta.sin();
+                count += 1;
+            }
+            if count == 0 { f64::NAN } else { re.hypot(im) / count as f64 }
+        })
+        .collect()
+}
+
+/// Time average of the defined values of R(t)
+pub fn mean_order_parameter(r: &[f64]) -> f64 {
+    let defined: Vec<f64> = r.iter().cloned().filter(|x| x.is_finite()).collect();
+    if defined.is_empty() { f64::NAN } else { defined.iter().sum::<f64>() / defined.len() as f64 }
+}
+
+/// Synchrony index χ of Golomb and Rinzel: the variance in time of the mean potential,
+/// relative to the average variance in time of the individual potentials. χ = 1 for
+/// identical traces, χ ~ 1/√L for independent neurons.
+pub fn synchrony_index<S: ModelState>(sim: &[Vec<S>]) -> f64 {
+    let variance = |x: &[f64]| -> f64 {
+        let mean = x.iter().sum::<f64>() / x.len() as f64;
+        x.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / x.len() as f64
+    };
+    let l = sim.len() as f64;
+    let m = sim[0].len();
+    let mean_v: Vec<f64> = (0..m).map(|t| sim.iter().map(|traj| traj[t].potential()).sum::<f64>() / l).collect();
+    let individual = sim
+        .iter()
+        .map(|traj| variance(&traj.iter().map(|state| state.potential()).collect::<Vec<f64>>()))
+        .sum::<f64>()
+        / l;
+    if individual > 0.0 { (variance(&mean_v) / individual).sqrt() } else { f64::NAN }
+}
+
+/// Mean Pearson correlation of the spike counts of all pairs of neurons in bins of width
+/// `bin`; neurons with a constant count are left out. NaN when fewer than two neurons remain
+/// or the bin width is not positive.
+pub fn spike_count_correlation(trains: &[Vec<f64>], duration: f64, bin: f64) -> f64 {
+    if bin.is_nan() || bin <= 0.0 {
+        return f64::NAN;
+    }
+    let bins = (duration / bin).ceil().max(1.0) as usize;
+    // Standardized counts of each neuron that fires irregularly enough
+    let standardized: Vec<Vec<f64>> = trains
+        .iter()
+        .filter_map(|times| {
+            let mut counts = vec![0.0; bins];
+            for &t in times {
+                counts[((t / bin) as usize).min(bins - 1)] += 1.0;
+            }
+            let mean = counts.iter().sum::<f64>() / bins as f64;
+            let std = (counts.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / bins as f64).sqrt();
+            (std > 0.0).then(|| counts.iter().map(|c| (c - mean) / std).collect())
+        })
+        .collect();
+
+    let n = standardized.len();
+    if n < 2 {
+        return f64::NAN;
+    }
+    let mut total = 0.0;
+    for i in 0..n {
+        for j in i + 1..n {
+            total += standardized[i].iter().zip(standardized[j].iter()).map(|(a, b)| a * b).sum::<f64>() / bins as f64;
+        }
+    }
+    total / (n * (n - 1) / 2) as f64
+}
+
+
+/// Writes R(t) for each phase extraction, one column each
+pub fn save_order_parameter_to_csv(
+    series: &[(String, Vec<f64>)],
+    dt: f64,
+    path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    let mut header = vec!["time".to_string()];
+    header.extend(series.iter().map(|(name, _)| name.clone()));
+    wtr.write_record(&header)?;
+
+    let m = series.iter().map(|(_, r)| r.len()).min().unwrap_or(0);
+    for t in 0..m {
+        let mut row = vec![(t as f64 * dt).to_string()];
+        row.extend(series.iter().map(|(_, r)| r[t].to_string()));
+        wtr.write_record(&row)?;
+    }
+
+    wtr.flush()?;
+    Ok(())
+}
+
+
+/// Plots R(t) for each phase extraction
+pub fn plot_order_parameter(
+    series: &[(String, Vec<f64>)],
+    dt: f64,
+    style: &PlotStyle,
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let m = series.iter().map(|(_, r)| r.len()).max().unwrap_or(0);
+
+    draw_figure!(style, filename, |root| {
+        let mut chart = style.chart(&root, "Kuramoto Order Parameter").build_cartesian_2d(0.0..m as f64 * dt, 0.0..1.05)?;
+
+        chart.configure_mesh().x_desc("t").y_desc("R(t)").label_style(style.label_font()).axis_desc_style(style.label_font()).draw()?;
+
+        for (k, (name, r)) in series.iter().enumerate() {
+            let line = style.line(k, 0);
+            // Only the defined values are drawn
+            let points: Vec<(f64, f64)> = r.iter().enumerate().filter(|(_, x)| x.is_finite()).map(|(t, x)| (t as f64 * dt, *x)).collect();
+            chart
+                .draw_series(LineSeries::new(points, line))?
+                .label(name.clone())
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], line));
+        }
+
+        chart.configure_series_labels().label_font(style.label_font()).border_style(BLACK).draw()?;
+        Ok(())
+    })
+}
+
+
+/// Frame of the geometric phase of a neuron: the angle of its state in the plane of the
+/// potential and the second state component (w for FHN and Morris–Lecar), around `center`
+/// and after scaling both coordinates by `scale`. Unlike the Hilbert phase it only depends
+/// on the current state, so it can enter a running cost.
+#[derive(Debug, Clone, Copy)]
+pub struct PhaseFrame {
+    pub center: [f64; 2],
+    pub scale: [f64; 2],
+}
+
+impl PhaseFrame {
+    /// Frame centered on the average state of a simulation, scaled by the standard deviations
+    pub fn from_simulation<S: ModelState>(sim: &[Vec<S>]) -> Self {
+        let n = (sim.len() * sim[0].len()) as f64;
+        let mut center = [0.0; 2];
+        let mut scale = [0.0; 2];
+        for k in 0..2 {
+            center[k] = sim.iter().flatten().map(|state| state[k]).sum::<f64>() / n;
+            let variance = sim.iter().flatten().map(|state| (state[k] - center[k]).powi(2)).sum::<f64>() / n;
+            scale[k] = if variance > 0.0 { variance.sqrt() } else { 1.0 };
+        }
+        PhaseFrame { center, scale }
+    }
+
+    fn coordinates<S: ModelState>(&self, state: &S) -> (f64, f64) {
+        ((state[0] - self.center[0]) / self.scale[0], (state[1] - self.center[1]) / self.scale[1])
+    }
+
+    pub fn phase<S: ModelState>(&self, state: &S) -> f64 {
+        let (x, y) = self.coordinates(state);
+        y.atan2(x)
+    }
+
+    /// Derivatives of the phase w.r.t. the two state components of the frame
+    pub fn phase_gradient<S: ModelState>(&self, state: &S) -> [f64; 2] {
+        let (x, y) = self.coordinates(state);
+        let rho2 = (x * x + y * y).max(1e-12);
+        [-y / (rho2 * self.scale[0]), x / (rho2 * self.scale[1])]
+    }
+}
+
+/// Geometric phases of every neuron of a simulation
+pub fn geometric_phases<S: ModelState>(sim: &[Vec<S>], frame: &PhaseFrame) -> Vec<Vec<f64>> {
+    sim.iter().map(|traj| traj.iter().map(|state| frame.phase(state)).collect()).collect()
+}
+
+
+/// Synchrony penalized by a desynchronizing control, as given on the command line:
+/// `variance` or `kuramoto`
+#[derive(Debug, Clone, Copy)]
+pub enum DesyncSpec {
+    Variance,
+    Kuramoto,
+}
+
+impl FromStr for DesyncSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "variance" => Ok(DesyncSpec::Variance),
+            "kuramoto" => Ok(DesyncSpec::Kuramoto),
+            _ => Err(format!("unknown objective '{}', expected variance or kuramoto", s)),
+        }
+    }
+}
+
+impl DesyncSpec {
+    /// Objective whose phase frame, if any, is fitted on the simulation `sim` and then kept fixed
+    pub fn build<S: ModelState>(&self, sim: &[Vec<S>]) -> DesyncObjective {
+        match self {
+            DesyncSpec::Variance => DesyncObjective::LfpVariance,
+            DesyncSpec::Kuramoto => DesyncObjective::Kuramoto(PhaseFrame::from_simulation(sim)),
+        }
+    }
+}
+
+/// Instantaneous synchrony D(t) penalized by a desynchronizing control
+#[derive(Debug, Clone, Copy)]
+pub enum DesyncObjective {
+    /// (v̄(t) - ⟨v̄⟩)², whose integral is the variance in time of the mean potential (the
+    /// power of the local field potential). The spread of the potentials across neurons is not
+    /// penalized: it grows, rather than shrinks, when the neurons desynchronize.
+    LfpVariance,
+    /// R(t)², with the geometric phases of the frame
+    Kuramoto(PhaseFrame),
+}
+
+impl DesyncObjective {
+    /// D(t) at every step of a simulation
+    pub fn density<S: ModelState>(&self, sim: &[Vec<S>]) -> Vec<f64> {
+        let l = sim.len() as f64;
+        let m = sim[0].len();
+        match self {
+            DesyncObjective::LfpVariance => {
+                let mean_v: Vec<f64> = (0..m).map(|t| sim.iter().map(|traj| traj[t].potential()).sum::<f64>() / l).collect();
+                let average = mean_v.iter().sum::<f64>() / m as f64;
+                mean_v.iter().map(|v| (v - average).powi(2)).collect()
+            }
+            DesyncObjective::Kuramoto(frame) => {
+                kuramoto_order_parameter(&geometric_phases(sim, frame)).iter().map(|r| r * r).collect()
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::neuron::NeuronState;
+
+    #[test]
+    fn identical_trains_are_synchronous() {
+        let train = vec![1.0, 3.0, 5.0, 7.0];
+        let phases = vec![spike_phase(&train, 100, 0.1); 2];
+        let r = kuramoto_order_parameter(&phases);
+        // Undefined before the first spike, then R = 1 until the last one
+        assert!(r[5].is_nan());
+        assert!(r[10..70].iter().all(|r| (r - 1.0).abs() < 1e-12));
+        assert!((mean_order_parameter(&r) - 1.0).abs() < 1e-12);
+
+        let trains = vec![vec![0.5, 1.5, 1.7, 4.2], vec![0.5, 1.5, 1.7, 4.2]];
+        assert!((spike_count_correlation(&trains, 5.0, 1.0) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn antiphase_trains_cancel_out() {
+        let phases = vec![spike_phase(&[1.0, 3.0, 5.0], 60, 0.1), spike_phase(&[2.0, 4.0, 6.0], 60, 0.1)];
+        let r = kuramoto_order_parameter(&phases);
+        assert!(r[20..50].iter().all(|r| r.abs() < 1e-9));
+    }
+
+    #[test]
+    fn identical_traces_have_unit_synchrony_index() {
+        let trace: Vec<NeuronState> = (0..200).map(|t| NeuronState { v: (0.1 * t as f64).sin(), w: 0.0, y: 0.0 }).collect();
+        assert!((synchrony_index(&vec![trace.clone(); 3]) - 1.0).abs() < 1e-12);
+
+        // Two traces in antiphase: the mean potential is flat
+        let opposite: Vec<NeuronState> = trace.iter().map(|s| NeuronState { v: -s.v, ..*s }).collect();
+        assert!(synchrony_index(&[trace, opposite]) < 1e-12);
+    }
+}
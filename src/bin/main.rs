@@ -1,17 +1,58 @@
 // src/bin/main.rs
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use fhn::models::model::{ModelState, NeuronModel};
 use fhn::models::neuron::{FhnParameters, NeuronState};
-use fhn::simulations::forward::{simulate_fhn_population, plot_local_field_potential, plot_individual_neurons, plot_average_potential, save_simulation_to_csv, simulate_with_control};
-use fhn::simulations::adjoint::{compute_adjoint, plot_adjoint_trajectories};
-use fhn::optim::gradient::{evaluate_cost, compute_control_gradient, gradient_step, plot_cost_trace, plot_control};
-use fhn::models::reference::plot_reference_profile;
+use fhn::models::morris_lecar::MorrisLecarParameters;
+use fhn::models::hodgkin_huxley::HodgkinHuxleyParameters;
+use fhn::simulations::forward::{simulate_population, plot_local_field_potential, plot_individual_neurons, plot_average_potential, save_simulation_to_csv, simulate_with_controls, simulate_with_feedback, simulate_controlled_population, save_population_means_to_csv, plot_population_potentials, potentials, plot_space_time, plot_field_snapshot};
+use fhn::simulations::adjoint::{compute_adjoint, compute_desync_adjoint, compute_pattern_adjoint, compute_feedback_adjoint, compute_tracking_adjoint, plot_adjoint_trajectories};
+use fhn::optim::feedback::{feedback_sensitivity, mean_potential, plot_policy, policy_gradient, save_policy_to_csv, FeedbackPolicy, PolicySpec};
+use fhn::optim::gradient::{evaluate_cost, evaluate_desync_cost, evaluate_pattern_cost, evaluate_tracking_cost, compute_channel_gradients, compute_control_gradient, gradient_step, plot_cost_trace, plot_control, plot_controls};
+use fhn::analysis::equilibria::{equilibria, nullcline_curves, plot_phase_plane, save_equilibria_to_csv, System};
+use fhn::analysis::ensemble::{ensemble_band, plot_ensemble_band, save_ensemble_band_to_csv, save_ensemble_summary_to_csv, summarize};
+use fhn::analysis::phase_plane::{animate_density, plot_density_snapshots, plot_phase_trajectories, Curve};
+use fhn::analysis::spikes::{detect_spikes, firing_statistics, interspike_intervals, plot_isi_distribution, plot_population_rate, plot_raster, population_rate, save_firing_statistics_to_csv, save_population_rate_to_csv, save_spike_times_to_csv, SpikeDetector};
+use fhn::analysis::synchrony::{DesyncObjective, DesyncSpec, kuramoto_order_parameter, mean_order_parameter, plot_order_parameter, population_phases, save_order_parameter_to_csv, spike_count_correlation, spike_phase, synchrony_index};
+use fhn::analysis::spectral::{dominant_frequency, local_field_potential, plot_psd, plot_spectrogram, save_psd_to_csv, save_spectrogram_to_csv, spectrogram, welch_psd};
+use fhn::analysis::bifurcation::{continue_equilibria, detect_bifurcations, plot_bifurcation_diagram, save_bifurcations_to_csv, save_branch_to_csv, BifurcationKind, ContinuationSettings};
+use fhn::optim::lqr::{mean_state, plot_controller_comparison, LqrController};
+use fhn::optim::mpc::{plot_tracking, receding_horizon, save_mpc_to_csv, MpcSettings};
+use fhn::models::reference::{plot_reference_profile, reference_profile, PatternSpec, ReferencePattern};
+use fhn::models::spatial::Lattice;
+use fhn::models::delay::SynapticDelay;
+use fhn::models::schedule::{plot_schedules, ParameterSchedule, SIGMA_EXT};
+use fhn::models::moment_closure::{MomentClosure, optimize_moment_control};
+use fhn::models::population::{CouplingMatrix, ParameterDraw, ParameterOverride, Population, Subpopulation, SubpopulationSpec};
+use fhn::plotting::{ImageFormat, Palette, PlotStyle};
+use fhn::models::connectivity::{Connectivity, ConnectivitySpec};
+use fhn::simulations::initial::{InitialCondition, InitialConditionSpec};
+use fhn::simulations::control::{ControlChannel, ControlSpec};
 use std::fs::File;
 use std::io::Write;
 
 
-
-
+// Settings of the gradient descent
+const MAX_ITERS: usize = 20;
+const STEP_SIZE: f64 = 0.005;
+// Gradient step sizes for the conductance-based models, whose potentials are in mV
+const MORRIS_LECAR_STEP_SIZE: f64 = 0.005;
+const HODGKIN_HUXLEY_STEP_SIZE: f64 = 0.001;
+const LAMBDA2: f64 = 0.01;
+const GAMMA: f64 = 1.0;
+const C_T: f64 = 1.0;
+// Step size of the gradient descent on the parameters of a feedback policy
+const POLICY_STEP_SIZE: f64 = 0.01;
+// Length of the Welch segments and spectrogram windows, in time units
+const PSD_WINDOW: f64 = 100.0;
+// Step size for the desynchronization objectives, whose gradients are larger and less regular
+const DESYNC_STEP_SIZE: f64 = 0.001;
+// Frames of the animated population density
+const ANIMATION_FRAMES: usize = 60;
+// Width of the bins of the population firing rate, in time units
+const RATE_BIN: f64 = 2.0;
+// Fraction of the realizations inside the ensemble quantile bands
+const BAND_LEVEL: f64 = 0.9;
 
 
 /// CLI for the FitzHugh–Nagumo control project
@@ -21,6 +62,67 @@ use std::io::Write;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    #[command(flatten)]
+    plot: PlotArgs,
+}
+
+/// Appearance of the figures, shared by the subcommands
+#[derive(Args)]
+struct PlotArgs {
+    /// File format of the figures: png or svg (the density animation is always a GIF)
+    #[arg(long, global = true, default_value = "png")]
+    format: ImageFormat,
+    /// Width of the figures, in pixels
+    #[arg(long, global = true, default_value_t = 800)]
+    width: u32,
+    /// Height of the figures, in pixels
+    #[arg(long, global = true, default_value_t = 600)]
+    height: u32,
+    /// Font family of the text of the figures
+    #[arg(long, global = true, default_value = "sans-serif")]
+    font: String,
+    /// Font size of the titles
+    #[arg(long, global = true, default_value_t = 30)]
+    title_size: u32,
+    /// Font size of the tick labels, axis descriptions and legends
+    #[arg(long, global = true, default_value_t = 12)]
+    label_size: u32,
+    /// Width of the lines, in pixels
+    #[arg(long, global = true, default_value_t = 1)]
+    line_width: u32,
+    /// Colors of the series: default, colorblind, grayscale or a list of hex colors, e.g.
+    /// `--palette '#1f77b4,#ff7f0e'`
+    #[arg(long, global = true, default_value = "default")]
+    palette: Palette,
+    /// Leave out the titles of the figures
+    #[arg(long, global = true)]
+    no_titles: bool,
+}
+
+impl PlotArgs {
+    fn style(&self) -> PlotStyle {
+        PlotStyle {
+            format: self.format,
+            size: (self.width, self.height),
+            font: self.font.clone(),
+            title_size: self.title_size,
+            label_size: self.label_size,
+            line_width: self.line_width,
+            palette: self.palette.clone(),
+            titles: !self.no_titles,
+        }
+    }
+}
+
+/// Single-neuron model of the population
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Model {
+    /// FitzHugh–Nagumo, as in the paper
+    Fhn,
+    /// Morris–Lecar (mV, ms)
+    MorrisLecar,
+    /// Hodgkin–Huxley (mV, ms); use a small time step, e.g. --dt 0.01
+    HodgkinHuxley,
 }
 
 #[derive(Subcommand)]
@@ -36,7 +138,20 @@ enum Commands {
         /// Time step size (dt)
         #[arg(short, long, default_value_t = 0.1)]
         dt: f64,
+        /// Neuron model
+        #[arg(long, value_enum, default_value_t = Model::Fhn)]
+        model: Model,
+        /// Initial states of the neurons: fixed, gaussian:STD[,..], uniform:LOW,HIGH[,..],
+        /// csv:PATH (last step of a previous simulation), burn-in:STEPS (stationary states)
+        /// or pulse:RADIUS,AMPLITUDE (raised potential around the first neuron)
+        #[arg(long, value_name = "SPEC", default_value = "fixed")]
+        initial: InitialConditionSpec,
+        #[command(flatten)]
+        population: PopulationArgs,
+        #[command(flatten)]
+        analysis: AnalysisArgs,
     },
+    /// Optimize the control of the neuron network by adjoint-based gradient descent
     Optimize {
         #[arg(short, long, default_value_t = 100)]
         neurons: usize,
@@ -44,166 +159,1423 @@ enum Commands {
         steps: usize,
         #[arg(short, long, default_value_t = 0.1)]
         dt: f64,
+        /// Neuron model
+        #[arg(long, value_enum, default_value_t = Model::Fhn)]
+        model: Model,
+        /// Initial states of the neurons: fixed, gaussian:STD[,..], uniform:LOW,HIGH[,..],
+        /// csv:PATH (last step of a previous simulation), burn-in:STEPS (stationary states)
+        /// or pulse:RADIUS,AMPLITUDE (raised potential around the first neuron)
+        #[arg(long, value_name = "SPEC", default_value = "fixed")]
+        initial: InitialConditionSpec,
+        #[command(flatten)]
+        population: PopulationArgs,
+        /// Warm-start the control by optimizing the moment-closure model first (FHN only)
+        #[arg(long)]
+        warm_start: bool,
+        /// Number of gradient steps on the moment-closure model
+        #[arg(long, default_value_t = 200)]
+        warm_start_iters: usize,
+        #[command(flatten)]
+        control: ControlArgs,
+    },
+    /// Track the reference profile with model predictive control over a receding horizon
+    Mpc {
+        #[arg(short, long, default_value_t = 100)]
+        neurons: usize,
+        #[arg(short, long, default_value_t = 1000)]
+        steps: usize,
+        #[arg(short, long, default_value_t = 0.1)]
+        dt: f64,
+        /// Neuron model
+        #[arg(long, value_enum, default_value_t = Model::Fhn)]
+        model: Model,
+        /// Initial states of the neurons (see simulate)
+        #[arg(long, value_name = "SPEC", default_value = "fixed")]
+        initial: InitialConditionSpec,
+        #[command(flatten)]
+        population: PopulationArgs,
+        /// Length of the prediction window, in time steps
+        #[arg(long, default_value_t = 200)]
+        horizon: usize,
+        /// Number of steps of each plan applied before solving again
+        #[arg(long, default_value_t = 20)]
+        chunk: usize,
+        /// Gradient steps per window
+        #[arg(long, default_value_t = 10)]
+        iterations: usize,
+    },
+    /// Stabilize the mean-field fixed point with a linear-quadratic regulator and compare it
+    /// to the adjoint-based open-loop control
+    Lqr {
+        #[arg(short, long, default_value_t = 100)]
+        neurons: usize,
+        #[arg(short, long, default_value_t = 1000)]
+        steps: usize,
+        #[arg(short, long, default_value_t = 0.1)]
+        dt: f64,
+        /// Neuron model
+        #[arg(long, value_enum, default_value_t = Model::Fhn)]
+        model: Model,
+        /// Initial states of the neurons (see simulate)
+        #[arg(long, value_name = "SPEC", default_value = "fixed")]
+        initial: InitialConditionSpec,
+        #[command(flatten)]
+        population: PopulationArgs,
+    },
+    /// Equilibria, linear stability and nullclines of the single-neuron and mean-field FHN systems
+    Analyze {
+        /// Change a parameter of the paper, e.g. `--set Iext=0.3`; repeatable
+        #[arg(long = "set", value_name = "PARAM=VALUE")]
+        overrides: Vec<ParameterOverride>,
     },
+    /// Continuation of the mean-field equilibria in a parameter, with their saddle-node and
+    /// Hopf bifurcations
+    Bifurcation {
+        /// Neuron model
+        #[arg(long, value_enum, default_value_t = Model::Fhn)]
+        model: Model,
+        /// Continuation parameter, e.g. Iext, J or VT
+        #[arg(long, default_value = "Iext")]
+        parameter: String,
+        /// Start of the parameter interval, where the branch is found from the resting state
+        #[arg(long, allow_negative_numbers = true)]
+        from: f64,
+        /// End of the parameter interval
+        #[arg(long, allow_negative_numbers = true)]
+        to: f64,
+        /// Initial arclength step; the step grows up to ten times this value
+        #[arg(long, default_value_t = 0.01)]
+        step: f64,
+        /// Maximal number of points on the branch
+        #[arg(long, default_value_t = 5000)]
+        max_points: usize,
+        /// Change another parameter of the model, e.g. `--set J=2`; repeatable
+        #[arg(long = "set", value_name = "PARAM=VALUE")]
+        overrides: Vec<ParameterOverride>,
+    },
+}
+
+
+/// Options of the analyses of the simulated population
+#[derive(Args)]
+struct AnalysisArgs {
+    /// Potential whose upward crossing is a spike (default: 0.5 for FHN, 0 mV for the
+    /// conductance-based models)
+    #[arg(long, allow_negative_numbers = true)]
+    spike_threshold: Option<f64>,
+    /// Potential below which the detector is rearmed after a spike (default: -0.5 for FHN,
+    /// -20 mV for the conductance-based models)
+    #[arg(long, allow_negative_numbers = true)]
+    spike_reset: Option<f64>,
+    /// Width of the bins of the population firing rate, in time units
+    #[arg(long, default_value_t = RATE_BIN)]
+    rate_bin: f64,
+    /// Largest number of neurons shown in the raster plot; larger populations are subsampled
+    #[arg(long, default_value_t = 200)]
+    raster_neurons: usize,
+    /// Length of the Welch segments and spectrogram windows of the LFP spectrum, in time units
+    #[arg(long, default_value_t = PSD_WINDOW)]
+    psd_window: f64,
+    /// Times of the snapshots of the population density in the phase plane, e.g.
+    /// `--snapshots 0,50,100` (default: four evenly spaced times)
+    #[arg(long, value_delimiter = ',')]
+    snapshots: Vec<f64>,
+    /// Frames of the animated population density (figures/density.gif, whatever the --format);
+    /// 0 disables it
+    #[arg(long, default_value_t = ANIMATION_FRAMES)]
+    frames: usize,
+}
+
+/// Analyses of a simulated population: the spike detector of the model, the command-line
+/// options and the curves drawn under the phase-plane trajectories
+struct SimulationAnalysis<'a> {
+    detector: SpikeDetector,
+    args: &'a AnalysisArgs,
+    nullclines: Vec<Curve>,
+}
+
+
+/// Options describing the population, shared by the subcommands
+#[derive(Args)]
+struct PopulationArgs {
+    /// Draw a model parameter independently for each neuron, e.g. `--draw Iext=normal:0.5,0.05`
+    /// (laws: normal:mean,std, uniform:low,high, lognormal:mu,sigma); repeatable
+    #[arg(long = "draw", value_name = "NAME=LAW:ARGS")]
+    draws: Vec<ParameterDraw>,
+    /// Synaptic network: mean-field (all-to-all), erdos-renyi:P, small-world:K,BETA,
+    /// scale-free:M, edges:PATH (lines `pre post [weight]`), or a distance kernel on a ring or
+    /// square grid, ring:KERNEL / grid:KERNEL (gaussian:SIGMA, mexican-hat:SIGMA_E,SIGMA_I,RATIO)
+    #[arg(long, value_name = "SPEC", default_value = "mean-field")]
+    network: ConnectivitySpec,
+    /// Split the network into populations coupled through their mean fields, e.g.
+    /// `--population E:80 --population I:20:Vrev=-2.0`; repeatable, replaces --neurons and --network
+    #[arg(long = "population", value_name = "NAME:SIZE[:PARAM=VALUE,..]")]
+    populations: Vec<SubpopulationSpec>,
+    /// Coupling strengths J_kl between populations, row k (postsynaptic) by row, e.g. `0.5,0.8;0.5,0.8`
+    /// (defaults to the J of each postsynaptic population)
+    #[arg(long, value_name = "J_11,J_12;J_21,J_22")]
+    coupling: Option<CouplingMatrix>,
+    /// Synaptic delay: none, fixed:TAU, uniform:LOW,HIGH or gamma:MEAN,SHAPE (in units of time)
+    #[arg(long, value_name = "SPEC", default_value = "none")]
+    delay: SynapticDelay,
+    /// Let a parameter vary in time, e.g. `--schedule Iext=linear:0:0.3,50:0.8`
+    /// (linear:T:V,.. ramps, steps:T:V,.. piecewise constant, csv:PATH with columns time and NAME);
+    /// NAME is a model parameter or sigma_ext; repeatable
+    #[arg(long = "schedule", value_name = "NAME=SPEC")]
+    schedules: Vec<ParameterSchedule>,
+}
+
+
+// Options describing what is controlled and how, for the optimizer
+#[derive(Args)]
+struct ControlArgs {
+    /// Track a spatio-temporal pattern v*_i(t) instead of the mean potential:
+    /// wave:SPEED,WIDTH (bump travelling along the lattice) or csv:PATH (columns neuron_id, v)
+    #[arg(long, value_name = "SPEC")]
+    target_pattern: Option<PatternSpec>,
+    /// Control channel COMPONENT[@TARGET], e.g. `v@E`, `w`, `y@0..50`, `v@bump:CENTER,WIDTH` or
+    /// `v@gains:PATH` (one channel per column of a CSV with one row per neuron); repeatable.
+    /// Explicit channels start from zero; by default a single control acts on v of all neurons
+    #[arg(long = "control", value_name = "SPEC")]
+    controls: Vec<ControlSpec>,
+    /// Learn a feedback policy α = π(v̄) of the observed mean potential instead of an open-loop
+    /// control: linear or mlp:HIDDEN (one hidden layer of tanh units)
+    #[arg(long, value_name = "POLICY")]
+    feedback: Option<PolicySpec>,
+    /// Number of fresh noise realizations on which the optimized control or feedback policy is
+    /// evaluated
+    #[arg(long, default_value_t = 10)]
+    evaluations: usize,
+    /// Desynchronize the population instead of tracking a potential, by penalizing the variance
+    /// in time of the mean potential (variance) or the squared Kuramoto order parameter of the
+    /// geometric phases (kuramoto)
+    #[arg(long, value_name = "OBJECTIVE", conflicts_with_all = ["target_pattern", "feedback"])]
+    desynchronize: Option<DesyncSpec>,
+}
+
+
+/// Initial condition used for the simulations in the paper
+fn paper_initial_state() -> NeuronState {
+    NeuronState {
+        v: -0.8275021695916729,
+        w: 0.1391607698173808,
+        y: 0.589165868968053
+    }
+}
+
+/// Simulation parameters used for the simulations in the paper
+fn paper_parameters() -> FhnParameters {
+    FhnParameters {
+        a: 0.7,
+        b: 0.8,
+        c: 0.08,
+        // excitatory synapses since rev. potential higher then rest. potential:
+        Vrev: 1.2,
+        // fast excitatory conductance i.e. fast activation and deactivation:
+        ar: 1.0,
+        ad: 0.3,
+        Tmax: 1.0,
+        lambda: 0.1,
+        // threshold for presynaptic neuron for opening of synaptic gates to postsynaptic neuron:
+        VT: 2.0,
+        // Coupling strength:
+        J: 0.46,
+        Iext: 0.5,
+    }
+}
+
+// External noise intensity of the paper; the conductance-based models receive a current noise
+const FHN_SIGMA_EXT: f64 = 0.04;
+const CONDUCTANCE_SIGMA_EXT: f64 = 1.0;
+
+
+/// Open-loop descent for one model: the state whose potential the population should hold,
+/// the gradient step size and the control the descent starts from
+struct Descent<S> {
+    target: S,
+    step_size: f64,
+    control: Vec<f64>,
 }
 
+
 fn main() {
     let cli = Cli::parse();
+    let style = &cli.plot.style();
 
     match &cli.command {
         Commands::Simulate {
             neurons,
             steps,
             dt,
+            model,
+            initial,
+            population: population_args,
+            analysis: analysis_args,
         } => {
-            println!("Running simulation with L = {neurons}, M = {steps}, dt = {dt}");
-            
-            // Initial condition used for the simulations in the paper
-            let initial = NeuronState {
-                v: -0.8275021695916729,
-                w: 0.1391607698173808,
-                y: 0.589165868968053
-            };
-            
-            // Simulation parameters used for the simulations in the paper
-            let params = FhnParameters {
-                a: 0.7,
-                b: 0.8,
-                c: 0.08,
-                // excitatory synapses since rev. potential higher then rest. potential:
-                Vrev: 1.2,
-                // fast excitatory conductance i.e. fast activation and deactivation:
-                ar: 1.0,
-                ad: 0.3,
-                Tmax: 1.0,
-                lambda: 0.1,
-                // threshold for presynaptic neuron for opening of synaptic gates to postsynaptic neuron:
-                VT: 2.0,
-                // Coupling strength:
-                J: 0.46,
-                Iext: 0.5,
-            };
-
-            let sigma_ext = 0.04;
-
-            let sim = simulate_fhn_population(*neurons, *steps, *dt, &params, sigma_ext, initial);
-            // Printing some values
-            println!("First neuron's v(t):");
-            for (i, state) in sim[0].iter().enumerate().step_by(steps / 10) {
-                println!("t = {:.1}, v = {:.3}", i as f64 * dt, state.v);
+            let detector = spike_detector(*model, analysis_args);
+
+            match model {
+                Model::Fhn => {
+                    let params = paper_parameters();
+                    let population = build_population(&params, *neurons, population_args);
+                    let initial = build_initial_condition(initial, &population, paper_initial_state(), *dt, FHN_SIGMA_EXT);
+                    let nullclines = nullcline_curves(&params, System::MeanField, (-2.5, 2.5), 500);
+                    let analysis = SimulationAnalysis { detector, args: analysis_args, nullclines };
+                    run_simulation(&population, &initial, FHN_SIGMA_EXT, *steps, *dt, &analysis, style)
+                }
+                Model::MorrisLecar => {
+                    let params = MorrisLecarParameters::default();
+                    let population = build_population(&params, *neurons, population_args);
+                    let initial = build_initial_condition(initial, &population, params.resting_state(), *dt, CONDUCTANCE_SIGMA_EXT);
+                    let analysis = SimulationAnalysis { detector, args: analysis_args, nullclines: Vec::new() };
+                    run_simulation(&population, &initial, CONDUCTANCE_SIGMA_EXT, *steps, *dt, &analysis, style)
+                }
+                Model::HodgkinHuxley => {
+                    let params = HodgkinHuxleyParameters::default();
+                    let population = build_population(&params, *neurons, population_args);
+                    let initial = build_initial_condition(initial, &population, params.resting_state(), *dt, CONDUCTANCE_SIGMA_EXT);
+                    let analysis = SimulationAnalysis { detector, args: analysis_args, nullclines: Vec::new() };
+                    run_simulation(&population, &initial, CONDUCTANCE_SIGMA_EXT, *steps, *dt, &analysis, style)
+                }
             }
-            // Saving to csv
-            match fhn::simulations::forward::save_simulation_to_csv(&sim, *dt, "output/simulation.csv") {
-                Ok(_) => println!("✅ Saved to output/simulation.csv"),
-                Err(e) => eprintln!("❌ Failed to save CSV: {}", e),
+        }
+        Commands::Optimize { neurons, steps, dt, model, initial, population: population_args, warm_start, warm_start_iters, control: control_args } => {
+            let detector = default_spike_detector(*model);
+
+            if *warm_start && *model != Model::Fhn {
+                eprintln!("⚠️ The moment-closure warm start is only available for the FHN model, ignoring it");
             }
-            // Plotting the average value of the potential
-            match fhn::simulations::forward::plot_local_field_potential(&sim, *dt, "figures/lfp.png") {
-                Ok(_) => println!("✅ Plot saved to figures/lfp.png"),
-                Err(e) => eprintln!("❌ Plotting error: {}", e),
+
+            match model {
+                Model::Fhn => {
+                    let params = paper_parameters();
+                    let mut control = vec![0.5; *steps];
+
+                    // Cheap initial control from the reduced (moment-closure) model
+                    if *warm_start && (!control_args.controls.is_empty() || control_args.feedback.is_some()) {
+                        println!("Note: the moment-closure warm start only applies to the default open-loop control, ignoring it");
+                    } else if *warm_start {
+                        if !population_args.draws.is_empty() || !population_args.populations.is_empty() {
+                            println!("Note: the moment-closure warm start uses the nominal parameters of the population");
+                        }
+                        if !matches!(population_args.delay, SynapticDelay::None) || !population_args.schedules.is_empty() {
+                            println!("Note: the moment-closure warm start ignores the synaptic delays and schedules");
+                        }
+                        let closure = MomentClosure::new(params, FHN_SIGMA_EXT);
+                        let (warm_control, warm_costs) = optimize_moment_control(
+                            &closure, paper_initial_state(), &control, *dt, NeuronState::default(), GAMMA, LAMBDA2, C_T, STEP_SIZE, *warm_start_iters,
+                        );
+                        if let (Some(first), Some(last)) = (warm_costs.first(), warm_costs.last()) {
+                            println!("Warm start: reduced cost {:.6} -> {:.6}", first, last);
+                        }
+                        control = warm_control;
+                    }
+
+                    let population = build_population(&params, *neurons, population_args);
+                    let initial = build_initial_condition(initial, &population, paper_initial_state(), *dt, FHN_SIGMA_EXT);
+                    let descent = Descent { target: NeuronState::default(), step_size: STEP_SIZE, control };
+                    run_optimization(&population, &initial, FHN_SIGMA_EXT, *steps, *dt, descent, control_args, &detector, style)
+                }
+                Model::MorrisLecar => {
+                    let params = MorrisLecarParameters::default();
+                    let population = build_population(&params, *neurons, population_args);
+                    let initial = build_initial_condition(initial, &population, params.resting_state(), *dt, CONDUCTANCE_SIGMA_EXT);
+                    // Hold the population at the resting potential of an uncoupled neuron without external
+                    // current, starting from the control (in mV/ms) that cancels that current
+                    let descent = Descent { target: params.resting_state(), step_size: MORRIS_LECAR_STEP_SIZE, control: vec![-params.Iext / params.C; *steps] };
+                    run_optimization(&population, &initial, CONDUCTANCE_SIGMA_EXT, *steps, *dt, descent, control_args, &detector, style)
+                }
+                Model::HodgkinHuxley => {
+                    let params = HodgkinHuxleyParameters::default();
+                    let population = build_population(&params, *neurons, population_args);
+                    let initial = build_initial_condition(initial, &population, params.resting_state(), *dt, CONDUCTANCE_SIGMA_EXT);
+                    // Hold the population at the resting potential of an uncoupled neuron without external
+                    // current, starting from the control (in mV/ms) that cancels that current
+                    let descent = Descent { target: params.resting_state(), step_size: HODGKIN_HUXLEY_STEP_SIZE, control: vec![-params.Iext / params.C; *steps] };
+                    run_optimization(&population, &initial, CONDUCTANCE_SIGMA_EXT, *steps, *dt, descent, control_args, &detector, style)
+                }
             }
-            // Plotting individual trajectories
-            match fhn::simulations::forward::plot_individual_neurons(&sim, *dt, "figures/neurons.png", 5) {
-                Ok(_) => println!("✅ Individual neuron plot saved to figures/neurons.png"),
-                Err(e) => eprintln!("❌ Neuron plot error: {}", e),
+        }
+        Commands::Mpc { neurons, steps, dt, model, initial, population: population_args, horizon, chunk, iterations } => {
+            let settings = mpc_settings(*horizon, *chunk, *iterations);
+
+            match model {
+                Model::Fhn => {
+                    let population = build_population(&paper_parameters(), *neurons, population_args);
+                    let initial = build_initial_condition(initial, &population, paper_initial_state(), *dt, FHN_SIGMA_EXT);
+                    run_mpc(&population, &initial, FHN_SIGMA_EXT, *steps, *dt, &settings, style)
+                }
+                Model::MorrisLecar => {
+                    let params = MorrisLecarParameters::default();
+                    let population = build_population(&params, *neurons, population_args);
+                    let initial = build_initial_condition(initial, &population, params.resting_state(), *dt, CONDUCTANCE_SIGMA_EXT);
+                    run_mpc(&population, &initial, CONDUCTANCE_SIGMA_EXT, *steps, *dt, &settings, style)
+                }
+                Model::HodgkinHuxley => {
+                    let params = HodgkinHuxleyParameters::default();
+                    let population = build_population(&params, *neurons, population_args);
+                    let initial = build_initial_condition(initial, &population, params.resting_state(), *dt, CONDUCTANCE_SIGMA_EXT);
+                    run_mpc(&population, &initial, CONDUCTANCE_SIGMA_EXT, *steps, *dt, &settings, style)
+                }
             }
         }
-        Commands::Optimize { neurons, steps, dt } => {
-            let initial = NeuronState {
-                v: -0.8275021695916729,
-                w: 0.1391607698173808,
-                y: 0.589165868968053
-            };
-            let params = FhnParameters {
-                a: 0.7,
-                b: 0.8,
-                c: 0.08,
-                Vrev: 1.2,
-                ar: 1.0,
-                ad: 0.3,
-                Tmax: 1.0,
-                lambda: 0.1,
-                VT: 2.0,
-                J: 0.46,
-                Iext: 0.5,
-            };
-            let sigma_ext = 0.04;
-
-            let mut control = vec![0.5; *steps];
-            let mut cost_trace = Vec::new();
-            let max_iters = 20;
-            let step_size = 0.005;
-            let lambda2 = 0.01;
-            let gamma = 1.0;
-            let c_t = 1.0;
-            let y_target = [0.0, 0.0, 0.0];
-            
-            // Introduce adjoint profile
-            let mut last_adj = vec![];
-
-            // Optimization loop
-            for iter in 0..max_iters {
-                let sim = simulate_fhn_population(*neurons, *steps, *dt, &params, sigma_ext, initial);
-                let cost = evaluate_cost(&sim, &control, y_target, gamma, lambda2, c_t, *dt);
-                cost_trace.push(cost);
-
-                let adj = compute_adjoint(&sim, &params, y_target, gamma, c_t, *dt);
-                last_adj = adj.clone(); // save last adjoint for plotting later
-
-                let grad = compute_control_gradient(&adj, &control, lambda2, *dt);
-                control = gradient_step(&control, &grad, step_size);
-
-                println!("Iter {:>2}: J(α) = {:.6}", iter, cost);
+        Commands::Lqr { neurons, steps, dt, model, initial, population: population_args } => {
+            if !population_args.draws.is_empty() || !population_args.populations.is_empty() {
+                println!("Note: the regulator is designed on the mean field of the nominal parameters");
             }
 
-            // save optimal control to csv
-            let mut file = File::create("output/control.csv").expect("Failed to create control.csv");
-            writeln!(file, "t,alpha").unwrap();
-            for (i, alpha) in control.iter().enumerate() {
-                writeln!(file, "{:.4},{}", i as f64 * dt, alpha).unwrap();
+            match model {
+                Model::Fhn => {
+                    let params = paper_parameters();
+                    let population = build_population(&params, *neurons, population_args);
+                    let initial = build_initial_condition(initial, &population, paper_initial_state(), *dt, FHN_SIGMA_EXT);
+                    run_lqr(&params, paper_initial_state(), &population, &initial, FHN_SIGMA_EXT, *steps, *dt, style)
+                }
+                Model::MorrisLecar => {
+                    let params = MorrisLecarParameters::default();
+                    let population = build_population(&params, *neurons, population_args);
+                    let initial = build_initial_condition(initial, &population, params.resting_state(), *dt, CONDUCTANCE_SIGMA_EXT);
+                    run_lqr(&params, params.resting_state(), &population, &initial, CONDUCTANCE_SIGMA_EXT, *steps, *dt, style)
+                }
+                Model::HodgkinHuxley => {
+                    let params = HodgkinHuxleyParameters::default();
+                    let population = build_population(&params, *neurons, population_args);
+                    let initial = build_initial_condition(initial, &population, params.resting_state(), *dt, CONDUCTANCE_SIGMA_EXT);
+                    run_lqr(&params, params.resting_state(), &population, &initial, CONDUCTANCE_SIGMA_EXT, *steps, *dt, style)
+                }
             }
-            println!("✅ Saved final control to output/control.csv");
+        }
+        Commands::Analyze { overrides } => {
+            run_analysis(&with_overrides(paper_parameters(), overrides), style)
+        }
+        Commands::Bifurcation { model, parameter, from, to, step, max_points, overrides } => {
+            println!("Continuing the mean-field equilibria in {parameter} from {from} to {to}");
+            let settings = ContinuationSettings { from: *from, to: *to, step: *step, max_step: 10.0 * step, max_points: *max_points };
+
+            match model {
+                Model::Fhn => {
+                    let params = with_overrides(paper_parameters(), overrides);
+                    run_bifurcation(&params, paper_initial_state(), parameter, &settings, style)
+                }
+                Model::MorrisLecar => {
+                    let params = with_overrides(MorrisLecarParameters::default(), overrides);
+                    run_bifurcation(&params, params.resting_state(), parameter, &settings, style)
+                }
+                Model::HodgkinHuxley => {
+                    let params = with_overrides(HodgkinHuxleyParameters::default(), overrides);
+                    run_bifurcation(&params, params.resting_state(), parameter, &settings, style)
+                }
+            }
+        }
+    }
+}
+
 
+/// Model with the parameters given on the command line changed
+fn with_overrides<N: NeuronModel>(mut params: N, overrides: &[ParameterOverride]) -> N {
+    for o in overrides {
+        if let Err(e) = params.set_parameter(&o.name, o.value) {
+            eprintln!("❌ Invalid parameter: {}", e);
+            std::process::exit(1);
+        }
+    }
+    params
+}
+
+
+/// Default spike detector of the model
+fn default_spike_detector(model: Model) -> SpikeDetector {
+    match model {
+        Model::Fhn => SpikeDetector { threshold: 0.5, reset: -0.5 },
+        Model::MorrisLecar | Model::HodgkinHuxley => SpikeDetector { threshold: 0.0, reset: -20.0 },
+    }
+}
 
-            // save cost to csv
-            let mut file = File::create("output/cost.csv").expect("Failed to create cost.csv");
-            writeln!(file, "iter,cost").unwrap();
-            for (i, j) in cost_trace.iter().enumerate() {
-                writeln!(file, "{},{}", i, j).unwrap();
+
+/// Spike detector of the model, with the thresholds given on the command line
+fn spike_detector(model: Model, args: &AnalysisArgs) -> SpikeDetector {
+    let default = default_spike_detector(model);
+    let detector = SpikeDetector {
+        threshold: args.spike_threshold.unwrap_or(default.threshold),
+        reset: args.spike_reset.unwrap_or(default.reset),
+    };
+    if detector.reset >= detector.threshold {
+        eprintln!("❌ Invalid spike detector: the reset ({}) must lie below the threshold ({})", detector.reset, detector.threshold);
+        std::process::exit(1);
+    }
+    if args.rate_bin.is_nan() || args.rate_bin <= 0.0 {
+        eprintln!("❌ Invalid rate bin: the width of the bins ({}) must be positive", args.rate_bin);
+        std::process::exit(1);
+    }
+    detector
+}
+
+
+/// Settings of the receding-horizon controller, with the cost weights of the optimizer
+fn mpc_settings(horizon: usize, chunk: usize, iterations: usize) -> MpcSettings {
+    MpcSettings { horizon, chunk, iterations, step_size: STEP_SIZE, gamma: GAMMA, lambda2: LAMBDA2, c_t: C_T }
+}
+
+
+/// Population of L neurons with nominal parameters `params`, some of them redrawn per neuron,
+/// coupled through the given network, or made of several populations coupled through their mean fields,
+/// with delayed synapses and scheduled parameters
+fn build_population<N: NeuronModel>(params: &N, neurons: usize, args: &PopulationArgs) -> Population<N> {
+    let PopulationArgs { draws, network, populations, coupling, delay, schedules } = args;
+    let mut rng = rand::thread_rng();
+    let mut population = if populations.is_empty() {
+        let population = Population::homogeneous(params, neurons);
+        match network.build(neurons, &mut rng) {
+            Ok(connectivity) => population.with_connectivity(connectivity),
+            Err(e) => {
+                eprintln!("❌ Invalid network: {}", e);
+                std::process::exit(1);
             }
-            println!("✅ Saved cost trace to output/cost.csv");
-            
-            // Plot control
-            match plot_control(&control, *dt, "figures/control.png") {
-                Ok(_) => println!("✅ Control plot saved to figures/control.png"),
-                Err(e) => eprintln!("❌ Failed to plot control: {}", e),
+        }
+    } else {
+        if !matches!(network, ConnectivitySpec::MeanField) {
+            eprintln!("⚠️ Populations are coupled through their mean fields, ignoring --network");
+        }
+        let subpopulations = populations
+            .iter()
+            .map(|spec| spec.build(params))
+            .collect::<Result<Vec<Subpopulation<N>>, String>>()
+            .and_then(|subpopulations| {
+                Population::from_subpopulations(&subpopulations, coupling.as_ref().map(|c| c.0.clone()))
+            });
+        match subpopulations {
+            Ok(population) => population,
+            Err(e) => {
+                eprintln!("❌ Invalid populations: {}", e);
+                std::process::exit(1);
             }
-
-            // Plot cost
-            match plot_cost_trace(&cost_trace, "figures/cost.png") {
-                Ok(_) => println!("✅ Cost plot saved to figures/cost.png"),
-                Err(e) => eprintln!("❌ Failed to plot cost: {}", e),
+        }
+    };
+    if let Err(e) = population.redraw(draws, &mut rng) {
+        eprintln!("❌ Invalid parameter draw: {}", e);
+        std::process::exit(1);
+    }
+    let population = match population.with_delay(*delay).with_schedules(schedules.clone()) {
+        Ok(population) => population,
+        Err(e) => {
+            eprintln!("❌ Invalid schedule: {}", e);
+            std::process::exit(1);
+        }
+    };
+    match &population.connectivity {
+        Connectivity::Sparse(weights) => {
+            println!("Network with {} synapses", weights.nnz());
+            match population.connectivity.save_edges_to_csv(neurons, "output/network.csv") {
+                Ok(_) => println!("✅ Saved network to output/network.csv"),
+                Err(e) => eprintln!("❌ Failed to save network: {}", e),
+            }
+        }
+        Connectivity::Populations(blocks) => {
+            println!("{} populations, L = {} neurons in total", blocks.len(), population.len());
+            for (l, name) in blocks.names.iter().enumerate() {
+                println!("Population {}: {} neurons, Vrev = {}, J = {:?}", name, blocks.range(l).len(), blocks.reversal[l], blocks.weights[l]);
             }
+        }
+        Connectivity::Kernel(kernel) => println!("Neurons on a {:?} lattice with a {:?} kernel", kernel.lattice, kernel.shape),
+        Connectivity::MeanField => {}
+    }
+    if !draws.is_empty() || !populations.is_empty() {
+        match population.save_to_csv("output/parameters.csv") {
+            Ok(_) => println!("✅ Saved neuron parameters to output/parameters.csv"),
+            Err(e) => eprintln!("❌ Failed to save parameters: {}", e),
+        }
+    }
+    population
+}
+
+
+/// Mean state of each population over time, when the network has several populations
+fn save_population_outputs<N: NeuronModel>(population: &Population<N>, sim: &[Vec<N::State>], dt: f64, style: &PlotStyle) {
+    if let Connectivity::Populations(blocks) = &population.connectivity {
+        match save_population_means_to_csv(sim, blocks, dt, "output/populations.csv") {
+            Ok(_) => println!("✅ Saved population means to output/populations.csv"),
+            Err(e) => eprintln!("❌ Failed to save population means: {}", e),
+        }
+        let filename = style.path("figures", "populations");
+        match plot_population_potentials(sim, blocks, dt, style, &filename) {
+            Ok(_) => println!("✅ Population plot saved to {}", filename),
+            Err(e) => eprintln!("❌ Population plot error: {}", e),
+        }
+    }
+}
+
 
-            // Plot adjoint
-            match plot_adjoint_trajectories(&last_adj, *dt, "figures/adjoint.png", 5) {
-                Ok(_) => println!("✅ Adjoint plot saved to figures/adjoint.png"),
-                Err(e) => eprintln!("❌ Failed to plot adjoint: {}", e),
+/// Time profiles of the scheduled parameters, if any
+fn plot_population_schedules<N: NeuronModel>(population: &Population<N>, steps: usize, dt: f64, style: &PlotStyle) {
+    if population.schedules.is_empty() {
+        return;
+    }
+    for schedule in &population.schedules {
+        let kind = if schedule.name == SIGMA_EXT { "noise" } else { "parameter" };
+        println!("Scheduled {} {}: {} breakpoints", kind, schedule.name, schedule.profile.times.len());
+    }
+    let filename = style.path("figures", "schedules");
+    match plot_schedules(&population.schedules, steps as f64 * dt, dt, style, &filename) {
+        Ok(_) => println!("✅ Schedule plot saved to {}", filename),
+        Err(e) => eprintln!("❌ Failed to plot schedules: {}", e),
+    }
+}
+
+
+/// Space-time plot of the potential, and its final snapshot on a grid
+fn save_spatial_outputs<N: NeuronModel>(population: &Population<N>, sim: &[Vec<N::State>], dt: f64, style: &PlotStyle) {
+    let values = potentials(sim);
+    let filename = style.path("figures", "space_time");
+    match plot_space_time(&values, dt, "Potential v(x, t)", style, &filename) {
+        Ok(_) => println!("✅ Space-time plot saved to {}", filename),
+        Err(e) => eprintln!("❌ Space-time plot error: {}", e),
+    }
+    let lattice = population.lattice();
+    if let Lattice::Grid(_, _) = lattice {
+        let last: Vec<f64> = values.iter().map(|v| v[v.len() - 1]).collect();
+        let filename = style.path("figures", "field");
+        match plot_field_snapshot(&last, &lattice, "Potential at final time", style, &filename) {
+            Ok(_) => println!("✅ Field snapshot saved to {}", filename),
+            Err(e) => eprintln!("❌ Field snapshot error: {}", e),
+        }
+    }
+}
+
+
+/// Control channels of the population, exiting on an invalid specification
+fn build_control_channels<N: NeuronModel>(specs: &[ControlSpec], population: &Population<N>) -> Vec<ControlChannel> {
+    let mut channels = Vec::new();
+    for spec in specs {
+        match spec.build(population) {
+            Ok(built) => channels.extend(built),
+            Err(e) => {
+                eprintln!("❌ Invalid control: {}", e);
+                std::process::exit(1);
             }
+        }
+    }
+    channels
+}
+
 
-            // Plot reference profile
-            match plot_reference_profile(*dt * *steps as f64, *dt, "figures/reference.png") {
-                Ok(_) => println!("✅ Reference profile plot saved to figures/reference.png"),
-                Err(e) => eprintln!("❌ Failed to plot reference profile: {}", e),
+/// Initial condition of the population; `default` is the model's reference initial state
+fn build_initial_condition<N: NeuronModel>(
+    spec: &InitialConditionSpec,
+    population: &Population<N>,
+    default: N::State,
+    dt: f64,
+    sigma_ext: f64,
+) -> InitialCondition<N::State> {
+    match spec.build(population, default, dt, sigma_ext) {
+        Ok(initial) => initial,
+        Err(e) => {
+            eprintln!("❌ Invalid initial condition: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+
+fn run_simulation<N: NeuronModel>(
+    population: &Population<N>,
+    initial: &InitialCondition<N::State>,
+    sigma_ext: f64,
+    steps: usize,
+    dt: f64,
+    analysis: &SimulationAnalysis,
+    style: &PlotStyle,
+) {
+    println!("Running simulation with L = {}, M = {}, dt = {}", population.len(), steps, dt);
+    plot_population_schedules(population, steps, dt, style);
+    let sim = simulate_population(population, steps, dt, sigma_ext, initial);
+    // Printing some values
+    println!("First neuron's v(t):");
+    for (i, state) in sim[0].iter().enumerate().step_by((steps / 10).max(1)) {
+        println!("t = {:.1}, v = {:.3}", i as f64 * dt, state.potential());
+    }
+    // Saving to csv
+    match save_simulation_to_csv(&sim, dt, "output/simulation.csv") {
+        Ok(_) => println!("✅ Saved to output/simulation.csv"),
+        Err(e) => eprintln!("❌ Failed to save CSV: {}", e),
+    }
+    // Plotting the average value of the potential
+    let filename = style.path("figures", "lfp");
+    match plot_local_field_potential(&sim, dt, style, &filename) {
+        Ok(_) => println!("✅ Plot saved to {}", filename),
+        Err(e) => eprintln!("❌ Plotting error: {}", e),
+    }
+    // Plotting individual trajectories
+    let filename = style.path("figures", "neurons");
+    match plot_individual_neurons(&sim, dt, style, &filename, 5) {
+        Ok(_) => println!("✅ Individual neuron plot saved to {}", filename),
+        Err(e) => eprintln!("❌ Neuron plot error: {}", e),
+    }
+    save_population_outputs(population, &sim, dt, style);
+    if let Connectivity::Kernel(_) = &population.connectivity {
+        save_spatial_outputs(population, &sim, dt, style);
+    }
+    save_spike_outputs(&sim, dt, &analysis.detector, analysis.args, style);
+    save_spectral_outputs(&[("simulated".to_string(), local_field_potential(&sim))], dt, analysis.args.psd_window, style);
+    save_phase_plane_outputs(&sim, dt, &analysis.args.snapshots, &analysis.nullclines, "", style);
+    save_density_animation(&sim, &[("mean potential".to_string(), local_field_potential(&sim))], dt, analysis.args.frames, "", style);
+}
+
+
+/// Spike times, firing statistics, population rate, raster and interspike intervals of a simulation
+fn save_spike_outputs<S: ModelState>(sim: &[Vec<S>], dt: f64, detector: &SpikeDetector, args: &AnalysisArgs, style: &PlotStyle) {
+    let rate_bin = args.rate_bin;
+    let duration = sim[0].len() as f64 * dt;
+    let trains = detect_spikes(sim, dt, detector);
+    let statistics = firing_statistics(&trains, duration);
+    let isis: Vec<f64> = trains.iter().flat_map(|times| interspike_intervals(times)).collect();
+    let spikes: usize = statistics.iter().map(|s| s.spikes).sum();
+    let mean_rate = statistics.iter().map(|s| s.rate).sum::<f64>() / statistics.len() as f64;
+    println!("Detected {} spikes, mean rate {:.4} per neuron per unit time", spikes, mean_rate);
+
+    match save_spike_times_to_csv(&trains, "output/spikes.csv") {
+        Ok(_) => println!("✅ Saved spike times to output/spikes.csv"),
+        Err(e) => eprintln!("❌ Failed to save spike times: {}", e),
+    }
+    match save_firing_statistics_to_csv(&statistics, "output/firing.csv") {
+        Ok(_) => println!("✅ Saved firing statistics to output/firing.csv"),
+        Err(e) => eprintln!("❌ Failed to save firing statistics: {}", e),
+    }
+    let rate = population_rate(&trains, duration, rate_bin);
+    match save_population_rate_to_csv(&rate, rate_bin, "output/rate.csv") {
+        Ok(_) => println!("✅ Saved population rate to output/rate.csv"),
+        Err(e) => eprintln!("❌ Failed to save population rate: {}", e),
+    }
+    let filename = style.path("figures", "rate");
+    match plot_population_rate(&rate, rate_bin, style, &filename) {
+        Ok(_) => println!("✅ Rate plot saved to {}", filename),
+        Err(e) => eprintln!("❌ Rate plot error: {}", e),
+    }
+    let filename = style.path("figures", "raster");
+    match plot_raster(&trains, &rate, rate_bin, duration, args.raster_neurons, style, &filename) {
+        Ok(_) => println!("✅ Raster plot saved to {}", filename),
+        Err(e) => eprintln!("❌ Raster plot error: {}", e),
+    }
+    save_synchrony_outputs(sim, &trains, dt, rate_bin, style);
+    if isis.is_empty() {
+        println!("Note: no neuron fired twice, no interspike interval distribution");
+        return;
+    }
+    let filename = style.path("figures", "isi");
+    match plot_isi_distribution(&isis, 30, style, &filename) {
+        Ok(_) => println!("✅ ISI distribution saved to {}", filename),
+        Err(e) => eprintln!("❌ ISI plot error: {}", e),
+    }
+}
+
+
+/// Trajectories of a few neurons in the phase plane over the given curves, and snapshots of
+/// the population density at the given times (four evenly spaced ones when empty); `suffix`
+/// distinguishes the figures of several runs
+fn save_phase_plane_outputs<S: ModelState>(sim: &[Vec<S>], dt: f64, times: &[f64], curves: &[Curve], suffix: &str, style: &PlotStyle) {
+    let last = sim[0].len() - 1;
+    let snapshots: Vec<usize> = if times.is_empty() {
+        (0..4).map(|k| k * last / 3).collect()
+    } else {
+        times.iter().map(|t| ((t / dt).round().max(0.0) as usize).min(last)).collect()
+    };
+
+    let trajectories = style.path("figures", &format!("trajectories{}", suffix));
+    match plot_phase_trajectories(sim, 5, curves, style, &trajectories) {
+        Ok(_) => println!("✅ Phase-plane trajectories saved to {}", trajectories),
+        Err(e) => eprintln!("❌ Phase-plane plot error: {}", e),
+    }
+    let density = style.path("figures", &format!("density{}", suffix));
+    match plot_density_snapshots(sim, &snapshots, 40, dt, style, &density) {
+        Ok(_) => println!("✅ Population density snapshots saved to {}", density),
+        Err(e) => eprintln!("❌ Density plot error: {}", e),
+    }
+}
+
+
+/// Animated population density alongside the given signals, to figures/density{suffix}.gif;
+/// nothing is drawn without frames
+fn save_density_animation<S: ModelState>(sim: &[Vec<S>], signals: &[(String, Vec<f64>)], dt: f64, frames: usize, suffix: &str, style: &PlotStyle) {
+    if frames == 0 {
+        return;
+    }
+    let filename = format!("figures/density{}.gif", suffix);
+    match animate_density(sim, signals, dt, frames, 40, style, &filename) {
+        Ok(_) => println!("✅ Density animation saved to {}", filename),
+        Err(e) => eprintln!("❌ Density animation error: {}", e),
+    }
+}
+
+
+/// Statistics over noise realizations of the mean potential and population rate at each time,
+/// and of the cost and mean firing rate, with the potential plotted against `references`
+fn save_ensemble_outputs(
+    potentials: &[Vec<f64>],
+    rates: &[Vec<f64>],
+    costs: &[f64],
+    firing: &[f64],
+    dt: f64,
+    references: &[(&str, &[f64])],
+    style: &PlotStyle,
+) {
+    let summary = [
+        ("cost".to_string(), summarize(costs, BAND_LEVEL)),
+        ("firing rate".to_string(), summarize(firing, BAND_LEVEL)),
+    ];
+    for (name, s) in &summary {
+        println!(
+            "{} over {} realizations: {:.6} ± {:.6} ({:.0}% band [{:.6}, {:.6}])",
+            name, costs.len(), s.mean, s.std, 100.0 * BAND_LEVEL, s.lower, s.upper,
+        );
+    }
+    match save_ensemble_summary_to_csv(&summary, "output/ensemble_summary.csv") {
+        Ok(_) => println!("✅ Saved ensemble summary to output/ensemble_summary.csv"),
+        Err(e) => eprintln!("❌ Failed to save ensemble summary: {}", e),
+    }
+
+    let potential = ensemble_band(potentials, BAND_LEVEL);
+    match save_ensemble_band_to_csv(&potential, dt, "output/ensemble_potential.csv") {
+        Ok(_) => println!("✅ Saved ensemble potential to output/ensemble_potential.csv"),
+        Err(e) => eprintln!("❌ Failed to save ensemble potential: {}", e),
+    }
+    let filename = style.path("figures", "ensemble_potential");
+    match plot_ensemble_band(&potential, dt, references, ("Average Voltage over Noise Realizations", "v̄(t)"), style, &filename) {
+        Ok(_) => println!("✅ Ensemble potential plot saved to {}", filename),
+        Err(e) => eprintln!("❌ Ensemble potential plot error: {}", e),
+    }
+
+    let rate = ensemble_band(rates, BAND_LEVEL);
+    match save_ensemble_band_to_csv(&rate, RATE_BIN, "output/ensemble_rate.csv") {
+        Ok(_) => println!("✅ Saved ensemble rate to output/ensemble_rate.csv"),
+        Err(e) => eprintln!("❌ Failed to save ensemble rate: {}", e),
+    }
+    let filename = style.path("figures", "ensemble_rate");
+    match plot_ensemble_band(&rate, RATE_BIN, &[], ("Population Rate over Noise Realizations", "rate"), style, &filename) {
+        Ok(_) => println!("✅ Ensemble rate plot saved to {}", filename),
+        Err(e) => eprintln!("❌ Ensemble rate plot error: {}", e),
+    }
+}
+
+
+/// Welch spectra of one or several LFPs with their dominant frequencies, and the spectrogram
+/// of the last one; `window` is the segment length in time units
+fn save_spectral_outputs(lfps: &[(String, Vec<f64>)], dt: f64, window: f64, style: &PlotStyle) {
+    if lfps.iter().any(|(_, lfp)| lfp.len() < 2) {
+        println!("Note: the LFP spectrum needs at least two time steps, skipping it");
+        return;
+    }
+    let segment = ((window / dt).round() as usize).max(2);
+    let mut frequencies = Vec::new();
+    let mut spectra = Vec::new();
+    for (name, lfp) in lfps {
+        let (f, psd) = welch_psd(lfp, dt, segment);
+        println!("Dominant frequency of the LFP ({}): {:.5}", name, dominant_frequency(&f, &psd));
+        frequencies = f;
+        spectra.push((name.clone(), psd));
+    }
+    // Up to five times the highest dominant frequency
+    let nyquist = 0.5 / dt;
+    let peak = spectra.iter().map(|(_, psd)| dominant_frequency(&frequencies, psd)).fold(0.0, f64::max);
+    let f_max = if peak > 0.0 { (5.0 * peak).min(nyquist) } else { nyquist };
+
+    match save_psd_to_csv(&frequencies, &spectra, "output/psd.csv") {
+        Ok(_) => println!("✅ Saved power spectral density to output/psd.csv"),
+        Err(e) => eprintln!("❌ Failed to save power spectral density: {}", e),
+    }
+    let filename = style.path("figures", "psd");
+    match plot_psd(&frequencies, &spectra, f_max, style, &filename) {
+        Ok(_) => println!("✅ PSD plot saved to {}", filename),
+        Err(e) => eprintln!("❌ PSD plot error: {}", e),
+    }
+
+    // At least four windows over the run
+    let Some((_, lfp)) = lfps.last() else { return };
+    let window = segment.min(lfp.len() / 4).max(2);
+    let short_time = spectrogram(lfp, dt, window, (window / 4).max(1));
+    match save_spectrogram_to_csv(&short_time, "output/spectrogram.csv") {
+        Ok(_) => println!("✅ Saved spectrogram to output/spectrogram.csv"),
+        Err(e) => eprintln!("❌ Failed to save spectrogram: {}", e),
+    }
+    let filename = style.path("figures", "spectrogram");
+    match plot_spectrogram(&short_time, f_max, style, &filename) {
+        Ok(_) => println!("✅ Spectrogram saved to {}", filename),
+        Err(e) => eprintln!("❌ Spectrogram error: {}", e),
+    }
+}
+
+
+/// Kuramoto order parameter from the Hilbert and spike-interpolated phases, synchrony index
+/// and spike-count correlations of a simulation
+fn save_synchrony_outputs<S: ModelState>(sim: &[Vec<S>], trains: &[Vec<f64>], dt: f64, bin: f64, style: &PlotStyle) {
+    let steps = sim[0].len();
+    let spike_phases: Vec<Vec<f64>> = trains.iter().map(|times| spike_phase(times, steps, dt)).collect();
+    let series = vec![
+        ("hilbert".to_string(), kuramoto_order_parameter(&population_phases(sim))),
+        ("spikes".to_string(), kuramoto_order_parameter(&spike_phases)),
+    ];
+    println!(
+        "Synchrony: mean R = {:.4} (Hilbert), {:.4} (spikes), χ = {:.4}, spike-count correlation = {:.4}",
+        mean_order_parameter(&series[0].1),
+        mean_order_parameter(&series[1].1),
+        synchrony_index(sim),
+        spike_count_correlation(trains, steps as f64 * dt, bin),
+    );
+
+    match save_order_parameter_to_csv(&series, dt, "output/synchrony.csv") {
+        Ok(_) => println!("✅ Saved order parameter to output/synchrony.csv"),
+        Err(e) => eprintln!("❌ Failed to save order parameter: {}", e),
+    }
+    let filename = style.path("figures", "synchrony");
+    match plot_order_parameter(&series, dt, style, &filename) {
+        Ok(_) => println!("✅ Order parameter plot saved to {}", filename),
+        Err(e) => eprintln!("❌ Order parameter plot error: {}", e),
+    }
+}
+
+
+/// Time average of the Kuramoto order parameter R and synchrony index χ of a simulation
+fn synchrony_measures<S: ModelState>(sim: &[Vec<S>]) -> (f64, f64) {
+    (mean_order_parameter(&kuramoto_order_parameter(&population_phases(sim))), synchrony_index(sim))
+}
+
+
+#[allow(clippy::too_many_arguments)]
+fn run_optimization<N: NeuronModel>(
+    population: &Population<N>,
+    initial: &InitialCondition<N::State>,
+    sigma_ext: f64,
+    steps: usize,
+    dt: f64,
+    descent: Descent<N::State>,
+    args: &ControlArgs,
+    detector: &SpikeDetector,
+    style: &PlotStyle,
+) {
+    let Descent { target: y_target, step_size, control } = descent;
+    if let Some(policy) = &args.feedback {
+        let offset = control.first().copied().unwrap_or(0.0);
+        let policy = policy.build(offset, &mut rand::thread_rng());
+        return run_feedback_optimization(population, initial, sigma_ext, steps, dt, policy, y_target, args, style);
+    }
+    let ControlArgs { target_pattern, controls: control_specs, desynchronize, .. } = args;
+    let mut cost_trace = Vec::new();
+    // Fitted on the first simulation, then fixed
+    let mut objective: Option<DesyncObjective> = None;
+    let pattern: Option<ReferencePattern> = target_pattern.as_ref().map(|spec| {
+        match spec.build(&population.lattice(), steps, dt) {
+            Ok(pattern) => pattern,
+            Err(e) => {
+                eprintln!("❌ Invalid target pattern: {}", e);
+                std::process::exit(1);
             }
-            
-            // Plot controlled profile
-            let final_sim = simulate_with_control(*neurons, *steps, *dt, &params, &control, initial);
-            match plot_average_potential(&final_sim, *dt, "figures/potential.png") {
-                Ok(_) => println!("✅ Average potential plot saved to figures/potential.png"),
-                Err(e) => eprintln!("❌ Failed to plot potential: {}", e),
+        }
+    });
+
+    // Control channels, the default one starting from `control`
+    let (channels, mut controls) = if control_specs.is_empty() {
+        (vec![ControlChannel::uniform(population.len())], vec![control])
+    } else {
+        let channels = build_control_channels(control_specs, population);
+        let controls = vec![vec![0.0; steps]; channels.len()];
+        (channels, controls)
+    };
+    if !control_specs.is_empty() {
+        for channel in &channels {
+            let reached = channel.gains.iter().filter(|g| g.abs() > 1e-9).count();
+            println!("Control {} on {}: {} neurons", channel.name, N::State::COMPONENTS[channel.component], reached);
+        }
+    }
+
+    plot_population_schedules(population, steps, dt, style);
+
+    // Introduce adjoint profile
+    let mut last_adj = vec![];
+
+    // Optimization loop
+    for iter in 0..MAX_ITERS {
+        let sim = simulate_with_controls(population, steps, dt, sigma_ext, &channels, &controls, initial);
+        let all_controls = controls.concat();
+        let (cost, adj) = match (&pattern, desynchronize) {
+            (Some(pattern), _) => (
+                evaluate_pattern_cost(&sim, &all_controls, pattern, GAMMA, LAMBDA2, C_T, dt),
+                compute_pattern_adjoint(&sim, population, pattern, GAMMA, C_T, dt),
+            ),
+            (None, Some(spec)) => {
+                let objective = objective.get_or_insert_with(|| spec.build(&sim));
+                (
+                    evaluate_desync_cost(&sim, &all_controls, objective, GAMMA, LAMBDA2, dt),
+                    compute_desync_adjoint(&sim, population, objective, GAMMA, dt),
+                )
             }
+            (None, None) => (
+                evaluate_cost(&sim, &all_controls, y_target, GAMMA, LAMBDA2, C_T, dt),
+                compute_adjoint(&sim, population, y_target, GAMMA, C_T, dt),
+            ),
+        };
+        cost_trace.push(cost);
+
+        let grads = compute_channel_gradients(&adj, &channels, &controls, LAMBDA2);
+        last_adj = adj; // save last adjoint for plotting later
+        controls = controls
+            .iter()
+            .zip(grads.iter())
+            .map(|(control, grad)| gradient_step(control, grad, if desynchronize.is_some() { DESYNC_STEP_SIZE } else { step_size }))
+            .collect();
+
+        println!("Iter {:>2}: J(α) = {:.6}", iter, cost);
+    }
+
+    // save optimal controls to csv, one column per channel
+    let names: Vec<String> = channels.iter().map(|channel| channel.name.clone()).collect();
+    let mut file = File::create("output/control.csv").expect("Failed to create control.csv");
+    writeln!(file, "t,{}", names.join(",")).unwrap();
+    for i in 0..steps {
+        let values: Vec<String> = controls.iter().map(|control| control[i].to_string()).collect();
+        writeln!(file, "{:.4},{}", i as f64 * dt, values.join(",")).unwrap();
+    }
+    println!("✅ Saved final control to output/control.csv");
+
+
+    // save cost to csv
+    let mut file = File::create("output/cost.csv").expect("Failed to create cost.csv");
+    writeln!(file, "iter,cost").unwrap();
+    for (i, j) in cost_trace.iter().enumerate() {
+        writeln!(file, "{},{}", i, j).unwrap();
+    }
+    println!("✅ Saved cost trace to output/cost.csv");
+
+    // Plot control
+    let filename = style.path("figures", "control");
+    let plotted = match controls.as_slice() {
+        [control] => plot_control(control, dt, style, &filename),
+        _ => plot_controls(&controls, &names, dt, style, &filename),
+    };
+    match plotted {
+        Ok(_) => println!("✅ Control plot saved to {}", filename),
+        Err(e) => eprintln!("❌ Failed to plot control: {}", e),
+    }
+
+    // Plot cost
+    let filename = style.path("figures", "cost");
+    match plot_cost_trace(&cost_trace, style, &filename) {
+        Ok(_) => println!("✅ Cost plot saved to {}", filename),
+        Err(e) => eprintln!("❌ Failed to plot cost: {}", e),
+    }
+
+    // Plot adjoint
+    let filename = style.path("figures", "adjoint");
+    match plot_adjoint_trajectories(&last_adj, dt, style, &filename, 5) {
+        Ok(_) => println!("✅ Adjoint plot saved to {}", filename),
+        Err(e) => eprintln!("❌ Failed to plot adjoint: {}", e),
+    }
+
+    // Plot reference profile
+    let filename = style.path("figures", "reference");
+    match plot_reference_profile(dt * steps as f64, dt, style, &filename) {
+        Ok(_) => println!("✅ Reference profile plot saved to {}", filename),
+        Err(e) => eprintln!("❌ Failed to plot reference profile: {}", e),
+    }
+
+    // Plot controlled profile
+    let final_sim = simulate_with_controls(population, steps, dt, 0.0, &channels, &controls, initial);
+    let filename = style.path("figures", "potential");
+    match plot_average_potential(&final_sim, dt, style, &filename) {
+        Ok(_) => println!("✅ Average potential plot saved to {}", filename),
+        Err(e) => eprintln!("❌ Failed to plot potential: {}", e),
+    }
+    save_population_outputs(population, &final_sim, dt, style);
+    let uncontrolled_controls = vec![vec![0.0; steps]; channels.len()];
+    let uncontrolled = simulate_with_controls(population, steps, dt, 0.0, &channels, &uncontrolled_controls, initial);
+    let lfps = [
+        ("uncontrolled".to_string(), local_field_potential(&uncontrolled)),
+        ("controlled".to_string(), local_field_potential(&final_sim)),
+    ];
+    save_spectral_outputs(&lfps, dt, PSD_WINDOW, style);
+    // Density of a noisy population under the optimized control
+    let noisy_sim = simulate_with_controls(population, steps, dt, sigma_ext, &channels, &controls, initial);
+    save_phase_plane_outputs(&noisy_sim, dt, &[], &[], "_controlled", style);
+    let signals: Vec<(String, Vec<f64>)> = channels.iter().map(|c| c.name.clone()).zip(controls.iter().cloned()).collect();
+    save_density_animation(&noisy_sim, &signals, dt, ANIMATION_FRAMES, "_controlled", style);
+
+    // Fresh noise realizations of the optimized control. The noiseless run from identical
+    // neurons is always synchronous, so the synchrony is measured on the noisy ones.
+    let all_controls = controls.concat();
+    let duration = steps as f64 * dt;
+    let (mut potentials, mut rates, mut costs, mut firing) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    let mut synchrony = vec![synchrony_measures(&noisy_sim)];
+    for _ in 0..args.evaluations {
+        let sim = simulate_with_controls(population, steps, dt, sigma_ext, &channels, &controls, initial);
+        synchrony.push(synchrony_measures(&sim));
+        let trains = detect_spikes(&sim, dt, detector);
+        potentials.push(local_field_potential(&sim));
+        rates.push(population_rate(&trains, duration, RATE_BIN));
+        firing.push(trains.iter().map(|times| times.len()).sum::<usize>() as f64 / (sim.len() as f64 * duration));
+        costs.push(match (&pattern, &objective) {
+            (Some(pattern), _) => evaluate_pattern_cost(&sim, &all_controls, pattern, GAMMA, LAMBDA2, C_T, dt),
+            (None, Some(objective)) => evaluate_desync_cost(&sim, &all_controls, objective, GAMMA, LAMBDA2, dt),
+            (None, None) => evaluate_cost(&sim, &all_controls, y_target, GAMMA, LAMBDA2, C_T, dt),
+        });
+    }
+    let realizations = synchrony.len() as f64;
+    println!(
+        "Synchrony of the controlled population, averaged over {} noisy run(s): mean R = {:.4}, χ = {:.4}",
+        synchrony.len(),
+        synchrony.iter().map(|(r, _)| r).sum::<f64>() / realizations,
+        synchrony.iter().map(|(_, chi)| chi).sum::<f64>() / realizations,
+    );
+    if args.evaluations > 0 {
+        // The constant target potential is only drawn when it is the one tracked
+        let profile: Vec<f64> = (0..steps).map(|t| reference_profile(t as f64 * dt, duration)).collect();
+        let target = vec![y_target.potential(); steps];
+        let mut references = vec![("reference_profile", profile.as_slice())];
+        if pattern.is_none() && objective.is_none() {
+            references.push(("target", target.as_slice()));
+        }
+        save_ensemble_outputs(&potentials, &rates, &costs, &firing, dt, &references, style);
+    }
+
+    // Plot target and controlled patterns
+    if let Some(pattern) = &pattern {
+        let filename = style.path("figures", "target_pattern");
+        match plot_space_time(pattern, dt, "Target Pattern v*(x, t)", style, &filename) {
+            Ok(_) => println!("✅ Target pattern plot saved to {}", filename),
+            Err(e) => eprintln!("❌ Failed to plot target pattern: {}", e),
+        }
+        save_spatial_outputs(population, &final_sim, dt, style);
+    }
+}
 
+
+/// Learns a feedback policy α = π(v̄) by stochastic gradient descent on the expected cost
+/// (one noise realization per iteration), then evaluates it on fresh noise
+#[allow(clippy::too_many_arguments)]
+fn run_feedback_optimization<N: NeuronModel>(
+    population: &Population<N>,
+    initial: &InitialCondition<N::State>,
+    sigma_ext: f64,
+    steps: usize,
+    dt: f64,
+    mut policy: FeedbackPolicy,
+    y_target: N::State,
+    args: &ControlArgs,
+    style: &PlotStyle,
+) {
+    if args.target_pattern.is_some() {
+        eprintln!("⚠️ The feedback policy observes the mean potential only, ignoring --target-pattern");
+    }
+    let channel = match build_control_channels(&args.controls, population).as_slice() {
+        [] => ControlChannel::uniform(population.len()),
+        [channel] => channel.clone(),
+        _ => {
+            eprintln!("❌ Invalid control: a feedback policy drives a single control channel");
+            std::process::exit(1);
         }
+    };
+    let channels = std::slice::from_ref(&channel);
+    let initial_policy = policy.clone();
+
+    plot_population_schedules(population, steps, dt, style);
+
+    // Closed-loop run of a policy, with the control it applied
+    let run = |policy: &FeedbackPolicy, sigma_ext: f64| {
+        let law = |t: usize, sim: &[Vec<N::State>]| vec![policy.control(mean_potential(sim, t))];
+        let sim = simulate_with_feedback(population, steps, dt, sigma_ext, channels, &law, initial);
+        let control: Vec<f64> = (0..steps).map(|t| policy.control(mean_potential(&sim, t))).collect();
+        let cost = evaluate_cost(&sim, &control, y_target, GAMMA, LAMBDA2, C_T, dt);
+        (sim, control, cost)
+    };
+
+    let mut cost_trace = Vec::new();
+    for iter in 0..MAX_ITERS {
+        let (sim, _, cost) = run(&policy, sigma_ext);
+        let feedback = feedback_sensitivity(&policy, &sim, &channel, LAMBDA2);
+        let adj = compute_feedback_adjoint(&sim, population, y_target, GAMMA, C_T, dt, &feedback);
+        let grad = policy_gradient(&policy, &sim, &adj, &feedback, dt);
+        let theta = gradient_step(&policy.parameters(), &grad, POLICY_STEP_SIZE);
+        policy.set_parameters(&theta);
+        cost_trace.push(cost);
+
+        println!("Iter {:>2}: J(π) = {:.6}", iter, cost);
+    }
+
+    // Expected cost on fresh noise, before and after training
+    let mut file = File::create("output/feedback_evaluation.csv").expect("Failed to create feedback_evaluation.csv");
+    writeln!(file, "realization,initial_cost,trained_cost").unwrap();
+    let (mut initial_costs, mut trained_costs) = (Vec::new(), Vec::new());
+    for k in 0..args.evaluations {
+        let (_, _, initial_cost) = run(&initial_policy, sigma_ext);
+        let (_, _, trained_cost) = run(&policy, sigma_ext);
+        writeln!(file, "{},{},{}", k, initial_cost, trained_cost).unwrap();
+        initial_costs.push(initial_cost);
+        trained_costs.push(trained_cost);
+    }
+    let mean_std = |costs: &[f64]| {
+        let n = costs.len().max(1) as f64;
+        let mean = costs.iter().sum::<f64>() / n;
+        (mean, (costs.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / n).sqrt())
+    };
+    let (initial_mean, initial_std) = mean_std(&initial_costs);
+    let (trained_mean, trained_std) = mean_std(&trained_costs);
+    println!("Fresh noise ({} realizations): J(initial) = {:.6} ± {:.6}, J(trained) = {:.6} ± {:.6}",
+        args.evaluations, initial_mean, initial_std, trained_mean, trained_std);
+    println!("✅ Saved evaluation to output/feedback_evaluation.csv");
+
+    match save_policy_to_csv(&policy, "output/policy.csv") {
+        Ok(_) => println!("✅ Saved policy to output/policy.csv"),
+        Err(e) => eprintln!("❌ Failed to save policy: {}", e),
+    }
+
+    // save cost to csv
+    let mut file = File::create("output/cost.csv").expect("Failed to create cost.csv");
+    writeln!(file, "iter,cost").unwrap();
+    for (i, j) in cost_trace.iter().enumerate() {
+        writeln!(file, "{},{}", i, j).unwrap();
+    }
+    println!("✅ Saved cost trace to output/cost.csv");
+
+    let filename = style.path("figures", "cost");
+    match plot_cost_trace(&cost_trace, style, &filename) {
+        Ok(_) => println!("✅ Cost plot saved to {}", filename),
+        Err(e) => eprintln!("❌ Failed to plot cost: {}", e),
+    }
+
+    // One closed-loop run on fresh noise, with the control it applied
+    let (final_sim, control, _) = run(&policy, sigma_ext);
+    let mean_v: Vec<f64> = (0..steps).map(|t| mean_potential(&final_sim, t)).collect();
+    let v_min = mean_v.iter().cloned().fold(f64::INFINITY, f64::min);
+    let v_max = mean_v.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let filename = style.path("figures", "policy");
+    match plot_policy(&policy, (v_min - 0.5, v_max + 0.5), style, &filename) {
+        Ok(_) => println!("✅ Policy plot saved to {}", filename),
+        Err(e) => eprintln!("❌ Failed to plot policy: {}", e),
+    }
+
+    let mut file = File::create("output/control.csv").expect("Failed to create control.csv");
+    writeln!(file, "t,{}", channel.name).unwrap();
+    for (i, alpha) in control.iter().enumerate() {
+        writeln!(file, "{:.4},{}", i as f64 * dt, alpha).unwrap();
+    }
+    println!("✅ Saved closed-loop control to output/control.csv");
+
+    let filename = style.path("figures", "control");
+    match plot_control(&control, dt, style, &filename) {
+        Ok(_) => println!("✅ Control plot saved to {}", filename),
+        Err(e) => eprintln!("❌ Failed to plot control: {}", e),
+    }
+    let filename = style.path("figures", "potential");
+    match plot_average_potential(&final_sim, dt, style, &filename) {
+        Ok(_) => println!("✅ Average potential plot saved to {}", filename),
+        Err(e) => eprintln!("❌ Failed to plot potential: {}", e),
+    }
+    save_population_outputs(population, &final_sim, dt, style);
+}
+
+
+/// Receding-horizon control of the noisy population towards the reference profile
+fn run_mpc<N: NeuronModel>(
+    population: &Population<N>,
+    initial: &InitialCondition<N::State>,
+    sigma_ext: f64,
+    steps: usize,
+    dt: f64,
+    settings: &MpcSettings,
+    style: &PlotStyle,
+) {
+    println!(
+        "Running MPC with L = {}, M = {}, dt = {}, horizon = {}, chunk = {}",
+        population.len(), steps, dt, settings.horizon, settings.chunk
+    );
+    if !matches!(population.delay, SynapticDelay::None) {
+        println!("Note: each MPC window starts without synaptic history, delayed inputs see the states at its start");
+    }
+    plot_population_schedules(population, steps, dt, style);
+
+    let t_final = steps as f64 * dt;
+    let reference: Vec<f64> = (0..steps).map(|t| reference_profile(t as f64 * dt, t_final)).collect();
+    let run = receding_horizon(population, initial, sigma_ext, dt, &reference, settings);
+
+    for (w, (before, after)) in run.window_costs.iter().enumerate() {
+        println!("Window {:>3} (t = {:.1}): J = {:.6} -> {:.6}", w, (w * settings.chunk) as f64 * dt, before, after);
+    }
+    let mean_v: Vec<f64> = (0..steps).map(|t| mean_potential(&run.sim, t)).collect();
+    let rms = (mean_v.iter().zip(reference.iter()).map(|(v, r)| (v - r).powi(2)).sum::<f64>() / steps as f64).sqrt();
+    println!("Closed-loop RMS tracking error: {:.6}", rms);
+
+    match save_mpc_to_csv(&run, &reference, dt, "output/mpc.csv") {
+        Ok(_) => println!("✅ Saved closed loop to output/mpc.csv"),
+        Err(e) => eprintln!("❌ Failed to save closed loop: {}", e),
+    }
+    let filename = style.path("figures", "mpc");
+    match plot_tracking(&run.sim, &reference, dt, style, &filename) {
+        Ok(_) => println!("✅ Tracking plot saved to {}", filename),
+        Err(e) => eprintln!("❌ Failed to plot tracking: {}", e),
+    }
+    let filename = style.path("figures", "control");
+    match plot_control(&run.control, dt, style, &filename) {
+        Ok(_) => println!("✅ Control plot saved to {}", filename),
+        Err(e) => eprintln!("❌ Failed to plot control: {}", e),
+    }
+    save_population_outputs(population, &run.sim, dt, style);
+}
+
+
+
+/// LQR around the mean-field fixed point of the nominal neuron `params` (Newton from `guess`),
+/// compared on the noisy population with no control and with the open-loop control of the
+/// adjoint descent, all for the cost ∫ γ (v̄ - v*)² + λ α² dt
+#[allow(clippy::too_many_arguments)]
+fn run_lqr<N: NeuronModel>(
+    params: &N,
+    guess: N::State,
+    population: &Population<N>,
+    initial: &InitialCondition<N::State>,
+    sigma_ext: f64,
+    steps: usize,
+    dt: f64,
+    style: &PlotStyle,
+) {
+    println!("Running LQR with L = {}, M = {}, dt = {}", population.len(), steps, dt);
+    let controller = match LqrController::new(params, guess, GAMMA, LAMBDA2) {
+        Ok(controller) => controller,
+        Err(e) => {
+            eprintln!("❌ Failed to compute the regulator: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let components = N::State::COMPONENTS;
+    let describe = |eigenvalues: &[nalgebra::Complex<f64>]| {
+        eigenvalues.iter().map(|e| format!("{:.4}{:+.4}i", e.re, e.im)).collect::<Vec<_>>().join(", ")
+    };
+    let open = controller.open_loop_eigenvalues();
+    let closed = controller.closed_loop_eigenvalues();
+    println!("Mean-field fixed point: {}", (0..N::State::DIM)
+        .map(|k| format!("{} = {:.6}", components[k], controller.fixed_point[k]))
+        .collect::<Vec<_>>()
+        .join(", "));
+    let stable = open.iter().all(|e| e.re < 0.0);
+    println!("Open-loop eigenvalues: {} ({})", describe(&open), if stable { "stable" } else { "unstable" });
+    println!("Gain K: {:?}", controller.gain);
+    println!("Closed-loop eigenvalues: {}", describe(&closed));
+    if closed.iter().any(|e| (1.0 + e.re * dt).hypot(e.im * dt) >= 1.0) {
+        println!("⚠️ dt = {} is too large for the closed-loop dynamics, the Euler scheme may be unstable", dt);
+    }
+    match controller.save_to_csv("output/lqr.csv") {
+        Ok(_) => println!("✅ Saved fixed point and gain to output/lqr.csv"),
+        Err(e) => eprintln!("❌ Failed to save regulator: {}", e),
+    }
+
+    let target = controller.fixed_point.potential();
+    let reference = vec![target; steps];
+    let mean_v = |sim: &[Vec<N::State>]| -> Vec<f64> { (0..steps).map(|t| mean_potential(sim, t)).collect() };
+    plot_population_schedules(population, steps, dt, style);
+
+    // Uncontrolled population
+    let free = simulate_population(population, steps, dt, sigma_ext, initial);
+    let free_cost = evaluate_tracking_cost(&free, &vec![0.0; steps], &reference, GAMMA, LAMBDA2, C_T, dt);
+
+    // LQR feedback on the mean state
+    let channels = [ControlChannel::uniform(population.len())];
+    let law = |t: usize, sim: &[Vec<N::State>]| vec![controller.control(&mean_state(sim, t))];
+    let regulated = simulate_with_feedback(population, steps, dt, sigma_ext, &channels, &law, initial);
+    let lqr_control: Vec<f64> = (0..steps).map(|t| controller.control(&mean_state(&regulated, t))).collect();
+    let lqr_cost = evaluate_tracking_cost(&regulated, &lqr_control, &reference, GAMMA, LAMBDA2, C_T, dt);
+
+    // Adjoint-based open-loop control for the same cost
+    let mut control = vec![0.0; steps];
+    for _ in 0..MAX_ITERS {
+        let sim = simulate_controlled_population(population, steps, dt, sigma_ext, &control, initial);
+        let adj = compute_tracking_adjoint(&sim, population, &reference, GAMMA, C_T, dt);
+        let grad = compute_control_gradient(&adj, &control, LAMBDA2, dt);
+        control = gradient_step(&control, &grad, STEP_SIZE);
+    }
+    let open_loop = simulate_controlled_population(population, steps, dt, sigma_ext, &control, initial);
+    let open_loop_cost = evaluate_tracking_cost(&open_loop, &control, &reference, GAMMA, LAMBDA2, C_T, dt);
+
+    println!("J(no control) = {:.6}", free_cost);
+    println!("J(LQR)        = {:.6}", lqr_cost);
+    println!("J(adjoint)    = {:.6} ({} open-loop iterations)", open_loop_cost, MAX_ITERS);
+
+    let mut file = File::create("output/lqr_comparison.csv").expect("Failed to create lqr_comparison.csv");
+    writeln!(file, "t,v_free,v_lqr,v_adjoint,alpha_lqr,alpha_adjoint").unwrap();
+    let (free_v, lqr_v, open_v) = (mean_v(&free), mean_v(&regulated), mean_v(&open_loop));
+    for t in 0..steps {
+        writeln!(file, "{:.4},{},{},{},{},{}", t as f64 * dt, free_v[t], lqr_v[t], open_v[t], lqr_control[t], control[t]).unwrap();
+    }
+    println!("✅ Saved comparison to output/lqr_comparison.csv");
+
+    let runs = vec![
+        ("no control".to_string(), free_v),
+        ("LQR".to_string(), lqr_v),
+        ("adjoint".to_string(), open_v),
+    ];
+    let filename = style.path("figures", "lqr");
+    match plot_controller_comparison(&runs, target, dt, style, &filename) {
+        Ok(_) => println!("✅ Comparison plot saved to {}", filename),
+        Err(e) => eprintln!("❌ Failed to plot comparison: {}", e),
+    }
+}
+
+
+
+/// Equilibria of the single-neuron and mean-field systems, with their phase planes
+fn run_analysis(params: &FhnParameters, style: &PlotStyle) {
+    let mut systems = Vec::new();
+    for system in [System::SingleNeuron, System::MeanField] {
+        let found = equilibria(params, system);
+        println!("{} system: {} equilibria", system.name(), found.len());
+        for eq in &found {
+            let eigenvalues: Vec<String> = eq.eigenvalues.iter().map(|e| format!("{:.4}{:+.4}i", e.re, e.im)).collect();
+            println!(
+                "  v = {:.6}, w = {:.6}, y = {:.6}: {} (eigenvalues {})",
+                eq.state.v, eq.state.w, eq.state.y, eq.stability.label(), eigenvalues.join(", ")
+            );
+        }
+        let filename = style.path("figures", &format!("phase_plane_{}", system.name()));
+        match plot_phase_plane(params, system, &found, style, &filename) {
+            Ok(_) => println!("✅ Phase plane saved to {}", filename),
+            Err(e) => eprintln!("❌ Failed to plot phase plane: {}", e),
+        }
+        systems.push((system, found));
+    }
+    match save_equilibria_to_csv(&systems, "output/equilibria.csv") {
+        Ok(_) => println!("✅ Saved equilibria to output/equilibria.csv"),
+        Err(e) => eprintln!("❌ Failed to save equilibria: {}", e),
+    }
+}
+
+
+
+/// Branch of mean-field equilibria in the parameter `name`, with its bifurcation points
+fn run_bifurcation<N: NeuronModel>(params: &N, guess: N::State, name: &str, settings: &ContinuationSettings, style: &PlotStyle) {
+    let branch = match continue_equilibria(params, name, guess, settings) {
+        Ok(branch) => branch,
+        Err(e) => {
+            eprintln!("❌ Continuation failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let points = detect_bifurcations(&branch);
+    println!("Branch of {} points, {} bifurcations", branch.len(), points.len());
+    for point in &points {
+        match point.kind {
+            BifurcationKind::SaddleNode => println!("  saddle-node at {} = {:.6}, v* = {:.6}", name, point.parameter, point.state.potential()),
+            BifurcationKind::Hopf => println!(
+                "  Hopf at {} = {:.6}, v* = {:.6}, period {:.4}",
+                name, point.parameter, point.state.potential(), 2.0 * std::f64::consts::PI / point.frequency
+            ),
+        }
+    }
+
+    match save_branch_to_csv(&branch, name, "output/bifurcation.csv") {
+        Ok(_) => println!("✅ Saved branch to output/bifurcation.csv"),
+        Err(e) => eprintln!("❌ Failed to save branch: {}", e),
+    }
+    match save_bifurcations_to_csv(&points, name, "output/bifurcation_points.csv") {
+        Ok(_) => println!("✅ Saved bifurcation points to output/bifurcation_points.csv"),
+        Err(e) => eprintln!("❌ Failed to save bifurcation points: {}", e),
+    }
+    let filename = style.path("figures", "bifurcation");
+    match plot_bifurcation_diagram(&branch, &points, name, style, &filename) {
+        Ok(_) => println!("✅ Bifurcation diagram saved to {}", filename),
+        Err(e) => eprintln!("❌ Failed to plot bifurcation diagram: {}", e),
     }
 }
@@ -3,3 +3,5 @@
 pub mod models;
 pub mod simulations;
 pub mod optim;
+pub mod analysis;
+pub mod plotting;
@@ -0,0 +1,482 @@
+// src/models/connectivity.rs
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::BTreeSet;
+use std::ops::Range;
+use std::str::FromStr;
+use crate::models::spatial::{KernelShape, Lattice, SpatialKernel};
+
+/// Sparse matrix in compressed sparse row format.
+/// Row i holds the presynaptic neurons of neuron i and their weights.
+#[derive(Debug, Clone)]
+pub struct CsrMatrix {
+    pub size: usize,
+    pub row_ptr: Vec<usize>,
+    pub col_idx: Vec<usize>,
+    pub values: Vec<f64>,
+}
+
+impl CsrMatrix {
+    /// Builds the matrix from (row, column, weight) triplets; repeated entries are summed
+    pub fn from_triplets(size: usize, triplets: &[(usize, usize, f64)]) -> Self {
+        let mut sorted = triplets.to_vec();
+        sorted.sort_by_key(|&(i, j, _)| (i, j));
+
+        let mut row_ptr = vec![0; size + 1];
+        let mut col_idx = Vec::with_capacity(sorted.len());
+        let mut values: Vec<f64> = Vec::with_capacity(sorted.len());
+        let mut last = None;
+        for (i, j, w) in sorted {
+            if last == Some((i, j)) {
+                *values.last_mut().unwrap() += w;
+                continue;
+            }
+            col_idx.push(j);
+            values.push(w);
+            row_ptr[i + 1] += 1;
+            last = Some((i, j));
+        }
+        for i in 0..size {
+            row_ptr[i + 1] += row_ptr[i];
+        }
+
+        CsrMatrix { size, row_ptr, col_idx, values }
+    }
+
+    /// Number of stored entries
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// (column, weight) pairs of row i
+    pub fn row(&self, i: usize) -> impl Iterator<Item = (usize, f64)> + '_ {
+        let range = self.row_ptr[i]..self.row_ptr[i + 1];
+        self.col_idx[range.clone()].iter().cloned().zip(self.values[range].iter().cloned())
+    }
+
+    /// Scales every non-empty row so that its weights sum to one
+    pub fn row_normalized(&self) -> Self {
+        let mut normalized = self.clone();
+        for i in 0..self.size {
+            let range = self.row_ptr[i]..self.row_ptr[i + 1];
+            let total: f64 = self.values[range.clone()].iter().sum();
+            if total != 0.0 {
+                normalized.values[range].iter_mut().for_each(|w| *w /= total);
+            }
+        }
+        normalized
+    }
+
+    /// y = A x
+    pub fn mul_vec(&self, x: &[f64]) -> Vec<f64> {
+        (0..self.size)
+            .map(|i| self.row(i).map(|(j, w)| w * x[j]).sum())
+            .collect()
+    }
+
+    /// y = Aᵀ x
+    pub fn transpose_mul_vec(&self, x: &[f64]) -> Vec<f64> {
+        let mut y = vec![0.0; self.size];
+        for (i, xi) in x.iter().enumerate() {
+            for (j, w) in self.row(i) {
+                y[j] += w * xi;
+            }
+        }
+        y
+    }
+}
+
+/// Neurons split into consecutive populations (e.g. excitatory and inhibitory), coupled
+/// all-to-all through one mean field per population.
+/// Synapses from population l onto population k have strength J_kl = `weights[k][l]` and
+/// the reversal potential `reversal[l]` of the presynaptic population.
+#[derive(Debug, Clone)]
+pub struct PopulationBlocks {
+    pub names: Vec<String>,
+    /// Population l holds the neurons offsets[l]..offsets[l + 1]
+    pub offsets: Vec<usize>,
+    pub weights: Vec<Vec<f64>>,
+    pub reversal: Vec<f64>,
+}
+
+impl PopulationBlocks {
+    /// Number of populations
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Neurons of population l
+    pub fn range(&self, l: usize) -> Range<usize> {
+        self.offsets[l]..self.offsets[l + 1]
+    }
+
+    /// Population of neuron i
+    pub fn population_of(&self, i: usize) -> usize {
+        self.offsets.partition_point(|&offset| offset <= i) - 1
+    }
+}
+
+/// How the synaptic input of each neuron is formed from the coupling observables
+/// (synaptic gates) of the population.
+#[derive(Debug, Clone)]
+pub enum Connectivity {
+    /// All-to-all coupling: every neuron sees the population average
+    MeanField,
+    /// Each neuron sees the weighted average over its presynaptic neurons
+    /// (rows of the matrix sum to one, or are empty for neurons without input)
+    Sparse(CsrMatrix),
+    /// Each neuron sees the average of every population separately, one input channel per population
+    Populations(PopulationBlocks),
+    /// Neurons on a ring or grid, each seeing the observables weighted by a distance kernel
+    Kernel(SpatialKernel),
+}
+
+impl Connectivity {
+    /// Directed Erdős–Rényi graph: each ordered pair is connected with probability p
+    pub fn erdos_renyi<R: Rng + ?Sized>(size: usize, p: f64, rng: &mut R) -> Self {
+        let mut edges = Vec::new();
+        for post in 0..size {
+            for pre in 0..size {
+                if pre != post && rng.gen_bool(p) {
+                    edges.push((pre, post, 1.0));
+                }
+            }
+        }
+        Self::from_edges(size, &edges)
+    }
+
+    /// Watts–Strogatz small world: a ring where each neuron is linked to its k nearest
+    /// neighbours (k even), each link being rewired to a random target with probability beta.
+    /// Links are symmetric.
+    pub fn small_world<R: Rng + ?Sized>(size: usize, k: usize, beta: f64, rng: &mut R) -> Self {
+        let mut links = BTreeSet::new();
+        for i in 0..size {
+            for d in 1..=k / 2 {
+                let j = (i + d) % size;
+                if i != j {
+                    links.insert((i.min(j), i.max(j)));
+                }
+            }
+        }
+        let ring: Vec<(usize, usize)> = links.iter().cloned().collect();
+        for (i, j) in ring {
+            if rng.gen_bool(beta) {
+                let target = rng.gen_range(0..size);
+                let rewired = (i.min(target), i.max(target));
+                if target != i && !links.contains(&rewired) {
+                    links.remove(&(i, j));
+                    links.insert(rewired);
+                }
+            }
+        }
+        Self::from_undirected(size, links.into_iter())
+    }
+
+    /// Barabási–Albert scale-free graph: starting from a clique of m + 1 neurons, each new
+    /// neuron attaches to m existing ones chosen with probability proportional to their degree.
+    /// Links are symmetric.
+    pub fn scale_free<R: Rng + ?Sized>(size: usize, m: usize, rng: &mut R) -> Self {
+        let core = (m + 1).min(size);
+        let mut links = BTreeSet::new();
+        // Every link contributes both ends, so that uniform draws are degree-biased
+        let mut ends = Vec::new();
+        for i in 0..core {
+            for j in i + 1..core {
+                links.insert((i, j));
+                ends.extend([i, j]);
+            }
+        }
+        for new in core..size {
+            let mut targets = BTreeSet::new();
+            while targets.len() < m.min(new) {
+                targets.insert(*ends.choose(rng).unwrap_or(&0));
+            }
+            for t in targets {
+                links.insert((t, new));
+                ends.extend([t, new]);
+            }
+        }
+        Self::from_undirected(size, links.into_iter())
+    }
+
+    /// Reads a weighted edge list: one `pre post [weight]` line per synapse, separated by
+    /// whitespace or commas, neurons numbered from 0; lines starting with `#` are ignored.
+    pub fn from_edge_list(path: &str, size: usize) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = std::fs::read_to_string(path)?;
+        let mut edges = Vec::new();
+
+        for (line_no, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|f| !f.is_empty())
+                .collect();
+            let invalid = || format!("{}:{}: expected 'pre post [weight]', got '{}'", path, line_no + 1, line);
+            if fields.len() < 2 || fields.len() > 3 {
+                return Err(invalid().into());
+            }
+            let pre: usize = fields[0].parse().map_err(|_| invalid())?;
+            let post: usize = fields[1].parse().map_err(|_| invalid())?;
+            let weight: f64 = match fields.get(2) {
+                Some(w) => w.parse().map_err(|_| invalid())?,
+                None => 1.0,
+            };
+            if pre >= size || post >= size {
+                return Err(format!("{}:{}: neuron index out of range for {} neurons", path, line_no + 1, size).into());
+            }
+            edges.push((pre, post, weight));
+        }
+
+        Ok(Self::from_edges(size, &edges))
+    }
+
+    /// Sparse connectivity from directed (pre, post, weight) synapses
+    pub fn from_edges(size: usize, edges: &[(usize, usize, f64)]) -> Self {
+        let triplets: Vec<(usize, usize, f64)> = edges.iter().map(|&(pre, post, w)| (post, pre, w)).collect();
+        Connectivity::Sparse(CsrMatrix::from_triplets(size, &triplets).row_normalized())
+    }
+
+    fn from_undirected(size: usize, links: impl Iterator<Item = (usize, usize)>) -> Self {
+        let edges: Vec<(usize, usize, f64)> = links.flat_map(|(i, j)| [(i, j, 1.0), (j, i, 1.0)]).collect();
+        Self::from_edges(size, &edges)
+    }
+
+    /// Number of synaptic inputs received by each neuron
+    pub fn channels(&self) -> usize {
+        match self {
+            Connectivity::Populations(blocks) => blocks.len(),
+            _ => 1,
+        }
+    }
+
+    /// Synaptic inputs of every neuron (one per channel), given the coupling observable of every neuron
+    pub fn inputs(&self, observables: &[f64]) -> Vec<Vec<f64>> {
+        match self {
+            Connectivity::MeanField => {
+                let mean = observables.iter().sum::<f64>() / observables.len() as f64;
+                vec![vec![mean]; observables.len()]
+            }
+            Connectivity::Sparse(weights) => weights.mul_vec(observables).into_iter().map(|u| vec![u]).collect(),
+            Connectivity::Kernel(kernel) => kernel.convolve(observables).into_iter().map(|u| vec![u]).collect(),
+            Connectivity::Populations(blocks) => {
+                let means: Vec<f64> = (0..blocks.len())
+                    .map(|l| {
+                        let range = blocks.range(l);
+                        observables[range.clone()].iter().sum::<f64>() / range.len() as f64
+                    })
+                    .collect();
+                vec![means; observables.len()]
+            }
+        }
+    }
+
+    /// Adjoint of `inputs`: maps sensitivities w.r.t. the inputs to sensitivities w.r.t. the observables
+    pub fn transpose_inputs(&self, sensitivities: &[Vec<f64>]) -> Vec<f64> {
+        match self {
+            Connectivity::MeanField => {
+                let mean = sensitivities.iter().map(|s| s[0]).sum::<f64>() / sensitivities.len() as f64;
+                vec![mean; sensitivities.len()]
+            }
+            Connectivity::Sparse(weights) => {
+                let sensitivities: Vec<f64> = sensitivities.iter().map(|s| s[0]).collect();
+                weights.transpose_mul_vec(&sensitivities)
+            }
+            Connectivity::Kernel(kernel) => {
+                let sensitivities: Vec<f64> = sensitivities.iter().map(|s| s[0]).collect();
+                kernel.convolve(&sensitivities)
+            }
+            Connectivity::Populations(blocks) => {
+                let mut transposed = vec![0.0; sensitivities.len()];
+                for l in 0..blocks.len() {
+                    let range = blocks.range(l);
+                    let mean = sensitivities.iter().map(|s| s[l]).sum::<f64>() / range.len() as f64;
+                    transposed[range].iter_mut().for_each(|t| *t = mean);
+                }
+                transposed
+            }
+        }
+    }
+
+    /// Writes the synapses as `pre,post,weight` rows (the weights are the normalized ones,
+    /// including the strength J_kl between populations; negligible kernel weights are skipped)
+    pub fn save_edges_to_csv(&self, size: usize, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut wtr = csv::Writer::from_path(path)?;
+        wtr.write_record(["pre", "post", "weight"])?;
+
+        match self {
+            Connectivity::MeanField => {
+                for post in 0..size {
+                    for pre in 0..size {
+                        wtr.write_record(&[pre.to_string(), post.to_string(), (1.0 / size as f64).to_string()])?;
+                    }
+                }
+            }
+            Connectivity::Sparse(weights) => {
+                for post in 0..weights.size {
+                    for (pre, w) in weights.row(post) {
+                        wtr.write_record(&[pre.to_string(), post.to_string(), w.to_string()])?;
+                    }
+                }
+            }
+            Connectivity::Populations(blocks) => {
+                for post in 0..size {
+                    let k = blocks.population_of(post);
+                    for l in 0..blocks.len() {
+                        let w = blocks.weights[k][l] / blocks.range(l).len() as f64;
+                        for pre in blocks.range(l) {
+                            wtr.write_record(&[pre.to_string(), post.to_string(), w.to_string()])?;
+                        }
+                    }
+                }
+            }
+            Connectivity::Kernel(kernel) => {
+                for post in 0..size {
+                    for pre in 0..size {
+                        let w = kernel.weight(post, pre);
+                        if w.abs() > 1e-9 {
+                            wtr.write_record(&[pre.to_string(), post.to_string(), w.to_string()])?;
+                        }
+                    }
+                }
+            }
+        }
+
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+/// Network description as given on the command line:
+/// `mean-field`, `erdos-renyi:P`, `small-world:K,BETA`, `scale-free:M`, `edges:PATH`,
+/// or a distance kernel on a ring or square grid, `ring:KERNEL` / `grid:KERNEL`
+/// (KERNEL = `gaussian:SIGMA` or `mexican-hat:SIGMA_E,SIGMA_I,RATIO`).
+#[derive(Debug, Clone)]
+pub enum ConnectivitySpec {
+    MeanField,
+    ErdosRenyi(f64),
+    SmallWorld(usize, f64),
+    ScaleFree(usize),
+    EdgeList(String),
+    Ring(KernelShape),
+    Grid(KernelShape),
+}
+
+impl FromStr for ConnectivitySpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, args) = s.split_once(':').unwrap_or((s, ""));
+        let fields: Vec<&str> = args.split(',').map(|a| a.trim()).collect();
+        let invalid = || format!("invalid network '{}'", s);
+
+        match (kind, fields.as_slice()) {
+            ("ring", _) => args.parse().map(ConnectivitySpec::Ring),
+            ("grid", _) => args.parse().map(ConnectivitySpec::Grid),
+            ("mean-field", _) => Ok(ConnectivitySpec::MeanField),
+            ("erdos-renyi", [p]) => match p.parse::<f64>() {
+                Ok(p) if (0.0..=1.0).contains(&p) => Ok(ConnectivitySpec::ErdosRenyi(p)),
+                _ => Err(invalid()),
+            },
+            ("small-world", [k, beta]) => match (k.parse::<usize>(), beta.parse::<f64>()) {
+                (Ok(k), Ok(beta)) if (0.0..=1.0).contains(&beta) => Ok(ConnectivitySpec::SmallWorld(k, beta)),
+                _ => Err(invalid()),
+            },
+            ("scale-free", [m]) => m.parse().map(ConnectivitySpec::ScaleFree).map_err(|_| invalid()),
+            ("edges", [path]) if !path.is_empty() => Ok(ConnectivitySpec::EdgeList(path.to_string())),
+            _ => Err(format!(
+                "unknown network '{}', expected mean-field, erdos-renyi:P, small-world:K,BETA, scale-free:M, edges:PATH, ring:KERNEL or grid:KERNEL",
+                s
+            )),
+        }
+    }
+}
+
+impl ConnectivitySpec {
+    pub fn build<R: Rng + ?Sized>(&self, size: usize, rng: &mut R) -> Result<Connectivity, String> {
+        match self {
+            ConnectivitySpec::MeanField => Ok(Connectivity::MeanField),
+            ConnectivitySpec::ErdosRenyi(p) => Ok(Connectivity::erdos_renyi(size, *p, rng)),
+            ConnectivitySpec::SmallWorld(k, beta) => Ok(Connectivity::small_world(size, *k, *beta, rng)),
+            ConnectivitySpec::ScaleFree(m) => Ok(Connectivity::scale_free(size, *m, rng)),
+            ConnectivitySpec::EdgeList(path) => Connectivity::from_edge_list(path, size)
+                .map_err(|e| format!("failed to load edge list {}: {}", path, e)),
+            ConnectivitySpec::Ring(shape) => Ok(Connectivity::Kernel(SpatialKernel::new(Lattice::Ring(size), *shape))),
+            ConnectivitySpec::Grid(shape) => {
+                let side = (size as f64).sqrt().round() as usize;
+                if side * side != size {
+                    return Err(format!("a grid needs a square number of neurons, got {}", size));
+                }
+                Ok(Connectivity::Kernel(SpatialKernel::new(Lattice::Grid(side, side), *shape)))
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Directed synapses (pre, post, weight), far from symmetric, with neuron 2 receiving none
+    fn edges() -> Vec<(usize, usize, f64)> {
+        vec![(3, 0, 1.0), (3, 1, 2.0), (0, 1, 1.0), (0, 3, 0.5), (1, 3, 1.5)]
+    }
+
+    #[test]
+    fn rows_are_normalized() {
+        let weights = CsrMatrix::from_triplets(4, &edges().iter().map(|&(pre, post, w)| (post, pre, w)).collect::<Vec<_>>());
+        let normalized = weights.row_normalized();
+        assert_eq!(normalized.nnz(), weights.nnz());
+        for i in 0..4 {
+            let row: Vec<(usize, f64)> = normalized.row(i).collect();
+            let total: f64 = row.iter().map(|(_, w)| w).sum();
+            match i {
+                2 => assert!(row.is_empty()),
+                _ => assert!((total - 1.0).abs() < 1e-12, "row {} sums to {}", i, total),
+            }
+        }
+        assert_eq!(normalized.row(1).collect::<Vec<_>>(), vec![(0, 1.0 / 3.0), (3, 2.0 / 3.0)]);
+    }
+
+    #[test]
+    fn transposed_inputs_are_the_adjoint_of_the_inputs() {
+        let observables = [0.3, -1.2, 0.7, 2.0];
+        let sensitivities = [0.5, 1.5, -0.4, 0.9];
+        for connectivity in [Connectivity::MeanField, Connectivity::from_edges(4, &edges())] {
+            let inputs = connectivity.inputs(&observables);
+            let transposed = connectivity.transpose_inputs(&sensitivities.iter().map(|&s| vec![s]).collect::<Vec<_>>());
+            // ⟨u(x), s⟩ = ⟨x, uᵀ(s)⟩
+            let forward: f64 = inputs.iter().zip(sensitivities.iter()).map(|(u, s)| u[0] * s).sum();
+            let backward: f64 = observables.iter().zip(transposed.iter()).map(|(x, t)| x * t).sum();
+            assert!((forward - backward).abs() < 1e-12, "{:?}: {} vs {}", connectivity, forward, backward);
+        }
+    }
+
+    #[test]
+    fn population_inputs_are_transposed_channel_by_channel() {
+        let blocks = PopulationBlocks {
+            names: vec!["E".to_string(), "I".to_string()],
+            offsets: vec![0, 1, 4],
+            weights: vec![vec![0.5, 1.2], vec![0.9, 0.3]],
+            reversal: vec![1.2, -1.5],
+        };
+        let connectivity = Connectivity::Populations(blocks);
+        let observables = [0.3, -1.2, 0.7, 2.0];
+        let sensitivities = vec![vec![0.5, -0.2], vec![1.5, 0.1], vec![-0.4, 0.8], vec![0.9, 0.6]];
+
+        let inputs = connectivity.inputs(&observables);
+        assert_eq!(inputs[2], vec![0.3, 0.5]);
+        let transposed = connectivity.transpose_inputs(&sensitivities);
+        let forward: f64 = inputs.iter().zip(sensitivities.iter()).map(|(u, s)| u[0] * s[0] + u[1] * s[1]).sum();
+        let backward: f64 = observables.iter().zip(transposed.iter()).map(|(x, t)| x * t).sum();
+        assert!((forward - backward).abs() < 1e-12, "{} vs {}", forward, backward);
+    }
+}
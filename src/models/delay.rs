@@ -0,0 +1,112 @@
+// src/models/delay.rs
+
+use std::str::FromStr;
+
+/// Axonal delay of the synaptic coupling: a neuron receives the observables of its
+/// presynaptic neurons as they were τ earlier, with τ fixed or distributed.
+/// Before the start of the simulation the observables keep their initial value.
+#[derive(Debug, Clone, Copy)]
+pub enum SynapticDelay {
+    None,
+    Fixed(f64),
+    Uniform { low: f64, high: f64 },
+    /// Gamma distribution with the given mean and shape parameter
+    Gamma { mean: f64, shape: f64 },
+}
+
+impl SynapticDelay {
+    /// Delays discretized on a time grid of step dt, as (number of steps, weight) pairs
+    /// with weights summing to one
+    pub fn taps(&self, dt: f64) -> Vec<(usize, f64)> {
+        let steps = |tau: f64| (tau / dt).round() as usize;
+        match *self {
+            SynapticDelay::None => vec![(0, 1.0)],
+            SynapticDelay::Fixed(tau) => vec![(steps(tau), 1.0)],
+            SynapticDelay::Uniform { low, high } => {
+                let (first, last) = (steps(low), steps(high));
+                let weight = 1.0 / (last - first + 1) as f64;
+                (first..=last).map(|k| (k, weight)).collect()
+            }
+            SynapticDelay::Gamma { mean, shape } => {
+                let scale = mean / shape;
+                let density = |tau: f64| tau.powf(shape - 1.0) * (-tau / scale).exp();
+                // Step k stands for the delays in [(k - 1/2) dt, (k + 1/2) dt), truncated far in the tail
+                let last = steps(mean + 8.0 * shape.sqrt() * scale).max(1);
+                let mut taps: Vec<(usize, f64)> = (0..=last)
+                    .map(|k| match k {
+                        0 => (0, 0.5 * dt * density(0.25 * dt)),
+                        k => (k, dt * density(k as f64 * dt)),
+                    })
+                    .collect();
+                let total: f64 = taps.iter().map(|(_, w)| w).sum();
+                taps.iter_mut().for_each(|(_, w)| *w /= total);
+                taps.retain(|&(_, w)| w > 1e-12);
+                taps
+            }
+        }
+    }
+}
+
+/// `none`, `fixed:TAU`, `uniform:LOW,HIGH` or `gamma:MEAN,SHAPE` (in units of time)
+impl FromStr for SynapticDelay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, args) = s.split_once(':').unwrap_or((s, ""));
+        let numbers = match args {
+            "" => Vec::new(),
+            args => args
+                .split(',')
+                .map(|a| a.trim().parse::<f64>().map_err(|e| format!("invalid number '{}': {}", a, e)))
+                .collect::<Result<Vec<f64>, String>>()?,
+        };
+
+        match (kind, numbers.as_slice()) {
+            ("none", &[]) => Ok(SynapticDelay::None),
+            ("fixed", &[tau]) if tau.is_finite() && tau >= 0.0 => Ok(SynapticDelay::Fixed(tau)),
+            ("uniform", &[low, high]) if high.is_finite() && 0.0 <= low && low <= high => {
+                Ok(SynapticDelay::Uniform { low, high })
+            }
+            ("gamma", &[mean, shape]) if mean.is_finite() && shape.is_finite() && mean > 0.0 && shape > 0.0 => {
+                Ok(SynapticDelay::Gamma { mean, shape })
+            }
+            _ => Err(format!(
+                "invalid delay '{}', expected none, fixed:TAU, uniform:LOW,HIGH or gamma:MEAN,SHAPE",
+                s
+            )),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tap_weights_sum_to_one() {
+        let delays = [
+            SynapticDelay::None,
+            SynapticDelay::Fixed(0.5),
+            SynapticDelay::Uniform { low: 0.2, high: 0.7 },
+            SynapticDelay::Gamma { mean: 0.5, shape: 3.0 },
+            SynapticDelay::Gamma { mean: 0.05, shape: 1.0 },
+        ];
+        for delay in delays {
+            let taps = delay.taps(0.01);
+            let total: f64 = taps.iter().map(|(_, w)| w).sum();
+            assert!((total - 1.0).abs() < 1e-12, "{:?}: weights sum to {}", delay, total);
+        }
+        assert_eq!(SynapticDelay::Fixed(0.5).taps(0.01), vec![(50, 1.0)]);
+        assert_eq!(SynapticDelay::Uniform { low: 0.2, high: 0.7 }.taps(0.1).len(), 6);
+    }
+
+    #[test]
+    fn delays_need_valid_arguments() {
+        assert!(matches!("fixed:0.5".parse(), Ok(SynapticDelay::Fixed(tau)) if tau == 0.5));
+        assert!(matches!("gamma:2, 4".parse(), Ok(SynapticDelay::Gamma { mean, shape }) if mean == 2.0 && shape == 4.0));
+        for invalid in ["fixed:1,abc", "fixed:inf", "fixed:-1", "uniform:0.5,0.2", "uniform:0,inf", "gamma:1e400,2", "gamma:1,NaN", "none:1", "fixed"] {
+            assert!(invalid.parse::<SynapticDelay>().is_err(), "{} was accepted", invalid);
+        }
+    }
+}
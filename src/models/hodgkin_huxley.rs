@@ -0,0 +1,200 @@
+// src/models/hodgkin_huxley.rs
+
+use std::ops::{Index, IndexMut};
+use crate::models::model::{ModelState, NeuronModel};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HodgkinHuxleyState {
+    pub v: f64, // Membrane potential (mV)
+    pub m: f64, // Sodium activation
+    pub h: f64, // Sodium inactivation
+    pub n: f64, // Potassium activation
+    pub y: f64, // Synaptic gate
+}
+
+impl Index<usize> for HodgkinHuxleyState {
+    type Output = f64;
+
+    fn index(&self, k: usize) -> &f64 {
+        match k {
+            0 => &self.v,
+            1 => &self.m,
+            2 => &self.h,
+            3 => &self.n,
+            4 => &self.y,
+            _ => panic!("HodgkinHuxleyState has 5 components, got index {}", k),
+        }
+    }
+}
+
+impl IndexMut<usize> for HodgkinHuxleyState {
+    fn index_mut(&mut self, k: usize) -> &mut f64 {
+        match k {
+            0 => &mut self.v,
+            1 => &mut self.m,
+            2 => &mut self.h,
+            3 => &mut self.n,
+            4 => &mut self.y,
+            _ => panic!("HodgkinHuxleyState has 5 components, got index {}", k),
+        }
+    }
+}
+
+impl ModelState for HodgkinHuxleyState {
+    const DIM: usize = 5;
+    const COMPONENTS: &'static [&'static str] = &["v", "m", "h", "n", "y"];
+}
+
+/// Hodgkin–Huxley neuron (squid giant axon, rest near -65 mV) with the same synaptic gate
+/// and mean-field coupling as `FhnParameters`. Time is in ms; the gating kinetics are stiff,
+/// so the Euler–Maruyama step should stay around dt = 0.01.
+///
+/// C = membrane capacitance
+/// gNa, gK, gL = maximal conductances of the sodium, potassium and leak currents
+/// ENa, EK, EL = reversal potentials of the sodium, potassium and leak currents
+/// Vrev, ar, ad, Tmax, lambda, VT, J, Iext = as in `FhnParameters`
+#[derive(Debug, Clone, Copy)]
+#[allow(non_snake_case)]
+pub struct HodgkinHuxleyParameters {
+    pub C: f64,
+    pub gNa: f64,
+    pub gK: f64,
+    pub gL: f64,
+    pub ENa: f64,
+    pub EK: f64,
+    pub EL: f64,
+    pub Vrev: f64,
+    pub ar: f64,
+    pub ad: f64,
+    pub Tmax: f64,
+    pub lambda: f64,
+    pub VT: f64,
+    pub J: f64,
+    pub Iext: f64,
+}
+
+/// Classical parameters, with fast excitatory (AMPA-like) synapses
+impl Default for HodgkinHuxleyParameters {
+    fn default() -> Self {
+        HodgkinHuxleyParameters {
+            C: 1.0,
+            gNa: 120.0,
+            gK: 36.0,
+            gL: 0.3,
+            ENa: 50.0,
+            EK: -77.0,
+            EL: -54.387,
+            Vrev: 0.0,
+            ar: 1.1,
+            ad: 0.19,
+            Tmax: 1.0,
+            lambda: 0.2,
+            VT: 2.0,
+            J: 0.1,
+            Iext: 10.0,
+        }
+    }
+}
+
+/// x / (1 - exp(-x / k)), continuous at x = 0
+fn vtrap(x: f64, k: f64) -> f64 {
+    if x.abs() < 1e-6 {
+        k * (1.0 + x / (2.0 * k))
+    } else {
+        x / (1.0 - (-x / k).exp())
+    }
+}
+
+/// Opening and closing rates of the m, h and n gates
+fn rates(v: f64) -> [(f64, f64); 3] {
+    let alpha_m = 0.1 * vtrap(v + 40.0, 10.0);
+    let beta_m = 4.0 * (-(v + 65.0) / 18.0).exp();
+    let alpha_h = 0.07 * (-(v + 65.0) / 20.0).exp();
+    let beta_h = 1.0 / (1.0 + (-(v + 35.0) / 10.0).exp());
+    let alpha_n = 0.01 * vtrap(v + 55.0, 10.0);
+    let beta_n = 0.125 * (-(v + 65.0) / 80.0).exp();
+    [(alpha_m, beta_m), (alpha_h, beta_h), (alpha_n, beta_n)]
+}
+
+impl HodgkinHuxleyParameters {
+    fn release(&self, v: f64) -> f64 {
+        self.Tmax / (1.0 + (-self.lambda * (v - self.VT)).exp())
+    }
+
+    /// Rest state: v at -65 mV, gates and synaptic gate at their steady states
+    pub fn resting_state(&self) -> HodgkinHuxleyState {
+        let v = -65.0;
+        let [(am, bm), (ah, bh), (an, bn)] = rates(v);
+        let s = self.ar * self.release(v);
+        HodgkinHuxleyState {
+            v,
+            m: am / (am + bm),
+            h: ah / (ah + bh),
+            n: an / (an + bn),
+            y: s / (s + self.ad),
+        }
+    }
+}
+
+impl NeuronModel for HodgkinHuxleyParameters {
+    type State = HodgkinHuxleyState;
+
+    const PARAMETERS: &'static [&'static str] = &["C", "gNa", "gK", "gL", "ENa", "EK", "EL", "Vrev", "ar", "ad", "Tmax", "lambda", "VT", "J", "Iext"];
+
+    fn parameter_mut(&mut self, name: &str) -> Option<&mut f64> {
+        match name {
+            "C" => Some(&mut self.C),
+            "gNa" => Some(&mut self.gNa),
+            "gK" => Some(&mut self.gK),
+            "gL" => Some(&mut self.gL),
+            "ENa" => Some(&mut self.ENa),
+            "EK" => Some(&mut self.EK),
+            "EL" => Some(&mut self.EL),
+            "Vrev" => Some(&mut self.Vrev),
+            "ar" => Some(&mut self.ar),
+            "ad" => Some(&mut self.ad),
+            "Tmax" => Some(&mut self.Tmax),
+            "lambda" => Some(&mut self.lambda),
+            "VT" => Some(&mut self.VT),
+            "J" => Some(&mut self.J),
+            "Iext" => Some(&mut self.Iext),
+            _ => None,
+        }
+    }
+
+    fn drift(&self, state: &HodgkinHuxleyState, mean_y: f64) -> HodgkinHuxleyState {
+        let HodgkinHuxleyState { v, m, h, n, y } = *state;
+        let [(am, bm), (ah, bh), (an, bn)] = rates(v);
+
+        // External current + mean-field coupling
+        let input = self.Iext - self.J * (v - self.Vrev) * mean_y;
+        let ionic = self.gNa * m.powi(3) * h * (v - self.ENa)
+            + self.gK * n.powi(4) * (v - self.EK)
+            + self.gL * (v - self.EL);
+
+        HodgkinHuxleyState {
+            v: (input - ionic) / self.C,
+            m: am * (1.0 - m) - bm * m,
+            h: ah * (1.0 - h) - bh * h,
+            n: an * (1.0 - n) - bn * n,
+            y: self.ar * self.release(v) * (1.0 - y) - self.ad * y,
+        }
+    }
+
+    /// Current noise on the membrane potential
+    fn diffusion(&self, _state: &HodgkinHuxleyState) -> HodgkinHuxleyState {
+        HodgkinHuxleyState { v: 1.0 / self.C, ..Default::default() }
+    }
+
+    fn drift_mean_field_derivative(&self, state: &HodgkinHuxleyState, _mean_y: f64) -> HodgkinHuxleyState {
+        HodgkinHuxleyState { v: -self.J * (state.v - self.Vrev) / self.C, ..Default::default() }
+    }
+
+    fn coupling_observable(&self, state: &HodgkinHuxleyState) -> f64 {
+        state.y
+    }
+
+    fn coupling_observable_gradient(&self, _state: &HodgkinHuxleyState) -> HodgkinHuxleyState {
+        HodgkinHuxleyState { y: 1.0, ..Default::default() }
+    }
+}
@@ -1,3 +1,11 @@
+pub mod model;
 pub mod neuron;
+pub mod morris_lecar;
+pub mod hodgkin_huxley;
+pub mod population;
+pub mod connectivity;
+pub mod spatial;
+pub mod delay;
+pub mod schedule;
 pub mod reference;
-
+pub mod moment_closure;
@@ -0,0 +1,97 @@
+// src/models/model.rs
+
+use nalgebra::DMatrix;
+use std::fmt::Debug;
+use std::ops::{Index, IndexMut};
+
+/// State of a single neuron, seen as a small vector of named components.
+/// By convention component 0 is the membrane potential: it receives the
+/// control and enters the cost functional.
+pub trait ModelState:
+    Copy + Debug + Default + Index<usize, Output = f64> + IndexMut<usize>
+{
+    /// Number of components
+    const DIM: usize;
+    /// Component names, used for CSV headers and plot labels
+    const COMPONENTS: &'static [&'static str];
+
+    /// Membrane potential
+    fn potential(&self) -> f64 {
+        self[0]
+    }
+}
+
+/// A neuron model coupled to the rest of the population through a mean field.
+///
+/// Each neuron only sees an average of the `coupling_observable` of its presynaptic
+/// neurons (for FHN, the synaptic gate y): the population average for all-to-all
+/// coupling, a weighted average on a sparse network. Forward, adjoint, cost and
+/// plotting are written against this trait.
+pub trait NeuronModel: Clone {
+    type State: ModelState;
+
+    /// Names of the scalar parameters reachable through `parameter_mut`
+    const PARAMETERS: &'static [&'static str];
+
+    /// Dimension of the state space
+    fn dim(&self) -> usize {
+        Self::State::DIM
+    }
+
+    /// Mutable access to a parameter by name (as spelled in the parameter struct)
+    fn parameter_mut(&mut self, name: &str) -> Option<&mut f64>;
+
+    /// Value of a parameter by name
+    fn parameter(&self, name: &str) -> Option<f64> {
+        self.clone().parameter_mut(name).map(|p| *p)
+    }
+
+    /// Sets a parameter by name
+    fn set_parameter(&mut self, name: &str, value: f64) -> Result<(), String> {
+        match self.parameter_mut(name) {
+            Some(p) => {
+                *p = value;
+                Ok(())
+            }
+            None => Err(format!(
+                "unknown parameter '{}', expected one of {}",
+                name,
+                Self::PARAMETERS.join(", ")
+            )),
+        }
+    }
+
+    /// Deterministic drift of one neuron, given its current mean field (synaptic input)
+    fn drift(&self, state: &Self::State, mean_field: f64) -> Self::State;
+
+    /// Noise loading of each component, scaled by `sigma_ext` in the simulations
+    fn diffusion(&self, state: &Self::State) -> Self::State;
+
+    /// Jacobian of the drift w.r.t. the state: entry (k, j) is ∂drift_k / ∂x_j.
+    /// Defaults to central differences; models with a closed form should override it.
+    fn drift_jacobian(&self, state: &Self::State, mean_field: f64) -> DMatrix<f64> {
+        let dim = Self::State::DIM;
+        let mut jac = DMatrix::zeros(dim, dim);
+        for j in 0..dim {
+            let h = 1e-6 * state[j].abs().max(1.0);
+            let mut plus = *state;
+            let mut minus = *state;
+            plus[j] += h;
+            minus[j] -= h;
+            let (f_plus, f_minus) = (self.drift(&plus, mean_field), self.drift(&minus, mean_field));
+            for k in 0..dim {
+                jac[(k, j)] = (f_plus[k] - f_minus[k]) / (2.0 * h);
+            }
+        }
+        jac
+    }
+
+    /// Derivative of the drift w.r.t. the mean field
+    fn drift_mean_field_derivative(&self, state: &Self::State, mean_field: f64) -> Self::State;
+
+    /// Quantity averaged over the population to form the mean field
+    fn coupling_observable(&self, state: &Self::State) -> f64;
+
+    /// Gradient of the coupling observable w.r.t. the state
+    fn coupling_observable_gradient(&self, state: &Self::State) -> Self::State;
+}
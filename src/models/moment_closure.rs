@@ -0,0 +1,285 @@
+// src/models/moment_closure.rs
+
+use nalgebra::{DMatrix, Matrix3, SMatrix, SVector, Vector3};
+use crate::models::neuron::{FhnParameters, NeuronState};
+
+/// Number of independent entries of the packed moment state:
+/// 3 means (v, w, y) and the 6 entries of the upper triangle of the covariance.
+pub const MOMENT_DIM: usize = 9;
+
+/// Packed moment state, used by the adjoint.
+pub type PackedMoments = SVector<f64, MOMENT_DIM>;
+
+/// Mean and covariance of (v, w, y) at one time step.
+#[derive(Debug, Clone, Copy)]
+pub struct MomentState {
+    pub mean: Vector3<f64>,
+    pub cov: Matrix3<f64>,
+}
+
+impl MomentState {
+    /// Deterministic initial condition: all neurons start from the same state.
+    pub fn from_neuron(initial: NeuronState) -> Self {
+        MomentState {
+            mean: Vector3::new(initial.v, initial.w, initial.y),
+            cov: Matrix3::zeros(),
+        }
+    }
+
+    pub fn pack(&self) -> PackedMoments {
+        let c = &self.cov;
+        PackedMoments::from_column_slice(&[
+            self.mean[0], self.mean[1], self.mean[2],
+            c[(0, 0)], c[(0, 1)], c[(0, 2)], c[(1, 1)], c[(1, 2)], c[(2, 2)],
+        ])
+    }
+
+    pub fn unpack(x: &PackedMoments) -> Self {
+        MomentState {
+            mean: Vector3::new(x[0], x[1], x[2]),
+            cov: Matrix3::new(
+                x[3], x[4], x[5],
+                x[4], x[6], x[7],
+                x[5], x[7], x[8],
+            ),
+        }
+    }
+
+    /// Standard deviation of the membrane potential across the population
+    pub fn std_v(&self) -> f64 {
+        self.cov[(0, 0)].max(0.0).sqrt()
+    }
+}
+
+/// Gauss–Hermite rule for expectations under a standard normal law,
+/// computed once with the Golub–Welsch algorithm.
+#[derive(Debug, Clone)]
+struct GaussHermite {
+    nodes: Vec<f64>,
+    weights: Vec<f64>,
+}
+
+impl GaussHermite {
+    fn new(n: usize) -> Self {
+        // Jacobi matrix of the probabilists' Hermite polynomials
+        let jacobi = DMatrix::from_fn(n, n, |i, j| {
+            if i + 1 == j || j + 1 == i {
+                (i.max(j) as f64).sqrt()
+            } else {
+                0.0
+            }
+        });
+        let eigen = jacobi.symmetric_eigen();
+        let nodes = eigen.eigenvalues.iter().cloned().collect();
+        let weights = (0..n).map(|k| eigen.eigenvectors[(0, k)].powi(2)).collect();
+        GaussHermite { nodes, weights }
+    }
+
+    /// E[f(X)] for X ~ N(mean, var)
+    fn expectation<F: Fn(f64) -> f64>(&self, mean: f64, var: f64, f: F) -> f64 {
+        let sd = var.max(0.0).sqrt();
+        self.nodes
+            .iter()
+            .zip(self.weights.iter())
+            .map(|(z, w)| w * f(mean + sd * z))
+            .sum()
+    }
+}
+
+/// Reduced model of the FHN mean-field dynamics: the population is replaced by a
+/// Gaussian law whose mean and covariance follow a closed system of ODEs.
+///
+/// Polynomial terms are closed exactly, the sigmoid release term is averaged by
+/// Gauss–Hermite quadrature, and covariances with nonlinear terms use Stein's
+/// lemma, Cov(x, f(v)) = E[f'(v)] Cov(x, v).
+#[derive(Debug, Clone)]
+pub struct MomentClosure {
+    pub params: FhnParameters,
+    pub sigma_ext: f64,
+    quadrature: GaussHermite,
+}
+
+impl MomentClosure {
+    pub fn new(params: FhnParameters, sigma_ext: f64) -> Self {
+        MomentClosure {
+            params,
+            sigma_ext,
+            quadrature: GaussHermite::new(12),
+        }
+    }
+
+    /// Time derivative of the moments under the common control alpha
+    pub fn rhs(&self, state: &MomentState, alpha: f64) -> MomentState {
+        let p = &self.params;
+        let (mv, mw, my) = (state.mean[0], state.mean[1], state.mean[2]);
+        let pvv = state.cov[(0, 0)].max(0.0);
+        let pvy = state.cov[(0, 2)];
+
+        // Sigmoid release rate and its first two derivatives, averaged over v
+        let s = |v: f64| p.Tmax / (1.0 + (-p.lambda * (v - p.VT)).exp());
+        let ds = |v: f64| p.lambda * s(v) * (1.0 - s(v) / p.Tmax);
+        let d2s = |v: f64| p.lambda * ds(v) * (1.0 - 2.0 * s(v) / p.Tmax);
+        let es = self.quadrature.expectation(mv, pvv, s);
+        let eds = self.quadrature.expectation(mv, pvv, ds);
+        let ed2s = self.quadrature.expectation(mv, pvv, d2s);
+
+        // Mean field: the synaptic input only sees the population mean of y
+        let input = p.Iext - p.J * (mv - p.Vrev) * my;
+        let e_cubic = mv.powi(3) + 3.0 * mv * pvv;
+
+        let dmv = mv - e_cubic / 3.0 - mw + input + alpha;
+        let dmw = p.c * (mv + p.a - p.b * mw);
+        let dmy = p.ar * (es * (1.0 - my) - eds * pvy) - p.ad * my;
+
+        // Expected Jacobian of the drift (with the mean field held fixed)
+        let a = Matrix3::new(
+            1.0 - mv.powi(2) - pvv - p.J * my, -1.0, 0.0,
+            p.c, -p.c * p.b, 0.0,
+            p.ar * (eds * (1.0 - my) - ed2s * pvy), 0.0, -p.ar * es - p.ad,
+        );
+        let mut q = Matrix3::zeros();
+        q[(0, 0)] = self.sigma_ext.powi(2);
+
+        MomentState {
+            mean: Vector3::new(dmv, dmw, dmy),
+            cov: a * state.cov + state.cov * a.transpose() + q,
+        }
+    }
+
+    fn packed_rhs(&self, x: &PackedMoments, alpha: f64) -> PackedMoments {
+        self.rhs(&MomentState::unpack(x), alpha).pack()
+    }
+
+    /// Jacobian of the packed right-hand side, by central differences
+    pub fn jacobian(&self, state: &MomentState, alpha: f64) -> SMatrix<f64, MOMENT_DIM, MOMENT_DIM> {
+        let x = state.pack();
+        let mut jac = SMatrix::<f64, MOMENT_DIM, MOMENT_DIM>::zeros();
+        for k in 0..MOMENT_DIM {
+            let h = 1e-6 * x[k].abs().max(1.0);
+            let mut plus = x;
+            let mut minus = x;
+            plus[k] += h;
+            minus[k] -= h;
+            let column = (self.packed_rhs(&plus, alpha) - self.packed_rhs(&minus, alpha)) / (2.0 * h);
+            jac.set_column(k, &column);
+        }
+        jac
+    }
+}
+
+/// Forward Euler integration of the moment equations under a control α(t)
+pub fn simulate_moments(
+    closure: &MomentClosure,
+    m: usize,
+    dt: f64,
+    control: &[f64],
+    initial: NeuronState,
+) -> Vec<MomentState> {
+    let mut trajectory = vec![MomentState::from_neuron(initial); m];
+
+    for t in 1..m {
+        let prev = trajectory[t - 1];
+        let d = closure.rhs(&prev, control[t - 1]);
+        trajectory[t] = MomentState {
+            mean: prev.mean + dt * d.mean,
+            cov: prev.cov + dt * d.cov,
+        };
+    }
+
+    trajectory
+}
+
+/// Cost functional of the reduced model, same weights as `evaluate_cost`
+pub fn evaluate_moment_cost(
+    moments: &[MomentState],
+    control: &[f64],
+    y_target: NeuronState,
+    gamma: f64,
+    lambda2: f64,
+    c_t: f64,
+    dt: f64,
+) -> f64 {
+    let running_cost: f64 = moments
+        .iter()
+        .zip(control.iter())
+        .map(|(x, a)| (gamma * (x.mean[0] - y_target.v).powi(2) + lambda2 * a.powi(2)) * dt)
+        .sum();
+    let terminal = moments[moments.len() - 1].mean[0];
+
+    running_cost + c_t * gamma * (terminal - y_target.v).powi(2)
+}
+
+/// Solves the adjoint of the moment equations backward in time
+pub fn compute_moment_adjoint(
+    closure: &MomentClosure,
+    moments: &[MomentState],
+    control: &[f64],
+    y_target: NeuronState,
+    gamma: f64,
+    c_t: f64,
+    dt: f64,
+) -> Vec<PackedMoments> {
+    let m = moments.len();
+    let mut adjoint = vec![PackedMoments::zeros(); m];
+
+    // Terminal condition
+    adjoint[m - 1][0] = 2.0 * c_t * gamma * (moments[m - 1].mean[0] - y_target.v);
+
+    // Backward loop
+    for r in (0..m - 1).rev() {
+        let jac = closure.jacobian(&moments[r], control[r]);
+        let mut source = PackedMoments::zeros();
+        source[0] = 2.0 * gamma * (moments[r].mean[0] - y_target.v);
+
+        adjoint[r] = adjoint[r + 1] + dt * (jac.transpose() * adjoint[r + 1] + source);
+    }
+
+    adjoint
+}
+
+/// Gradient of the reduced cost; the control only enters the equation for the mean of v
+pub fn compute_moment_gradient(
+    adjoint: &[PackedMoments],
+    control: &[f64],
+    lambda2: f64,
+) -> Vec<f64> {
+    adjoint
+        .iter()
+        .zip(control.iter())
+        .map(|(p, a)| p[0] + 2.0 * lambda2 * a)
+        .collect()
+}
+
+/// Gradient descent on the reduced model.
+/// Returns the resulting control and the cost at each iteration; the control is meant to
+/// warm-start the optimization of the full stochastic population.
+#[allow(clippy::too_many_arguments)]
+pub fn optimize_moment_control(
+    closure: &MomentClosure,
+    initial: NeuronState,
+    control: &[f64],
+    dt: f64,
+    y_target: NeuronState,
+    gamma: f64,
+    lambda2: f64,
+    c_t: f64,
+    step_size: f64,
+    max_iters: usize,
+) -> (Vec<f64>, Vec<f64>) {
+    let m = control.len();
+    let mut control = control.to_vec();
+    let mut cost_trace = Vec::with_capacity(max_iters);
+
+    for _ in 0..max_iters {
+        let moments = simulate_moments(closure, m, dt, &control, initial);
+        cost_trace.push(evaluate_moment_cost(&moments, &control, y_target, gamma, lambda2, c_t, dt));
+
+        let adjoint = compute_moment_adjoint(closure, &moments, &control, y_target, gamma, c_t, dt);
+        let gradient = compute_moment_gradient(&adjoint, &control, lambda2);
+        for (a, g) in control.iter_mut().zip(gradient.iter()) {
+            *a -= step_size * g;
+        }
+    }
+
+    (control, cost_trace)
+}
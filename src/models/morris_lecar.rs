@@ -0,0 +1,166 @@
+// src/models/morris_lecar.rs
+
+use crate::models::model::NeuronModel;
+use crate::models::neuron::NeuronState;
+
+/// Morris–Lecar neuron with the same synaptic gate and mean-field coupling as `FhnParameters`.
+/// The state reuses `NeuronState`: v is the membrane potential (mV), w the fraction of open
+/// potassium channels and y the synaptic gate. Time is in ms.
+///
+/// C = membrane capacitance
+/// gL, gCa, gK = maximal conductances of the leak, calcium and potassium currents
+/// VL, VCa, VK = reversal potentials of the leak, calcium and potassium currents
+/// V1, V2, V3, V4 = half-activation and slope of the steady-state calcium and potassium gates
+/// phi = rate scale of the potassium gate
+/// Vrev, ar, ad, Tmax, lambda, VT, J, Iext = as in `FhnParameters`
+#[derive(Debug, Clone, Copy)]
+#[allow(non_snake_case)]
+pub struct MorrisLecarParameters {
+    pub C: f64,
+    pub gL: f64,
+    pub gCa: f64,
+    pub gK: f64,
+    pub VL: f64,
+    pub VCa: f64,
+    pub VK: f64,
+    pub V1: f64,
+    pub V2: f64,
+    pub V3: f64,
+    pub V4: f64,
+    pub phi: f64,
+    pub Vrev: f64,
+    pub ar: f64,
+    pub ad: f64,
+    pub Tmax: f64,
+    pub lambda: f64,
+    pub VT: f64,
+    pub J: f64,
+    pub Iext: f64,
+}
+
+/// Class II (Hopf) parameter set of Rinzel and Ermentrout, with fast excitatory (AMPA-like) synapses
+impl Default for MorrisLecarParameters {
+    fn default() -> Self {
+        MorrisLecarParameters {
+            C: 20.0,
+            gL: 2.0,
+            gCa: 4.4,
+            gK: 8.0,
+            VL: -60.0,
+            VCa: 120.0,
+            VK: -84.0,
+            V1: -1.2,
+            V2: 18.0,
+            V3: 2.0,
+            V4: 30.0,
+            phi: 0.04,
+            Vrev: 0.0,
+            ar: 1.1,
+            ad: 0.19,
+            Tmax: 1.0,
+            lambda: 0.2,
+            VT: 2.0,
+            J: 0.5,
+            Iext: 90.0,
+        }
+    }
+}
+
+impl MorrisLecarParameters {
+    fn m_inf(&self, v: f64) -> f64 {
+        0.5 * (1.0 + ((v - self.V1) / self.V2).tanh())
+    }
+
+    fn w_inf(&self, v: f64) -> f64 {
+        0.5 * (1.0 + ((v - self.V3) / self.V4).tanh())
+    }
+
+    fn tau_w(&self, v: f64) -> f64 {
+        1.0 / ((v - self.V3) / (2.0 * self.V4)).cosh()
+    }
+
+    fn release(&self, v: f64) -> f64 {
+        self.Tmax / (1.0 + (-self.lambda * (v - self.VT)).exp())
+    }
+
+    /// Rest state of an uncoupled neuron without external current, found by
+    /// integrating the single-neuron dynamics until they settle.
+    pub fn resting_state(&self) -> NeuronState {
+        let uncoupled = MorrisLecarParameters { Iext: 0.0, J: 0.0, ..*self };
+        let mut state = NeuronState { v: self.VL, w: self.w_inf(self.VL), y: 0.0 };
+        for _ in 0..100_000 {
+            let d = uncoupled.drift(&state, 0.0);
+            state.v += 0.05 * d.v;
+            state.w += 0.05 * d.w;
+            state.y += 0.05 * d.y;
+        }
+        state
+    }
+}
+
+impl NeuronModel for MorrisLecarParameters {
+    type State = NeuronState;
+
+    const PARAMETERS: &'static [&'static str] = &["C", "gL", "gCa", "gK", "VL", "VCa", "VK", "V1", "V2", "V3", "V4", "phi", "Vrev", "ar", "ad", "Tmax", "lambda", "VT", "J", "Iext"];
+
+    fn parameter_mut(&mut self, name: &str) -> Option<&mut f64> {
+        match name {
+            "C" => Some(&mut self.C),
+            "gL" => Some(&mut self.gL),
+            "gCa" => Some(&mut self.gCa),
+            "gK" => Some(&mut self.gK),
+            "VL" => Some(&mut self.VL),
+            "VCa" => Some(&mut self.VCa),
+            "VK" => Some(&mut self.VK),
+            "V1" => Some(&mut self.V1),
+            "V2" => Some(&mut self.V2),
+            "V3" => Some(&mut self.V3),
+            "V4" => Some(&mut self.V4),
+            "phi" => Some(&mut self.phi),
+            "Vrev" => Some(&mut self.Vrev),
+            "ar" => Some(&mut self.ar),
+            "ad" => Some(&mut self.ad),
+            "Tmax" => Some(&mut self.Tmax),
+            "lambda" => Some(&mut self.lambda),
+            "VT" => Some(&mut self.VT),
+            "J" => Some(&mut self.J),
+            "Iext" => Some(&mut self.Iext),
+            _ => None,
+        }
+    }
+
+    fn drift(&self, state: &NeuronState, mean_y: f64) -> NeuronState {
+        let v = state.v;
+        let w = state.w;
+        let y = state.y;
+
+        // External current + mean-field coupling
+        let input = self.Iext - self.J * (v - self.Vrev) * mean_y;
+        let ionic = self.gL * (v - self.VL)
+            + self.gCa * self.m_inf(v) * (v - self.VCa)
+            + self.gK * w * (v - self.VK);
+
+        let dv = (input - ionic) / self.C;
+        let dw = self.phi * (self.w_inf(v) - w) / self.tau_w(v);
+        let dy = self.ar * self.release(v) * (1.0 - y) - self.ad * y;
+
+        NeuronState { v: dv, w: dw, y: dy }
+    }
+
+    /// Current noise on the membrane potential
+    fn diffusion(&self, _state: &NeuronState) -> NeuronState {
+        NeuronState { v: 1.0 / self.C, w: 0.0, y: 0.0 }
+    }
+
+    fn drift_mean_field_derivative(&self, state: &NeuronState, _mean_y: f64) -> NeuronState {
+        NeuronState { v: -self.J * (state.v - self.Vrev) / self.C, w: 0.0, y: 0.0 }
+    }
+
+    fn coupling_observable(&self, state: &NeuronState) -> f64 {
+        state.y
+    }
+
+    fn coupling_observable_gradient(&self, _state: &NeuronState) -> NeuronState {
+        NeuronState { v: 0.0, w: 0.0, y: 1.0 }
+    }
+}
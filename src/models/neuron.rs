@@ -1,15 +1,48 @@
 // src/models/neuron.rs
 
-#[derive(Debug,Clone, Copy)]
+use nalgebra::DMatrix;
+use std::ops::{Index, IndexMut};
+use crate::models::model::{ModelState, NeuronModel};
+
+#[derive(Debug, Clone, Copy, Default)]
 pub struct NeuronState {
     pub v: f64, // Membrane potential
     pub w: f64, // Recovery variable
     pub y: f64, // Synaptic gate
 }
- 
+
+impl Index<usize> for NeuronState {
+    type Output = f64;
+
+    fn index(&self, k: usize) -> &f64 {
+        match k {
+            0 => &self.v,
+            1 => &self.w,
+            2 => &self.y,
+            _ => panic!("NeuronState has 3 components, got index {}", k),
+        }
+    }
+}
+
+impl IndexMut<usize> for NeuronState {
+    fn index_mut(&mut self, k: usize) -> &mut f64 {
+        match k {
+            0 => &mut self.v,
+            1 => &mut self.w,
+            2 => &mut self.y,
+            _ => panic!("NeuronState has 3 components, got index {}", k),
+        }
+    }
+}
+
+impl ModelState for NeuronState {
+    const DIM: usize = 3;
+    const COMPONENTS: &'static [&'static str] = &["v", "w", "y"];
+}
+
 /// We now define the Parameters Struct, which holds all constants from the FHN model.
 /// This struct will be passed to the simulation functions.
-/// 
+///
 /// The significance of each parameter is roughly described here (see the AMOP paper for details):
 /// a,b,c,I,sigex = parameter of non coupled FitzHugh-Nagumo model
 /// J, sigJ = synaptical weights
@@ -33,8 +66,22 @@ pub struct FhnParameters {
     pub Iext: f64,
 }
 
+impl FhnParameters {
+    /// Sigmoid release of neurotransmitter and its derivative w.r.t. v
+    fn release(&self, v: f64) -> (f64, f64) {
+        let s = self.Tmax / (1.0 + (-self.lambda * (v - self.VT)).exp());
+        (s, self.lambda * s * (1.0 - s / self.Tmax))
+    }
+
+    /// Opening y∞(v) of the synaptic gate at rest for a fixed potential v
+    pub fn gate_steady_state(&self, v: f64) -> f64 {
+        let (s, _) = self.release(v);
+        self.ar * s / (self.ar * s + self.ad)
+    }
+}
+
 /// The next step is to implement the drift function:
-/// Add a method to compute the deterministic drift of a neuron's state. 
+/// Add a method to compute the deterministic drift of a neuron's state.
 /// The mean_y variable is the average from the other neurons (mean field term)
 impl NeuronState {
     pub fn drift(&self, mean_y: f64, params: &FhnParameters) -> NeuronState {
@@ -53,3 +100,87 @@ impl NeuronState {
         NeuronState { v: dv, w: dw, y: dy }
     }
 }
+
+/// FitzHugh–Nagumo neurons coupled through the mean synaptic gate
+impl NeuronModel for FhnParameters {
+    type State = NeuronState;
+
+    const PARAMETERS: &'static [&'static str] = &["a", "b", "c", "Vrev", "ar", "ad", "Tmax", "lambda", "VT", "J", "Iext"];
+
+    fn parameter_mut(&mut self, name: &str) -> Option<&mut f64> {
+        match name {
+            "a" => Some(&mut self.a),
+            "b" => Some(&mut self.b),
+            "c" => Some(&mut self.c),
+            "Vrev" => Some(&mut self.Vrev),
+            "ar" => Some(&mut self.ar),
+            "ad" => Some(&mut self.ad),
+            "Tmax" => Some(&mut self.Tmax),
+            "lambda" => Some(&mut self.lambda),
+            "VT" => Some(&mut self.VT),
+            "J" => Some(&mut self.J),
+            "Iext" => Some(&mut self.Iext),
+            _ => None,
+        }
+    }
+
+    fn drift(&self, state: &NeuronState, mean_y: f64) -> NeuronState {
+        state.drift(mean_y, self)
+    }
+
+    /// Additive noise on the membrane potential only
+    fn diffusion(&self, _state: &NeuronState) -> NeuronState {
+        NeuronState { v: 1.0, w: 0.0, y: 0.0 }
+    }
+
+    fn drift_jacobian(&self, state: &NeuronState, mean_y: f64) -> DMatrix<f64> {
+        let (s, ds) = self.release(state.v);
+        DMatrix::from_row_slice(3, 3, &[
+            1.0 - state.v.powi(2) - self.J * mean_y, -1.0, 0.0,
+            self.c, -self.c * self.b, 0.0,
+            self.ar * ds * (1.0 - state.y), 0.0, -self.ar * s - self.ad,
+        ])
+    }
+
+    fn drift_mean_field_derivative(&self, state: &NeuronState, _mean_y: f64) -> NeuronState {
+        NeuronState { v: -self.J * (state.v - self.Vrev), w: 0.0, y: 0.0 }
+    }
+
+    fn coupling_observable(&self, state: &NeuronState) -> f64 {
+        state.y
+    }
+
+    fn coupling_observable_gradient(&self, _state: &NeuronState) -> NeuronState {
+        NeuronState { v: 0.0, w: 0.0, y: 1.0 }
+    }
+}
+
+
+/// Parameters of the paper, for the tests
+#[cfg(test)]
+pub(crate) fn test_parameters() -> FhnParameters {
+    FhnParameters {
+        a: 0.7,
+        b: 0.8,
+        c: 0.08,
+        Vrev: 1.2,
+        ar: 1.0,
+        ad: 0.3,
+        Tmax: 1.0,
+        lambda: 0.1,
+        VT: 2.0,
+        J: 0.46,
+        Iext: 0.5,
+    }
+}
+
+/// A few states spread around the resting state, so that the mean field matters in the tests
+#[cfg(test)]
+pub(crate) fn test_states() -> Vec<NeuronState> {
+    vec![
+        NeuronState { v: -1.2, w: -0.3, y: 0.2 },
+        NeuronState { v: -0.8, w: 0.1, y: 0.6 },
+        NeuronState { v: 0.4, w: 0.5, y: 0.4 },
+        NeuronState { v: 1.5, w: 0.9, y: 0.8 },
+    ]
+}
@@ -0,0 +1,471 @@
+// src/models/population.rs
+
+use nalgebra::DMatrix;
+use rand::distributions::Uniform;
+use rand::Rng;
+use rand_distr::{Distribution, LogNormal, Normal};
+use std::str::FromStr;
+use crate::models::connectivity::{Connectivity, PopulationBlocks};
+use crate::models::delay::SynapticDelay;
+use crate::models::schedule::{ParameterSchedule, SIGMA_EXT};
+use crate::models::spatial::Lattice;
+use crate::models::model::{ModelState, NeuronModel};
+
+/// Law of a parameter across the population
+#[derive(Debug, Clone, Copy)]
+pub enum ParameterDistribution {
+    Normal { mean: f64, std: f64 },
+    Uniform { low: f64, high: f64 },
+    /// exp(N(mu, sigma²))
+    LogNormal { mu: f64, sigma: f64 },
+}
+
+impl ParameterDistribution {
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        match *self {
+            ParameterDistribution::Normal { mean, std } => Normal::new(mean, std).unwrap().sample(rng),
+            ParameterDistribution::Uniform { low, high } => Uniform::new_inclusive(low, high).sample(rng),
+            ParameterDistribution::LogNormal { mu, sigma } => LogNormal::new(mu, sigma).unwrap().sample(rng),
+        }
+    }
+}
+
+/// A parameter of the neuron model drawn independently for each neuron,
+/// written on the command line as `name=law:arg1,arg2`, e.g. `Iext=normal:0.5,0.05`
+/// (laws: `normal:mean,std`, `uniform:low,high`, `lognormal:mu,sigma`).
+#[derive(Debug, Clone)]
+pub struct ParameterDraw {
+    pub name: String,
+    pub distribution: ParameterDistribution,
+}
+
+impl FromStr for ParameterDraw {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, law) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected name=law:arg1,arg2, got '{}'", s))?;
+        let (kind, args) = law
+            .split_once(':')
+            .ok_or_else(|| format!("expected law:arg1,arg2, got '{}'", law))?;
+        let args = args
+            .split(',')
+            .map(|a| a.trim().parse::<f64>().map_err(|e| format!("invalid number '{}': {}", a, e)))
+            .collect::<Result<Vec<f64>, String>>()?;
+        if args.len() != 2 {
+            return Err(format!("law '{}' takes 2 arguments, got {}", kind, args.len()));
+        }
+        let (p, q) = (args[0], args[1]);
+        let finite = p.is_finite() && q.is_finite();
+
+        let distribution = match kind {
+            "normal" if finite && q >= 0.0 => ParameterDistribution::Normal { mean: p, std: q },
+            "uniform" if finite && p <= q => ParameterDistribution::Uniform { low: p, high: q },
+            "lognormal" if finite && q >= 0.0 => ParameterDistribution::LogNormal { mu: p, sigma: q },
+            "normal" | "uniform" | "lognormal" => return Err(format!("invalid arguments for law '{}'", kind)),
+            _ => return Err(format!("unknown law '{}', expected normal, uniform or lognormal", kind)),
+        };
+
+        Ok(ParameterDraw { name: name.trim().to_string(), distribution })
+    }
+}
+
+/// One population of a multi-population network: `size` neurons sharing the parameters `params`.
+/// Its `Vrev` is the reversal potential of the synapses it makes onto the other populations.
+#[derive(Debug, Clone)]
+pub struct Subpopulation<N: NeuronModel> {
+    pub name: String,
+    pub params: N,
+    pub size: usize,
+}
+
+/// New value of a model parameter, as given on the command line: `PARAM=VALUE`
+#[derive(Debug, Clone)]
+pub struct ParameterOverride {
+    pub name: String,
+    pub value: f64,
+}
+
+impl FromStr for ParameterOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected PARAM=VALUE, got '{}'", s))?;
+        let value = value
+            .trim()
+            .parse::<f64>()
+            .map_err(|e| format!("invalid number '{}': {}", value, e))?;
+        Ok(ParameterOverride { name: name.trim().to_string(), value })
+    }
+}
+
+/// Population as given on the command line: `NAME:SIZE[:PARAM=VALUE,...]`,
+/// e.g. `I:20:Vrev=-2.0,J=0.8`; the listed parameters override the model defaults.
+#[derive(Debug, Clone)]
+pub struct SubpopulationSpec {
+    pub name: String,
+    pub size: usize,
+    pub overrides: Vec<(String, f64)>,
+}
+
+impl FromStr for SubpopulationSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.splitn(3, ':');
+        let name = fields.next().unwrap_or("").trim();
+        let size = fields
+            .next()
+            .ok_or_else(|| format!("expected NAME:SIZE[:PARAM=VALUE,...], got '{}'", s))?;
+        let size: usize = size
+            .trim()
+            .parse()
+            .map_err(|e| format!("invalid population size '{}': {}", size, e))?;
+        if name.is_empty() || size == 0 {
+            return Err(format!("population '{}' needs a name and at least one neuron", s));
+        }
+
+        let overrides = match fields.next() {
+            Some(overrides) => overrides
+                .split(',')
+                .map(|o| o.parse::<ParameterOverride>().map(|o| (o.name, o.value)))
+                .collect::<Result<Vec<(String, f64)>, String>>()?,
+            None => Vec::new(),
+        };
+
+        Ok(SubpopulationSpec { name: name.to_string(), size, overrides })
+    }
+}
+
+impl SubpopulationSpec {
+    /// Population with the parameters `defaults`, overridden as specified
+    pub fn build<N: NeuronModel>(&self, defaults: &N) -> Result<Subpopulation<N>, String> {
+        let mut params = defaults.clone();
+        for (name, value) in &self.overrides {
+            params.set_parameter(name, *value)?;
+        }
+        Ok(Subpopulation { name: self.name.clone(), params, size: self.size })
+    }
+}
+
+/// Population-by-population coupling strengths J_kl, given row by row on the command line:
+/// rows (separated by `;`) are postsynaptic populations, columns (separated by `,`) presynaptic
+/// ones, e.g. `0.5,0.8;0.5,0.8`.
+#[derive(Debug, Clone)]
+pub struct CouplingMatrix(pub Vec<Vec<f64>>);
+
+impl FromStr for CouplingMatrix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows = s
+            .split(';')
+            .map(|row| {
+                row.split(',')
+                    .map(|a| a.trim().parse::<f64>().map_err(|e| format!("invalid number '{}': {}", a, e)))
+                    .collect::<Result<Vec<f64>, String>>()
+            })
+            .collect::<Result<Vec<Vec<f64>>, String>>()?;
+        if rows.iter().any(|row| row.len() != rows.len()) {
+            return Err(format!("coupling matrix '{}' is not square", s));
+        }
+        Ok(CouplingMatrix(rows))
+    }
+}
+
+/// Parameters of every neuron of the population (neuron i uses `neurons[i]`),
+/// the network through which they are coupled, the delay of the synapses and
+/// the parameters that vary in time.
+#[derive(Debug, Clone)]
+pub struct Population<N: NeuronModel> {
+    pub neurons: Vec<N>,
+    pub connectivity: Connectivity,
+    pub delay: SynapticDelay,
+    pub schedules: Vec<ParameterSchedule>,
+}
+
+impl<N: NeuronModel> Population<N> {
+    /// All neurons share the same parameters and are coupled through the mean field
+    pub fn homogeneous(params: &N, size: usize) -> Self {
+        Population {
+            neurons: vec![params.clone(); size],
+            connectivity: Connectivity::MeanField,
+            delay: SynapticDelay::None,
+            schedules: Vec::new(),
+        }
+    }
+
+    /// Replaces the all-to-all coupling by the given network
+    pub fn with_connectivity(mut self, connectivity: Connectivity) -> Self {
+        self.connectivity = connectivity;
+        self
+    }
+
+    /// Delays the synaptic transmission
+    pub fn with_delay(mut self, delay: SynapticDelay) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Lets parameters follow time profiles; they override the values of every neuron, so only
+    /// parameters shared by all neurons can be scheduled (not those drawn per neuron or set per
+    /// population). Between populations, J and Vrev come from the coupling of the populations
+    /// and cannot be scheduled either.
+    pub fn with_schedules(mut self, schedules: Vec<ParameterSchedule>) -> Result<Self, String> {
+        for schedule in &schedules {
+            if schedule.name != SIGMA_EXT && !N::PARAMETERS.contains(&schedule.name.as_str()) {
+                return Err(format!(
+                    "unknown parameter '{}', expected {} or one of {}",
+                    schedule.name,
+                    SIGMA_EXT,
+                    N::PARAMETERS.join(", ")
+                ));
+            }
+            if matches!(self.connectivity, Connectivity::Populations(_)) && (schedule.name == "J" || schedule.name == "Vrev") {
+                return Err(format!("{} is set by the coupling between populations and cannot be scheduled", schedule.name));
+            }
+            let mut values = self.neurons.iter().filter_map(|neuron| neuron.parameter(&schedule.name));
+            let first = values.next();
+            if values.any(|value| Some(value) != first) {
+                return Err(format!(
+                    "{} differs between neurons (drawn per neuron or set per population) and cannot be scheduled",
+                    schedule.name
+                ));
+            }
+        }
+        self.schedules = schedules;
+        Ok(self)
+    }
+
+    /// Sets the scheduled parameters of every neuron to their value at the given time
+    pub fn apply_schedules(&mut self, time: f64) {
+        for schedule in self.schedules.iter().filter(|s| s.name != SIGMA_EXT) {
+            let value = schedule.profile.value(time);
+            for neuron in self.neurons.iter_mut() {
+                *neuron.parameter_mut(&schedule.name).expect("checked by with_schedules") = value;
+            }
+        }
+    }
+
+    /// Noise intensity at the given time: its schedule if any, `default` otherwise
+    pub fn sigma_ext_at(&self, time: f64, default: f64) -> f64 {
+        self.schedules
+            .iter()
+            .find(|s| s.name == SIGMA_EXT)
+            .map_or(default, |s| s.profile.value(time))
+    }
+
+    /// The same population seen from `time` on: its schedules are shifted so that a simulation
+    /// started at time zero continues from `time`
+    pub fn starting_at(&self, time: f64) -> Self {
+        let mut population = self.clone();
+        for schedule in population.schedules.iter_mut() {
+            schedule.profile = schedule.profile.shifted(time);
+        }
+        population
+    }
+
+    /// Starts from `params` and redraws the listed parameters independently for each neuron
+    pub fn heterogeneous<R: Rng + ?Sized>(
+        params: &N,
+        size: usize,
+        draws: &[ParameterDraw],
+        rng: &mut R,
+    ) -> Result<Self, String> {
+        let mut population = Self::homogeneous(params, size);
+        population.redraw(draws, rng)?;
+        Ok(population)
+    }
+
+    /// Populations of neurons stacked in the given order and coupled all-to-all,
+    /// with strength J_kl = `weights[k][l]` from population l onto population k
+    /// (by default, the `J` of population k for every l)
+    pub fn from_subpopulations(
+        subpopulations: &[Subpopulation<N>],
+        weights: Option<Vec<Vec<f64>>>,
+    ) -> Result<Self, String> {
+        let count = subpopulations.len();
+        let weights = match weights {
+            Some(weights) if weights.len() != count || weights.iter().any(|row| row.len() != count) => {
+                return Err(format!("expected a {}x{} coupling matrix", count, count));
+            }
+            Some(weights) => weights,
+            None => subpopulations
+                .iter()
+                .map(|sub| sub.params.parameter("J").map(|j| vec![j; count]))
+                .collect::<Option<Vec<Vec<f64>>>>()
+                .ok_or("the neuron model has no coupling strength J")?,
+        };
+        let reversal = subpopulations
+            .iter()
+            .map(|sub| sub.params.parameter("Vrev"))
+            .collect::<Option<Vec<f64>>>()
+            .ok_or("the neuron model has no reversal potential Vrev")?;
+
+        let mut offsets = vec![0];
+        let mut neurons = Vec::new();
+        for sub in subpopulations {
+            neurons.extend(vec![sub.params.clone(); sub.size]);
+            offsets.push(neurons.len());
+        }
+
+        Ok(Population {
+            neurons,
+            connectivity: Connectivity::Populations(PopulationBlocks {
+                names: subpopulations.iter().map(|sub| sub.name.clone()).collect(),
+                offsets,
+                weights,
+                reversal,
+            }),
+            delay: SynapticDelay::None,
+            schedules: Vec::new(),
+        })
+    }
+
+    /// Redraws the listed parameters independently for each neuron. Between populations, the
+    /// synapses take J and Vrev from the coupling of the populations, so these cannot be drawn.
+    pub fn redraw<R: Rng + ?Sized>(&mut self, draws: &[ParameterDraw], rng: &mut R) -> Result<(), String> {
+        if matches!(self.connectivity, Connectivity::Populations(_)) {
+            if let Some(draw) = draws.iter().find(|draw| draw.name == "J" || draw.name == "Vrev") {
+                return Err(format!(
+                    "{} is set by the coupling between populations and cannot be drawn per neuron, use --coupling or the population parameters",
+                    draw.name
+                ));
+            }
+        }
+        for neuron in self.neurons.iter_mut() {
+            for draw in draws {
+                neuron.set_parameter(&draw.name, draw.distribution.sample(rng))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.neurons.len()
+    }
+
+    /// Positions of the neurons: the lattice of a spatial kernel, neuron indices on a ring otherwise
+    pub fn lattice(&self) -> Lattice {
+        match &self.connectivity {
+            Connectivity::Kernel(kernel) => kernel.lattice,
+            _ => Lattice::Ring(self.len()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.neurons.is_empty()
+    }
+
+    /// Neuron i as seen through input channel c: between populations, its synapses from
+    /// population c have strength J_kc and the reversal potential of population c
+    fn synapse(&self, i: usize, c: usize) -> N {
+        let mut neuron = self.neurons[i].clone();
+        if let Connectivity::Populations(blocks) = &self.connectivity {
+            let k = blocks.population_of(i);
+            neuron
+                .set_parameter("J", blocks.weights[k][c])
+                .and_then(|_| neuron.set_parameter("Vrev", blocks.reversal[c]))
+                .expect("coupling between populations requires the parameters J and Vrev");
+        }
+        neuron
+    }
+
+    /// Drift of neuron i given its synaptic inputs (one per channel of the connectivity).
+    /// The drift is affine in the input, so that the channels add up:
+    /// f(x, 0) + Σ_c u_c ∂f_c/∂u(x).
+    pub fn drift(&self, i: usize, state: &N::State, inputs: &[f64]) -> N::State {
+        if !matches!(self.connectivity, Connectivity::Populations(_)) {
+            return self.neurons[i].drift(state, inputs[0]);
+        }
+        let mut drift = self.neurons[i].drift(state, 0.0);
+        for (dfdu, u) in self.drift_input_derivatives(i, state, inputs).iter().zip(inputs) {
+            for k in 0..N::State::DIM {
+                drift[k] += u * dfdu[k];
+            }
+        }
+        drift
+    }
+
+    /// Jacobian of `drift` w.r.t. the state of neuron i
+    pub fn drift_jacobian(&self, i: usize, state: &N::State, inputs: &[f64]) -> DMatrix<f64> {
+        if !matches!(self.connectivity, Connectivity::Populations(_)) {
+            return self.neurons[i].drift_jacobian(state, inputs[0]);
+        }
+        let mut jac = self.neurons[i].drift_jacobian(state, 0.0);
+        for (c, &u) in inputs.iter().enumerate() {
+            let synapse = self.synapse(i, c);
+            jac += synapse.drift_jacobian(state, u) - synapse.drift_jacobian(state, 0.0);
+        }
+        jac
+    }
+
+    /// Derivatives of `drift` w.r.t. each synaptic input of neuron i
+    pub fn drift_input_derivatives(&self, i: usize, state: &N::State, inputs: &[f64]) -> Vec<N::State> {
+        inputs
+            .iter()
+            .enumerate()
+            .map(|(c, &u)| self.synapse(i, c).drift_mean_field_derivative(state, u))
+            .collect()
+    }
+
+    /// Writes one row per neuron with all model parameters
+    pub fn save_to_csv(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut wtr = csv::Writer::from_path(path)?;
+
+        let mut header = vec!["neuron_id"];
+        header.extend_from_slice(N::PARAMETERS);
+        wtr.write_record(&header)?;
+
+        for (neuron_id, neuron) in self.neurons.iter().enumerate() {
+            let mut row = vec![neuron_id.to_string()];
+            row.extend(N::PARAMETERS.iter().map(|name| neuron.parameter(name).unwrap().to_string()));
+            wtr.write_record(&row)?;
+        }
+
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::neuron::test_parameters;
+
+    #[test]
+    fn parameter_draws_need_finite_arguments() {
+        let draw: ParameterDraw = "Iext=uniform:0.4, 0.6".parse().unwrap();
+        assert_eq!(draw.name, "Iext");
+        assert!(matches!(draw.distribution, ParameterDistribution::Uniform { low, high } if low == 0.4 && high == 0.6));
+
+        for invalid in ["Iext=uniform:0,inf", "Iext=normal:0.5,inf", "Iext=lognormal:NaN,0.1", "Iext=uniform:1,0", "Iext=normal:0.5"] {
+            assert!(invalid.parse::<ParameterDraw>().is_err(), "{} was accepted", invalid);
+        }
+    }
+
+    #[test]
+    fn coupling_matrices_are_read_row_by_row() {
+        let CouplingMatrix(rows) = "0.5, 1.2; 0.9,0.3".parse().unwrap();
+        assert_eq!(rows, vec![vec![0.5, 1.2], vec![0.9, 0.3]]);
+
+        for invalid in ["0.5,1.2;0.9", "0.5,1.2", "0.5,x;0.9,0.3"] {
+            assert!(invalid.parse::<CouplingMatrix>().is_err(), "{} was accepted", invalid);
+        }
+    }
+
+    #[test]
+    fn only_shared_parameters_can_be_scheduled() {
+        let params = test_parameters();
+        let schedule: ParameterSchedule = "Iext=linear:0:0.3,10:0.8".parse().unwrap();
+        let draw: ParameterDraw = "Iext=uniform:0.4,0.6".parse().unwrap();
+
+        assert!(Population::homogeneous(&params, 4).with_schedules(vec![schedule.clone()]).is_ok());
+        let drawn = Population::heterogeneous(&params, 4, &[draw], &mut rand::thread_rng()).unwrap();
+        assert!(drawn.with_schedules(vec![schedule]).is_err());
+    }
+}
@@ -1,6 +1,10 @@
 // src/models/reference.rs
 
 use plotters::prelude::*;
+use std::str::FromStr;
+use crate::draw_figure;
+use crate::models::spatial::{periodic_offset, Lattice};
+use crate::plotting::PlotStyle;
 
 
 pub fn reference_profile(t: f64, t_final: f64) -> f64 {
@@ -13,11 +17,9 @@ pub fn reference_profile(t: f64, t_final: f64) -> f64 {
 pub fn plot_reference_profile(
     t_final: f64,
     dt: f64,
+    style: &PlotStyle,
     filename: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let root = BitMapBackend::new(filename, (800, 600)).into_drawing_area();
-    root.fill(&WHITE)?;
-
     let t_values: Vec<f64> = (0..=(t_final / dt) as usize)
         .map(|i| i as f64 * dt)
         .collect();
@@ -30,22 +32,111 @@ pub fn plot_reference_profile(
     let v_min = v_values.iter().cloned().fold(f64::INFINITY, f64::min);
     let v_max = v_values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
 
-    let mut chart = ChartBuilder::on(&root)
-        .caption("Reference Profile v_ref(t)", ("sans-serif", 30))
-        .margin(20)
-        .x_label_area_size(40)
-        .y_label_area_size(50)
-        .build_cartesian_2d(0.0..t_final, v_min..v_max)?;
+    draw_figure!(style, filename, |root| {
+        let mut chart = style
+            .chart(&root, "Reference Profile v_ref(t)")
+            .build_cartesian_2d(0.0..t_final, v_min..v_max)?;
+
+        chart.configure_mesh().label_style(style.label_font()).draw()?;
+
+        let line = style.line(2, 0);
+        chart.draw_series(LineSeries::new(
+            t_values.iter().zip(v_values.iter()).map(|(&t, &v)| (t, v)),
+            line,
+        ))?
+        .label("v_ref(t)")
+        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], line));
+
+        chart.configure_series_labels().label_font(style.label_font()).border_style(BLACK).draw()?;
+        Ok(())
+    })
+}
+
+
+/// Target potential v*_i(t) of every neuron (rows) at every time step (columns)
+pub type ReferencePattern = Vec<Vec<f64>>;
+
+
+/// A bump with the height of the reference profile travelling along the x axis of the lattice,
+/// at `speed` sites per unit of time and with Gaussian width `width` (in sites)
+pub fn traveling_wave_pattern(lattice: &Lattice, m: usize, dt: f64, speed: f64, width: f64) -> ReferencePattern {
+    let (nx, _) = lattice.dims();
+    (0..lattice.size())
+        .map(|i| {
+            let x = lattice.position(i).0 as f64;
+            (0..m)
+                .map(|t| {
+                    let d = periodic_offset(x - speed * t as f64 * dt, nx);
+                    1.5 * (-d * d / (2.0 * width * width)).exp()
+                })
+                .collect()
+        })
+        .collect()
+}
+
 
-    chart.configure_mesh().draw()?;
+/// Potentials of `size` neurons read from a CSV with columns `neuron_id` and `v` (e.g. written by
+/// `save_simulation_to_csv`); the first m rows of every neuron are kept
+pub fn load_pattern_from_csv(path: &str, size: usize, m: usize) -> Result<ReferencePattern, Box<dyn std::error::Error>> {
+    let mut rdr = csv::Reader::from_path(path)?;
 
-    chart.draw_series(LineSeries::new(
-        t_values.iter().zip(v_values.iter()).map(|(&t, &v)| (t, v)),
-        &GREEN,
-    ))?
-    .label("v_ref(t)")
-    .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &GREEN));
+    let header = rdr.headers()?.clone();
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|h| h == name)
+            .ok_or_else(|| format!("column '{}' missing in {}", name, path))
+    };
+    let id_col = column("neuron_id")?;
+    let v_col = column("v")?;
+
+    let mut pattern: ReferencePattern = vec![Vec::with_capacity(m); size];
+    for record in rdr.records() {
+        let record = record?;
+        let neuron_id: usize = record[id_col].parse()?;
+        if neuron_id >= size {
+            return Err(format!("neuron {} out of range for {} neurons in {}", neuron_id, size, path).into());
+        }
+        if pattern[neuron_id].len() < m {
+            pattern[neuron_id].push(record[v_col].parse()?);
+        }
+    }
+
+    if let Some(i) = pattern.iter().position(|row| row.len() < m) {
+        return Err(format!("neuron {} has fewer than {} time steps in {}", i, m, path).into());
+    }
+    Ok(pattern)
+}
+
+
+/// Spatio-temporal target as given on the command line: `wave:SPEED,WIDTH` or `csv:PATH`
+#[derive(Debug, Clone)]
+pub enum PatternSpec {
+    Wave(f64, f64),
+    Csv(String),
+}
+
+impl FromStr for PatternSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, args) = s.split_once(':').unwrap_or((s, ""));
+        let numbers: Vec<f64> = args.split(',').filter_map(|a| a.trim().parse().ok()).collect();
+
+        match (kind, numbers.as_slice()) {
+            ("wave", &[speed, width]) if width > 0.0 => Ok(PatternSpec::Wave(speed, width)),
+            ("csv", _) if !args.is_empty() => Ok(PatternSpec::Csv(args.to_string())),
+            _ => Err(format!("unknown target pattern '{}', expected wave:SPEED,WIDTH or csv:PATH", s)),
+        }
+    }
+}
 
-    chart.configure_series_labels().border_style(&BLACK).draw()?;
-    Ok(())
+impl PatternSpec {
+    pub fn build(&self, lattice: &Lattice, m: usize, dt: f64) -> Result<ReferencePattern, String> {
+        match self {
+            PatternSpec::Wave(speed, width) => Ok(traveling_wave_pattern(lattice, m, dt, *speed, *width)),
+            PatternSpec::Csv(path) => load_pattern_from_csv(path, lattice.size(), m)
+                .map_err(|e| format!("failed to load target pattern from {}: {}", path, e)),
+        }
+    }
 }
@@ -0,0 +1,177 @@
+// src/models/schedule.rs
+
+use plotters::prelude::*;
+use std::str::FromStr;
+use crate::draw_figure;
+use crate::plotting::PlotStyle;
+
+/// Name under which the noise intensity can be scheduled like a model parameter
+pub const SIGMA_EXT: &str = "sigma_ext";
+
+/// How a profile is evaluated between its breakpoints
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Each value holds until the next breakpoint
+    Step,
+    Linear,
+}
+
+/// Value of a parameter over time, given at breakpoints (t_k, v_k) with increasing t_k.
+/// Before the first breakpoint the first value applies, after the last one the last value.
+#[derive(Debug, Clone)]
+pub struct TimeProfile {
+    pub times: Vec<f64>,
+    pub values: Vec<f64>,
+    pub interpolation: Interpolation,
+}
+
+impl TimeProfile {
+    pub fn new(points: Vec<(f64, f64)>, interpolation: Interpolation) -> Result<Self, String> {
+        if points.is_empty() {
+            return Err("a time profile needs at least one point".to_string());
+        }
+        if points.windows(2).any(|w| w[1].0 < w[0].0) {
+            return Err("the times of a profile must be increasing".to_string());
+        }
+        let (times, values) = points.into_iter().unzip();
+        Ok(TimeProfile { times, values, interpolation })
+    }
+
+    /// Reads the columns `time` and `column` of a CSV (the second column when `column` is absent),
+    /// interpolated linearly
+    pub fn from_csv(path: &str, column: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut rdr = csv::Reader::from_path(path)?;
+
+        let header = rdr.headers()?.clone();
+        let time_col = header
+            .iter()
+            .position(|h| h == "time")
+            .ok_or_else(|| format!("column 'time' missing in {}", path))?;
+        let value_col = match header.iter().position(|h| h == column) {
+            Some(col) => col,
+            None if header.len() == 2 => 1 - time_col,
+            None => return Err(format!("column '{}' missing in {}", column, path).into()),
+        };
+
+        let mut points = Vec::new();
+        for record in rdr.records() {
+            let record = record?;
+            points.push((record[time_col].parse()?, record[value_col].parse()?));
+        }
+        Ok(Self::new(points, Interpolation::Linear)?)
+    }
+
+    /// The same profile on a time axis starting at `origin`
+    pub fn shifted(&self, origin: f64) -> Self {
+        TimeProfile {
+            times: self.times.iter().map(|t| t - origin).collect(),
+            values: self.values.clone(),
+            interpolation: self.interpolation,
+        }
+    }
+
+    pub fn value(&self, t: f64) -> f64 {
+        let n = self.times.len();
+        // First breakpoint after t
+        let k = self.times.partition_point(|&tk| tk <= t);
+        if k == 0 {
+            return self.values[0];
+        }
+        if k == n {
+            return self.values[n - 1];
+        }
+        match self.interpolation {
+            Interpolation::Step => self.values[k - 1],
+            Interpolation::Linear => {
+                let (t0, t1) = (self.times[k - 1], self.times[k]);
+                let (v0, v1) = (self.values[k - 1], self.values[k]);
+                v0 + (v1 - v0) * (t - t0) / (t1 - t0)
+            }
+        }
+    }
+}
+
+/// A parameter following a time profile, written on the command line as `NAME=SPEC` with SPEC
+/// `linear:T0:V0,T1:V1,..` (ramps), `steps:T0:V0,T1:V1,..` (piecewise constant) or `csv:PATH`
+/// (columns `time` and NAME, or `time` and one value column), e.g. `Iext=linear:0:0.3,50:0.8`.
+/// NAME is a model parameter, or `sigma_ext` for the noise intensity.
+#[derive(Debug, Clone)]
+pub struct ParameterSchedule {
+    pub name: String,
+    pub profile: TimeProfile,
+}
+
+impl FromStr for ParameterSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, spec) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected NAME=SPEC, got '{}'", s))?;
+        let name = name.trim().to_string();
+        let (kind, args) = spec.split_once(':').unwrap_or((spec, ""));
+
+        let points = || {
+            args.split(',')
+                .map(|point| {
+                    let (t, v) = point
+                        .split_once(':')
+                        .ok_or_else(|| format!("expected TIME:VALUE, got '{}'", point))?;
+                    let number = |x: &str| x.trim().parse::<f64>().map_err(|e| format!("invalid number '{}': {}", x, e));
+                    Ok((number(t)?, number(v)?))
+                })
+                .collect::<Result<Vec<(f64, f64)>, String>>()
+        };
+
+        let profile = match kind {
+            "linear" => TimeProfile::new(points()?, Interpolation::Linear)?,
+            "steps" => TimeProfile::new(points()?, Interpolation::Step)?,
+            "csv" if !args.is_empty() => TimeProfile::from_csv(args, &name)
+                .map_err(|e| format!("failed to load schedule from {}: {}", args, e))?,
+            _ => return Err(format!(
+                "unknown schedule '{}', expected linear:T:V,.., steps:T:V,.. or csv:PATH",
+                spec
+            )),
+        };
+
+        Ok(ParameterSchedule { name, profile })
+    }
+}
+
+
+/// Plots the scheduled parameters over [0, t_final]
+pub fn plot_schedules(
+    schedules: &[ParameterSchedule],
+    t_final: f64,
+    dt: f64,
+    style: &PlotStyle,
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let steps = (t_final / dt) as usize;
+    let values = |schedule: &ParameterSchedule| -> Vec<(f64, f64)> {
+        (0..=steps).map(|i| (i as f64 * dt, schedule.profile.value(i as f64 * dt))).collect()
+    };
+    let all: Vec<f64> = schedules.iter().flat_map(|s| values(s).into_iter().map(|(_, v)| v)).collect();
+    let v_min = all.iter().cloned().fold(f64::INFINITY, f64::min);
+    let v_max = all.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let margin = if v_max > v_min { 0.05 * (v_max - v_min) } else { 0.5 };
+
+    draw_figure!(style, filename, |root| {
+        let mut chart = style
+            .chart(&root, "Parameter Schedules")
+            .build_cartesian_2d(0.0..t_final, (v_min - margin)..(v_max + margin))?;
+
+        chart.configure_mesh().label_style(style.label_font()).draw()?;
+
+        for (k, schedule) in schedules.iter().enumerate() {
+            let line = style.line(k, 0);
+            chart
+                .draw_series(LineSeries::new(values(schedule), line))?
+                .label(schedule.name.clone())
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], line));
+        }
+
+        chart.configure_series_labels().label_font(style.label_font()).border_style(BLACK).draw()?;
+        Ok(())
+    })
+}
@@ -0,0 +1,208 @@
+// src/models/spatial.rs
+
+use crate::analysis::fourier::fft;
+use nalgebra::Complex;
+use std::str::FromStr;
+
+/// Positions of the neurons: a ring of n sites or an nx × ny grid (stored row by row),
+/// both with periodic boundaries
+#[derive(Debug, Clone, Copy)]
+pub enum Lattice {
+    Ring(usize),
+    Grid(usize, usize),
+}
+
+impl Lattice {
+    /// Number of sites along each axis
+    pub fn dims(&self) -> (usize, usize) {
+        match *self {
+            Lattice::Ring(n) => (n, 1),
+            Lattice::Grid(nx, ny) => (nx, ny),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        let (nx, ny) = self.dims();
+        nx * ny
+    }
+
+    /// Coordinates (x, y) of site i
+    pub fn position(&self, i: usize) -> (usize, usize) {
+        let (nx, _) = self.dims();
+        (i % nx, i / nx)
+    }
+
+    /// Periodic distance between sites i and j, in lattice units
+    pub fn distance(&self, i: usize, j: usize) -> f64 {
+        let (nx, ny) = self.dims();
+        let (xi, yi) = self.position(i);
+        let (xj, yj) = self.position(j);
+        periodic_offset(xi as f64 - xj as f64, nx).hypot(periodic_offset(yi as f64 - yj as f64, ny))
+    }
+}
+
+/// Shortest signed offset on a periodic axis of length n
+pub fn periodic_offset(d: f64, n: usize) -> f64 {
+    let n = n as f64;
+    d - n * (d / n).round()
+}
+
+/// Coupling profile as a function of distance, widths in lattice units
+#[derive(Debug, Clone, Copy)]
+pub enum KernelShape {
+    Gaussian { sigma: f64 },
+    /// Difference of Gaussians: local excitation minus `ratio` times a wider inhibition
+    MexicanHat { sigma_e: f64, sigma_i: f64, ratio: f64 },
+}
+
+/// `gaussian:SIGMA` or `mexican-hat:SIGMA_E,SIGMA_I,RATIO`
+impl FromStr for KernelShape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, args) = s.split_once(':').unwrap_or((s, ""));
+        let args = args
+            .split(',')
+            .map(|a| a.trim().parse::<f64>().map_err(|e| format!("invalid number '{}': {}", a, e)))
+            .collect::<Result<Vec<f64>, String>>()?;
+
+        match (kind, args.as_slice()) {
+            ("gaussian", &[sigma]) if sigma > 0.0 => Ok(KernelShape::Gaussian { sigma }),
+            ("mexican-hat", &[sigma_e, sigma_i, ratio]) if sigma_e > 0.0 && sigma_i > 0.0 => {
+                Ok(KernelShape::MexicanHat { sigma_e, sigma_i, ratio })
+            }
+            _ => Err(format!(
+                "invalid kernel '{}', expected gaussian:SIGMA or mexican-hat:SIGMA_E,SIGMA_I,RATIO",
+                s
+            )),
+        }
+    }
+}
+
+/// Distance-dependent coupling on a periodic lattice: the input of neuron i is
+/// Σ_j K(x_i - x_j) obs_j, computed as a convolution by FFT.
+/// Each Gaussian is normalized to unit mass, so that a uniform observable y gives the input y
+/// (and (1 - ratio) y for the Mexican hat), as for the mean field.
+#[derive(Debug, Clone)]
+pub struct SpatialKernel {
+    pub lattice: Lattice,
+    pub shape: KernelShape,
+    /// K at each offset (dx, dy), stored like the lattice
+    weights: Vec<f64>,
+    /// FFT sizes along each axis and spectrum of the kernel
+    padded: (usize, usize),
+    spectrum: Vec<Complex<f64>>,
+}
+
+impl SpatialKernel {
+    pub fn new(lattice: Lattice, shape: KernelShape) -> Self {
+        let (nx, ny) = lattice.dims();
+        let gaussian = |sigma: f64| -> Vec<f64> {
+            let values: Vec<f64> = (0..nx * ny)
+                .map(|k| {
+                    let d = lattice.distance(k, 0);
+                    (-d * d / (2.0 * sigma * sigma)).exp()
+                })
+                .collect();
+            let total: f64 = values.iter().sum();
+            values.into_iter().map(|v| v / total).collect()
+        };
+        let weights = match shape {
+            KernelShape::Gaussian { sigma } => gaussian(sigma),
+            KernelShape::MexicanHat { sigma_e, sigma_i, ratio } => gaussian(sigma_e)
+                .into_iter()
+                .zip(gaussian(sigma_i))
+                .map(|(e, i)| e - ratio * i)
+                .collect(),
+        };
+
+        // A circular convolution of length n is read off a linear one against the kernel at
+        // offsets -(n - 1)..=(n - 1); FFTs of size ≥ 2n - 1 hold the needed entries without aliasing.
+        let padded = ((2 * nx - 1).next_power_of_two(), (2 * ny - 1).next_power_of_two());
+        let mut spectrum = vec![Complex::new(0.0, 0.0); padded.0 * padded.1];
+        for my in 0..2 * ny - 1 {
+            for mx in 0..2 * nx - 1 {
+                let dx = (mx + 1) % nx;
+                let dy = (my + 1) % ny;
+                spectrum[my * padded.0 + mx] = Complex::new(weights[dy * nx + dx], 0.0);
+            }
+        }
+        fft2(&mut spectrum, padded, false);
+
+        SpatialKernel { lattice, shape, weights, padded, spectrum }
+    }
+
+    /// Coupling weight K(x_i - x_j) from neuron j onto neuron i
+    pub fn weight(&self, i: usize, j: usize) -> f64 {
+        let (nx, ny) = self.lattice.dims();
+        let (xi, yi) = self.lattice.position(i);
+        let (xj, yj) = self.lattice.position(j);
+        self.weights[((yi + ny - yj) % ny) * nx + (xi + nx - xj) % nx]
+    }
+
+    /// y_i = Σ_j K(x_i - x_j) x_j; the kernel is symmetric, so this is also its own transpose
+    pub fn convolve(&self, x: &[f64]) -> Vec<f64> {
+        let (nx, ny) = self.lattice.dims();
+        let (px, py) = self.padded;
+
+        let mut buffer = vec![Complex::new(0.0, 0.0); px * py];
+        for (i, xi) in x.iter().enumerate() {
+            let (a, b) = self.lattice.position(i);
+            buffer[b * px + a] = Complex::new(*xi, 0.0);
+        }
+        fft2(&mut buffer, self.padded, false);
+        for (b, k) in buffer.iter_mut().zip(self.spectrum.iter()) {
+            *b *= k;
+        }
+        fft2(&mut buffer, self.padded, true);
+
+        (0..x.len())
+            .map(|i| {
+                let (a, b) = self.lattice.position(i);
+                buffer[(b + ny - 1) * px + a + nx - 1].re
+            })
+            .collect()
+    }
+}
+
+/// FFT of a px × py array stored row by row: along x, then along y
+fn fft2(buffer: &mut [Complex<f64>], (px, py): (usize, usize), inverse: bool) {
+    for row in buffer.chunks_mut(px) {
+        fft(row, inverse);
+    }
+    if py > 1 {
+        let mut column = vec![Complex::new(0.0, 0.0); py];
+        for x in 0..px {
+            for y in 0..py {
+                column[y] = buffer[y * px + x];
+            }
+            fft(&mut column, inverse);
+            for y in 0..py {
+                buffer[y * px + x] = column[y];
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fft_convolution_matches_the_periodic_sum() {
+        let shape = KernelShape::MexicanHat { sigma_e: 0.8, sigma_i: 1.6, ratio: 0.4 };
+        for lattice in [Lattice::Ring(7), Lattice::Grid(5, 3)] {
+            let kernel = SpatialKernel::new(lattice, shape);
+            let size = lattice.size();
+            // A bump away from the origin plus a ramp, so that no symmetry hides a misplaced offset
+            let x: Vec<f64> = (0..size).map(|i| if i == size / 2 + 1 { 2.0 } else { 0.1 * i as f64 }).collect();
+
+            let convolved = kernel.convolve(&x);
+            for (i, yi) in convolved.iter().enumerate() {
+                let direct: f64 = (0..size).map(|j| kernel.weight(i, j) * x[j]).sum();
+                assert!((yi - direct).abs() < 1e-12, "{:?}, site {}: {} vs {}", lattice, i, yi, direct);
+            }
+        }
+    }
+}
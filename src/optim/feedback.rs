@@ -0,0 +1,325 @@
+// src/optim/feedback.rs
+
+use plotters::prelude::*;
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+use std::str::FromStr;
+use crate::draw_figure;
+use crate::models::model::ModelState;
+use crate::plotting::PlotStyle;
+use crate::simulations::adjoint::FeedbackSensitivity;
+use crate::simulations::control::ControlChannel;
+
+/// Control law α = π_θ(v̄) of the observed mean potential, with parameters θ
+#[derive(Debug, Clone)]
+pub enum FeedbackPolicy {
+    /// α = offset + gain v̄
+    Linear { offset: f64, gain: f64 },
+    /// One hidden layer of tanh units: α = offset + Σ_h output_h tanh(input_h v̄ + bias_h)
+    Mlp { input: Vec<f64>, bias: Vec<f64>, output: Vec<f64>, offset: f64 },
+}
+
+impl FeedbackPolicy {
+    /// Control for the mean potential v̄
+    pub fn control(&self, v: f64) -> f64 {
+        match self {
+            FeedbackPolicy::Linear { offset, gain } => offset + gain * v,
+            FeedbackPolicy::Mlp { input, bias, output, offset } => {
+                offset + (0..input.len()).map(|h| output[h] * (input[h] * v + bias[h]).tanh()).sum::<f64>()
+            }
+        }
+    }
+
+    /// dπ/dv̄
+    pub fn slope(&self, v: f64) -> f64 {
+        match self {
+            FeedbackPolicy::Linear { gain, .. } => *gain,
+            FeedbackPolicy::Mlp { input, bias, output, .. } => (0..input.len())
+                .map(|h| output[h] * input[h] * (1.0 - (input[h] * v + bias[h]).tanh().powi(2)))
+                .sum(),
+        }
+    }
+
+    /// Parameters θ, in the order of `parameter_gradient`
+    pub fn parameters(&self) -> Vec<f64> {
+        match self {
+            FeedbackPolicy::Linear { offset, gain } => vec![*offset, *gain],
+            FeedbackPolicy::Mlp { input, bias, output, offset } => {
+                let mut theta = Vec::with_capacity(3 * input.len() + 1);
+                theta.extend_from_slice(input);
+                theta.extend_from_slice(bias);
+                theta.extend_from_slice(output);
+                theta.push(*offset);
+                theta
+            }
+        }
+    }
+
+    pub fn set_parameters(&mut self, theta: &[f64]) {
+        match self {
+            FeedbackPolicy::Linear { offset, gain } => {
+                *offset = theta[0];
+                *gain = theta[1];
+            }
+            FeedbackPolicy::Mlp { input, bias, output, offset } => {
+                let h = input.len();
+                input.copy_from_slice(&theta[..h]);
+                bias.copy_from_slice(&theta[h..2 * h]);
+                output.copy_from_slice(&theta[2 * h..3 * h]);
+                *offset = theta[3 * h];
+            }
+        }
+    }
+
+    /// ∇_θ π(v̄)
+    pub fn parameter_gradient(&self, v: f64) -> Vec<f64> {
+        match self {
+            FeedbackPolicy::Linear { .. } => vec![1.0, v],
+            FeedbackPolicy::Mlp { input, bias, output, .. } => {
+                let h = input.len();
+                let mut gradient = vec![0.0; 3 * h + 1];
+                for k in 0..h {
+                    let a = (input[k] * v + bias[k]).tanh();
+                    let da = output[k] * (1.0 - a * a);
+                    gradient[k] = da * v;
+                    gradient[h + k] = da;
+                    gradient[2 * h + k] = a;
+                }
+                gradient[3 * h] = 1.0;
+                gradient
+            }
+        }
+    }
+}
+
+/// Architecture of a feedback policy, as given on the command line: `linear` or `mlp:HIDDEN`
+#[derive(Debug, Clone, Copy)]
+pub enum PolicySpec {
+    Linear,
+    Mlp(usize),
+}
+
+impl FromStr for PolicySpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, args) = s.split_once(':').unwrap_or((s, ""));
+        match kind {
+            "linear" => Ok(PolicySpec::Linear),
+            "mlp" => match args.parse::<usize>() {
+                Ok(hidden) if hidden > 0 => Ok(PolicySpec::Mlp(hidden)),
+                _ => Err(format!("expected mlp:HIDDEN with HIDDEN > 0, got '{}'", s)),
+            },
+            _ => Err(format!("unknown policy '{}', expected linear or mlp:HIDDEN", s)),
+        }
+    }
+}
+
+impl PolicySpec {
+    /// Policy starting from the constant control `offset`; the hidden weights of an MLP
+    /// are drawn at random, its output weights are small
+    pub fn build<R: Rng + ?Sized>(&self, offset: f64, rng: &mut R) -> FeedbackPolicy {
+        match *self {
+            PolicySpec::Linear => FeedbackPolicy::Linear { offset, gain: 0.0 },
+            PolicySpec::Mlp(hidden) => {
+                let normal = Normal::new(0.0, 1.0).unwrap();
+                let mut draw = |scale: f64| -> Vec<f64> { (0..hidden).map(|_| scale * normal.sample(rng)).collect() };
+                FeedbackPolicy::Mlp { input: draw(1.0), bias: draw(1.0), output: draw(0.01), offset }
+            }
+        }
+    }
+}
+
+
+/// Mean potential of the population at time step t
+pub fn mean_potential<S: ModelState>(sim: &[Vec<S>], t: usize) -> f64 {
+    sim.iter().map(|traj| traj[t].potential()).sum::<f64>() / sim.len() as f64
+}
+
+
+/// Control applied by the policy along a simulated trajectory, with its linearization for the adjoint
+pub fn feedback_sensitivity<'a, S: ModelState>(
+    policy: &FeedbackPolicy,
+    sim: &[Vec<S>],
+    channel: &'a ControlChannel,
+    lambda2: f64,
+) -> FeedbackSensitivity<'a> {
+    let mean_v: Vec<f64> = (0..sim[0].len()).map(|t| mean_potential(sim, t)).collect();
+    FeedbackSensitivity {
+        channel,
+        slopes: mean_v.iter().map(|&v| policy.slope(v)).collect(),
+        controls: mean_v.iter().map(|&v| policy.control(v)).collect(),
+        lambda2,
+    }
+}
+
+
+/// Gradient of the cost w.r.t. the policy parameters, from the closed-loop adjoint:
+///   dJ/dθ = Σ_t dt ((1/L) Σ_i g_i p_iᵏ(t + dt) + 2λα(t)) ∇_θ π(v̄(t))
+pub fn policy_gradient<S: ModelState>(
+    policy: &FeedbackPolicy,
+    sim: &[Vec<S>],
+    adjoints: &[Vec<S>],
+    feedback: &FeedbackSensitivity,
+    dt: f64,
+) -> Vec<f64> {
+    let l = adjoints.len() as f64;
+    let m = sim[0].len();
+    let k = feedback.channel.component;
+    let mut gradient = vec![0.0; policy.parameters().len()];
+
+    for t in 0..m {
+        let mean_p = if t + 1 < m {
+            adjoints
+                .iter()
+                .zip(feedback.channel.gains.iter())
+                .map(|(p, g)| g * p[t + 1][k])
+                .sum::<f64>()
+                / l
+        } else {
+            0.0
+        };
+        let sensitivity = mean_p + 2.0 * feedback.lambda2 * feedback.controls[t];
+        for (g, d) in gradient.iter_mut().zip(policy.parameter_gradient(mean_potential(sim, t))) {
+            *g += dt * sensitivity * d;
+        }
+    }
+
+    gradient
+}
+
+
+/// Writes the policy parameters, one row per parameter
+pub fn save_policy_to_csv(policy: &FeedbackPolicy, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    wtr.write_record(["parameter", "value"])?;
+
+    let names: Vec<String> = match policy {
+        FeedbackPolicy::Linear { .. } => vec!["offset".to_string(), "gain".to_string()],
+        FeedbackPolicy::Mlp { input, .. } => {
+            let h = input.len();
+            (0..h).map(|k| format!("input_{}", k))
+                .chain((0..h).map(|k| format!("bias_{}", k)))
+                .chain((0..h).map(|k| format!("output_{}", k)))
+                .chain(std::iter::once("offset".to_string()))
+                .collect()
+        }
+    };
+    for (name, value) in names.iter().zip(policy.parameters()) {
+        wtr.write_record([name.clone(), value.to_string()])?;
+    }
+
+    wtr.flush()?;
+    Ok(())
+}
+
+
+/// Plot the control law α = π(v̄) over a range of mean potentials
+pub fn plot_policy(
+    policy: &FeedbackPolicy,
+    v_range: (f64, f64),
+    style: &PlotStyle,
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (v_min, v_max) = v_range;
+    let points: Vec<(f64, f64)> = (0..=200)
+        .map(|k| {
+            let v = v_min + (v_max - v_min) * k as f64 / 200.0;
+            (v, policy.control(v))
+        })
+        .collect();
+    let alpha_min = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+    let alpha_max = points.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+    let margin = if alpha_max > alpha_min { 0.05 * (alpha_max - alpha_min) } else { 0.5 };
+
+    draw_figure!(style, filename, |root| {
+        let mut chart = style
+            .chart(&root, "Feedback Policy α = π(v̄)")
+            .build_cartesian_2d(v_min..v_max, (alpha_min - margin)..(alpha_max + margin))?;
+
+        chart.configure_mesh().x_desc("v̄").y_desc("α").label_style(style.label_font()).axis_desc_style(style.label_font()).draw()?;
+
+        let line = style.line(0, 0);
+        chart.draw_series(LineSeries::new(points.iter().cloned(), line))?
+        .label("π(v̄)")
+        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], line));
+
+        chart.configure_series_labels().label_font(style.label_font()).border_style(BLACK).draw()?;
+        Ok(())
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::neuron::{test_parameters, test_states, FhnParameters, NeuronState};
+    use crate::models::population::Population;
+    use crate::optim::gradient::evaluate_cost;
+    use crate::simulations::adjoint::compute_feedback_adjoint;
+    use crate::simulations::forward::simulate_with_feedback;
+    use crate::simulations::initial::InitialCondition;
+
+    const STEPS: usize = 2000;
+    const DT: f64 = 0.01;
+    const LAMBDA2: f64 = 0.01;
+
+    /// Deterministic closed-loop run of the policy: the trajectories and the cost
+    fn run(
+        policy: &FeedbackPolicy,
+        population: &Population<FhnParameters>,
+        channel: &ControlChannel,
+    ) -> (Vec<Vec<NeuronState>>, f64) {
+        let law = |t: usize, sim: &[Vec<NeuronState>]| vec![policy.control(mean_potential(sim, t))];
+        let initial = InitialCondition::Samples(test_states());
+        let sim = simulate_with_feedback(population, STEPS, DT, 0.0, std::slice::from_ref(channel), &law, &initial);
+        let control: Vec<f64> = (0..STEPS).map(|t| policy.control(mean_potential(&sim, t))).collect();
+        let cost = evaluate_cost(&sim, &control, NeuronState::default(), 1.0, LAMBDA2, 1.0, DT);
+        (sim, cost)
+    }
+
+    fn check_policy_gradient(policy: FeedbackPolicy) {
+        let population = Population::homogeneous(&test_parameters(), 4);
+        let channel = ControlChannel::uniform(population.len());
+
+        let (sim, _) = run(&policy, &population, &channel);
+        let feedback = feedback_sensitivity(&policy, &sim, &channel, LAMBDA2);
+        let adjoints = compute_feedback_adjoint(&sim, &population, NeuronState::default(), 1.0, 1.0, DT, &feedback);
+        let gradient = policy_gradient(&policy, &sim, &adjoints, &feedback, DT);
+
+        let theta = policy.parameters();
+        let eps = 1e-5;
+        for (j, predicted) in gradient.iter().enumerate() {
+            let cost = |sign: f64| {
+                let mut shifted = policy.clone();
+                let mut values = theta.clone();
+                values[j] += sign * eps;
+                shifted.set_parameters(&values);
+                run(&shifted, &population, &channel).1
+            };
+            let measured = (cost(1.0) - cost(-1.0)) / (2.0 * eps);
+            assert!(
+                (predicted - measured).abs() < 0.01 * measured.abs().max(1.0),
+                "parameter {}: adjoint {} vs finite differences {}",
+                j,
+                predicted,
+                measured
+            );
+        }
+    }
+
+    #[test]
+    fn linear_policy_gradient_matches_finite_differences() {
+        check_policy_gradient(FeedbackPolicy::Linear { offset: 0.3, gain: -0.5 });
+    }
+
+    #[test]
+    fn mlp_policy_gradient_matches_finite_differences() {
+        check_policy_gradient(FeedbackPolicy::Mlp {
+            input: vec![0.8, -0.5],
+            bias: vec![0.1, 0.3],
+            output: vec![0.2, -0.1],
+            offset: 0.3,
+        });
+    }
+}
@@ -1,11 +1,15 @@
 // src/optim/gradient.rs
 
-use crate::models::neuron::NeuronState;
+use crate::analysis::synchrony::DesyncObjective;
+use crate::draw_figure;
+use crate::models::model::ModelState;
+use crate::plotting::PlotStyle;
+use crate::simulations::control::ControlChannel;
 
 
 /// Computes the gradient of the cost functional
-pub fn compute_control_gradient(
-    adjoints: &Vec<Vec<[f64; 3]>>, // L x M
+pub fn compute_control_gradient<S: ModelState>(
+    adjoints: &[Vec<S>],           // L x M
     control: &Vec<f64>,            // alpha(t)
     lambda2: f64,
     dt: f64,
@@ -23,6 +27,37 @@ pub fn compute_control_gradient(
 }
 
 
+/// Gradient of the cost w.r.t. the control of each channel: the adjoint of the controlled
+/// component weighted by the gains, averaged over the population, plus the control penalty
+pub fn compute_channel_gradients<S: ModelState>(
+    adjoints: &[Vec<S>],           // L x M
+    channels: &[ControlChannel],
+    controls: &[Vec<f64>],         // alpha_c(t)
+    lambda2: f64,
+) -> Vec<Vec<f64>> {
+    let l = adjoints.len();
+    let m = adjoints[0].len();
+
+    channels
+        .iter()
+        .zip(controls.iter())
+        .map(|(channel, control)| {
+            (0..m)
+                .map(|t| {
+                    let mean_p: f64 = adjoints
+                        .iter()
+                        .zip(channel.gains.iter())
+                        .map(|(p, g)| g * p[t][channel.component])
+                        .sum::<f64>()
+                        / l as f64;
+                    mean_p + 2.0 * lambda2 * control[t]
+                })
+                .collect()
+        })
+        .collect()
+}
+
+
 /// One gradient descent update: alpha_new = alpha - s * grad
 pub fn gradient_step(
     control: &Vec<f64>,
@@ -37,11 +72,12 @@ pub fn gradient_step(
 }
 
 
-/// Evaluate the cost functional J(alpha)
-pub fn evaluate_cost(
-    sim: &Vec<Vec<NeuronState>>,
+/// Evaluate the cost functional J(alpha); with several control channels, `control`
+/// holds all of them one after the other
+pub fn evaluate_cost<S: ModelState>(
+    sim: &[Vec<S>],
     control: &Vec<f64>,
-    y_target: [f64; 3],
+    y_target: S,
     gamma: f64,
     lambda2: f64,
     c_t: f64,
@@ -56,51 +92,120 @@ pub fn evaluate_cost(
 
     for t in 0..m {
         // Mean v at time t
-        let mean_v: f64 = sim.iter().map(|traj| traj[t].v).sum::<f64>() / (l as f64);
-        let y_ref = y_target[0]; // reference voltage
+        let mean_v: f64 = sim.iter().map(|traj| traj[t].potential()).sum::<f64>() / (l as f64);
+        let y_ref = y_target.potential(); // reference voltage
         running_cost += gamma * (mean_v - y_ref).powi(2) * dt;
-        control_cost += lambda2 * control[t].powi(2) * dt;
+    }
+    for alpha in control {
+        control_cost += lambda2 * alpha.powi(2) * dt;
     }
 
-    let mean_v_T: f64 = sim.iter().map(|traj| traj[m - 1].v).sum::<f64>() / (l as f64);
-    terminal_cost = c_t * gamma * (mean_v_T - y_target[0]).powi(2);
+    let mean_v_T: f64 = sim.iter().map(|traj| traj[m - 1].potential()).sum::<f64>() / (l as f64);
+    terminal_cost = c_t * gamma * (mean_v_T - y_target.potential()).powi(2);
 
     running_cost + terminal_cost + control_cost
 }
 
 
+/// Cost functional for a mean potential following a reference v_ref(t) (one value per step),
+///   J(α) = ∫ γ (v̄ - v_ref)² dt + c_T γ (v̄(T) - v_ref(T))² + ∫ λ α² dt
+pub fn evaluate_tracking_cost<S: ModelState>(
+    sim: &[Vec<S>],
+    control: &[f64],
+    reference: &[f64],
+    gamma: f64,
+    lambda2: f64,
+    c_t: f64,
+    dt: f64,
+) -> f64 {
+    let l = sim.len() as f64;
+    let m = sim[0].len();
+    let error = |t: usize| sim.iter().map(|traj| traj[t].potential()).sum::<f64>() / l - reference[t];
+
+    let running_cost: f64 = (0..m).map(|t| gamma * error(t).powi(2) * dt).sum();
+    let terminal_cost = c_t * gamma * error(m - 1).powi(2);
+    let control_cost: f64 = control.iter().map(|a| lambda2 * a.powi(2) * dt).sum();
+
+    running_cost + terminal_cost + control_cost
+}
+
+
+/// Cost functional for a spatio-temporal target: every neuron tracks its own potential v*_i(t),
+///   J(α) = ∫ γ (1/L) Σ_i (v_i - v*_i)² dt + c_T γ (1/L) Σ_i (v_i(T) - v*_i(T))² + ∫ λ α² dt
+pub fn evaluate_pattern_cost<S: ModelState>(
+    sim: &[Vec<S>],
+    control: &[f64],
+    pattern: &[Vec<f64>],
+    gamma: f64,
+    lambda2: f64,
+    c_t: f64,
+    dt: f64,
+) -> f64 {
+    let l = sim.len() as f64;
+    let m = sim[0].len();
+    let squared_error = |t: usize| {
+        sim.iter()
+            .zip(pattern.iter())
+            .map(|(traj, target)| (traj[t].potential() - target[t]).powi(2))
+            .sum::<f64>()
+            / l
+    };
+
+    let running_cost: f64 = (0..m).map(|t| gamma * squared_error(t) * dt).sum();
+    let terminal_cost = c_t * gamma * squared_error(m - 1);
+    let control_cost: f64 = control.iter().map(|a| lambda2 * a.powi(2) * dt).sum();
+
+    running_cost + terminal_cost + control_cost
+}
+
+
+/// Cost functional of a desynchronizing control, penalizing the synchrony D(t) of the objective,
+///   J(α) = ∫ γ D(t) dt + ∫ λ α² dt
+pub fn evaluate_desync_cost<S: ModelState>(
+    sim: &[Vec<S>],
+    control: &[f64],
+    objective: &DesyncObjective,
+    gamma: f64,
+    lambda2: f64,
+    dt: f64,
+) -> f64 {
+    let running_cost: f64 = objective.density(sim).iter().map(|d| gamma * d * dt).sum();
+    let control_cost: f64 = control.iter().map(|a| lambda2 * a.powi(2) * dt).sum();
+
+    running_cost + control_cost
+}
+
+
 use plotters::prelude::*;
 
 /// Plot the cost vs iteration curve
 pub fn plot_cost_trace(
     cost: &Vec<f64>,
+    style: &PlotStyle,
     path: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let root = BitMapBackend::new(path, (800, 600)).into_drawing_area();
-    root.fill(&WHITE)?;
-
     let iter_max = cost.len() as f64;
     let cost_min = cost.iter().cloned().fold(f64::INFINITY, f64::min);
     let cost_max = cost.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
 
-    let mut chart = ChartBuilder::on(&root)
-        .caption("Cost Evolution", ("sans-serif", 30))
-        .margin(20)
-        .x_label_area_size(40)
-        .y_label_area_size(50)
-        .build_cartesian_2d(0.0..iter_max, cost_min..cost_max)?;
+    draw_figure!(style, path, |root| {
+        let mut chart = style
+            .chart(&root, "Cost Evolution")
+            .build_cartesian_2d(0.0..iter_max, cost_min..cost_max)?;
 
-    chart.configure_mesh().draw()?;
+        chart.configure_mesh().label_style(style.label_font()).draw()?;
 
-    chart.draw_series(LineSeries::new(
-        cost.iter().enumerate().map(|(i, j)| (i as f64, *j)),
-        &BLUE,
-    ))?
-    .label("J(α)")
-    .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &BLUE));
+        let line = style.line(1, 0);
+        chart.draw_series(LineSeries::new(
+            cost.iter().enumerate().map(|(i, j)| (i as f64, *j)),
+            line,
+        ))?
+        .label("J(α)")
+        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], line));
 
-    chart.configure_series_labels().border_style(&BLACK).draw()?;
-    Ok(())
+        chart.configure_series_labels().label_font(style.label_font()).border_style(BLACK).draw()?;
+        Ok(())
+    })
 }
 
 
@@ -108,33 +213,65 @@ pub fn plot_cost_trace(
 pub fn plot_control(
     control: &Vec<f64>,
     dt: f64,
+    style: &PlotStyle,
     filename: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    use plotters::prelude::*;
-
-    let root = BitMapBackend::new(filename, (800, 600)).into_drawing_area();
-    root.fill(&WHITE)?;
-
     let t_max = dt * control.len() as f64;
     let alpha_min = control.iter().cloned().fold(f64::INFINITY, f64::min);
     let alpha_max = control.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
 
-    let mut chart = ChartBuilder::on(&root)
-        .caption("Optimal Control α(t)", ("sans-serif", 30))
-        .margin(20)
-        .x_label_area_size(40)
-        .y_label_area_size(50)
-        .build_cartesian_2d(0.0..t_max, alpha_min..alpha_max)?;
+    draw_figure!(style, filename, |root| {
+        let mut chart = style
+            .chart(&root, "Optimal Control α(t)")
+            .build_cartesian_2d(0.0..t_max, alpha_min..alpha_max)?;
+
+        chart.configure_mesh().label_style(style.label_font()).draw()?;
+
+        let line = style.line(0, 0);
+        chart.draw_series(LineSeries::new(
+            control.iter().enumerate().map(|(i, a)| (i as f64 * dt, *a)),
+            line,
+        ))?
+        .label("α(t)")
+        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], line));
+
+        chart.configure_series_labels().label_font(style.label_font()).border_style(BLACK).draw()?;
+        Ok(())
+    })
+}
+
+
+/// Plot the optimal controls α_c(t) of several channels
+pub fn plot_controls(
+    controls: &[Vec<f64>],
+    names: &[String],
+    dt: f64,
+    style: &PlotStyle,
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let t_max = dt * controls[0].len() as f64;
+    let alpha_min = controls.iter().flatten().cloned().fold(f64::INFINITY, f64::min);
+    let alpha_max = controls.iter().flatten().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+    draw_figure!(style, filename, |root| {
+        let mut chart = style
+            .chart(&root, "Optimal Controls α_c(t)")
+            .build_cartesian_2d(0.0..t_max, alpha_min..alpha_max)?;
 
-    chart.configure_mesh().draw()?;
+        chart.configure_mesh().label_style(style.label_font()).draw()?;
 
-    chart.draw_series(LineSeries::new(
-        control.iter().enumerate().map(|(i, a)| (i as f64 * dt, *a)),
-        &RED,
-    ))?
-    .label("α(t)")
-    .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &RED));
+        for (c, (control, name)) in controls.iter().zip(names.iter()).enumerate() {
+            let line = style.line(c, 0);
+            chart
+                .draw_series(LineSeries::new(
+                    control.iter().enumerate().map(|(i, a)| (i as f64 * dt, *a)),
+                    line,
+                ))?
+                .label(name.clone())
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], line));
+        }
 
-    chart.configure_series_labels().border_style(&BLACK).draw()?;
-    Ok(())
+        chart.configure_series_labels().label_font(style.label_font()).border_style(BLACK).draw()?;
+        Ok(())
+    })
 }
@@ -0,0 +1,254 @@
+// src/optim/lqr.rs
+
+use nalgebra::{Complex, DMatrix, DVector};
+use plotters::prelude::*;
+use crate::draw_figure;
+use crate::models::model::{ModelState, NeuronModel};
+use crate::plotting::PlotStyle;
+
+/// Fixed point x* of the deterministic mean-field system, where every neuron sees the
+/// coupling observable of its own state: drift(x*, obs(x*)) = 0, found by Newton's method
+/// from `guess`
+pub fn mean_field_fixed_point<N: NeuronModel>(neuron: &N, guess: N::State) -> Result<N::State, String> {
+    let dim = N::State::DIM;
+    let mut state = guess;
+    for _ in 0..100 {
+        let drift = neuron.drift(&state, neuron.coupling_observable(&state));
+        let residual = DVector::from_fn(dim, |k, _| drift[k]);
+        if residual.norm() < 1e-12 {
+            return Ok(state);
+        }
+        let step = mean_field_jacobian(neuron, &state)
+            .lu()
+            .solve(&residual)
+            .ok_or_else(|| "singular Jacobian in the fixed-point iteration".to_string())?;
+        for k in 0..dim {
+            state[k] -= step[k];
+        }
+    }
+    Err(format!("no mean-field fixed point found from {:?}", guess))
+}
+
+
+/// Jacobian of the mean-field drift x ↦ drift(x, obs(x)):
+///   A = Df(x) + ∂f/∂u(x) ∇obs(x)ᵀ
+pub fn mean_field_jacobian<N: NeuronModel>(neuron: &N, state: &N::State) -> DMatrix<f64> {
+    let dim = N::State::DIM;
+    let u = neuron.coupling_observable(state);
+    let dfdu = neuron.drift_mean_field_derivative(state, u);
+    let grad_obs = neuron.coupling_observable_gradient(state);
+    let mut jacobian = neuron.drift_jacobian(state, u);
+    for k in 0..dim {
+        for j in 0..dim {
+            jacobian[(k, j)] += dfdu[k] * grad_obs[j];
+        }
+    }
+    jacobian
+}
+
+
+/// Stabilizing solution P of the continuous algebraic Riccati equation
+///   AᵀP + PA - PBR⁻¹BᵀP + Q = 0,
+/// computed with the matrix sign function of the Hamiltonian [[A, -BR⁻¹Bᵀ], [-Q, -Aᵀ]]:
+/// its stable invariant subspace is spanned by [I; P]
+pub fn solve_riccati(
+    a: &DMatrix<f64>,
+    b: &DMatrix<f64>,
+    q: &DMatrix<f64>,
+    r: &DMatrix<f64>,
+) -> Result<DMatrix<f64>, String> {
+    let n = a.nrows();
+    let r_inv = r.clone().try_inverse().ok_or_else(|| "R is not invertible".to_string())?;
+    let g = b * r_inv * b.transpose();
+
+    let mut hamiltonian = DMatrix::zeros(2 * n, 2 * n);
+    hamiltonian.view_mut((0, 0), (n, n)).copy_from(a);
+    hamiltonian.view_mut((0, n), (n, n)).copy_from(&(-g));
+    hamiltonian.view_mut((n, 0), (n, n)).copy_from(&(-q));
+    hamiltonian.view_mut((n, n), (n, n)).copy_from(&(-a.transpose()));
+
+    // Newton iteration for sign(H), with determinant scaling
+    let mut sign = hamiltonian;
+    let mut converged = false;
+    for _ in 0..100 {
+        let inverse = sign
+            .clone()
+            .try_inverse()
+            .ok_or_else(|| "the Hamiltonian has eigenvalues on the imaginary axis".to_string())?;
+        let scale = sign.determinant().abs().powf(-1.0 / (2 * n) as f64);
+        let next = (&sign * scale + inverse / scale) * 0.5;
+        let change = (&next - &sign).norm();
+        sign = next;
+        if change <= 1e-12 * sign.norm() {
+            converged = true;
+            break;
+        }
+    }
+    if !converged {
+        return Err("the sign iteration did not converge".to_string());
+    }
+
+    // (sign(H) + I) [I; P] = 0, solved for P in the least-squares sense
+    let identity = DMatrix::<f64>::identity(n, n);
+    let mut lhs = DMatrix::zeros(2 * n, n);
+    lhs.view_mut((0, 0), (n, n)).copy_from(&sign.view((0, n), (n, n)));
+    lhs.view_mut((n, 0), (n, n)).copy_from(&(sign.view((n, n), (n, n)) + &identity));
+    let mut rhs = DMatrix::zeros(2 * n, n);
+    rhs.view_mut((0, 0), (n, n)).copy_from(&(-(sign.view((0, 0), (n, n)) + &identity)));
+    rhs.view_mut((n, 0), (n, n)).copy_from(&(-sign.view((n, 0), (n, n))));
+    let p = lhs.svd(true, true).solve(&rhs, 1e-14)?;
+
+    Ok((&p + p.transpose()) * 0.5)
+}
+
+
+/// Linear-quadratic regulator of the mean-field system around its fixed point:
+/// the control α = -K (x̄ - x*) enters the potential, as in the optimizer, and minimizes
+/// the linearized cost ∫ γ (v̄ - v*)² + λ α² dt
+#[derive(Debug, Clone)]
+pub struct LqrController<S: ModelState> {
+    pub fixed_point: S,
+    /// Linearized mean-field dynamics A
+    pub jacobian: DMatrix<f64>,
+    /// Solution of the Riccati equation
+    pub riccati: DMatrix<f64>,
+    /// Feedback gain K = R⁻¹BᵀP, one entry per state component
+    pub gain: Vec<f64>,
+}
+
+impl<S: ModelState> LqrController<S> {
+    pub fn new<N: NeuronModel<State = S>>(neuron: &N, guess: S, gamma: f64, lambda2: f64) -> Result<Self, String> {
+        let dim = S::DIM;
+        let fixed_point = mean_field_fixed_point(neuron, guess)?;
+        let jacobian = mean_field_jacobian(neuron, &fixed_point);
+
+        let b = DMatrix::from_fn(dim, 1, |k, _| if k == 0 { 1.0 } else { 0.0 });
+        let q = DMatrix::from_fn(dim, dim, |k, j| if k == 0 && j == 0 { gamma } else { 0.0 });
+        let r = DMatrix::from_element(1, 1, lambda2);
+        let riccati = solve_riccati(&jacobian, &b, &q, &r)?;
+        let gain = (b.transpose() * &riccati / lambda2).iter().cloned().collect();
+
+        Ok(LqrController { fixed_point, jacobian, riccati, gain })
+    }
+
+    /// Control for the mean state x̄ of the population
+    pub fn control(&self, mean_state: &S) -> f64 {
+        -(0..S::DIM).map(|k| self.gain[k] * (mean_state[k] - self.fixed_point[k])).sum::<f64>()
+    }
+
+    /// Eigenvalues of the open-loop linearization A
+    pub fn open_loop_eigenvalues(&self) -> Vec<Complex<f64>> {
+        self.jacobian.complex_eigenvalues().iter().cloned().collect()
+    }
+
+    /// Eigenvalues of the closed loop A - BK
+    pub fn closed_loop_eigenvalues(&self) -> Vec<Complex<f64>> {
+        let mut closed = self.jacobian.clone();
+        for j in 0..S::DIM {
+            closed[(0, j)] -= self.gain[j];
+        }
+        closed.complex_eigenvalues().iter().cloned().collect()
+    }
+
+    /// Writes the fixed point and the gain, one row per state component
+    pub fn save_to_csv(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut wtr = csv::Writer::from_path(path)?;
+        wtr.write_record(["component", "fixed_point", "gain"])?;
+        for k in 0..S::DIM {
+            wtr.write_record([S::COMPONENTS[k].to_string(), self.fixed_point[k].to_string(), self.gain[k].to_string()])?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+
+/// Mean state of the population at time step t
+pub fn mean_state<S: ModelState>(sim: &[Vec<S>], t: usize) -> S {
+    let mut mean = S::default();
+    for traj in sim {
+        for k in 0..S::DIM {
+            mean[k] += traj[t][k] / sim.len() as f64;
+        }
+    }
+    mean
+}
+
+
+/// Plots the mean potential of several controlled runs against the target potential
+pub fn plot_controller_comparison(
+    runs: &[(String, Vec<f64>)],
+    target: f64,
+    dt: f64,
+    style: &PlotStyle,
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let m = runs.iter().map(|(_, v)| v.len()).max().unwrap_or(0);
+    let t_max = dt * m as f64;
+    let values = || runs.iter().flat_map(|(_, v)| v.iter().cloned()).chain(std::iter::once(target));
+    let v_min = values().fold(f64::INFINITY, f64::min);
+    let v_max = values().fold(f64::NEG_INFINITY, f64::max);
+
+    draw_figure!(style, filename, |root| {
+        let mut chart = style
+            .chart(&root, "Mean Potential under Feedback and Open-Loop Control")
+            .build_cartesian_2d(0.0..t_max, v_min..v_max)?;
+
+        chart.configure_mesh().label_style(style.label_font()).draw()?;
+
+        for (k, (name, mean_v)) in runs.iter().enumerate() {
+            let line = style.line(k, 0);
+            chart
+                .draw_series(LineSeries::new(
+                    mean_v.iter().enumerate().map(|(t, v)| (t as f64 * dt, *v)),
+                    line,
+                ))?
+                .label(name.clone())
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], line));
+        }
+
+        let target_line = BLACK.stroke_width(style.line_width);
+        chart
+            .draw_series(LineSeries::new(vec![(0.0, target), (t_max, target)], target_line))?
+            .label("v*")
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], target_line));
+
+        chart.configure_series_labels().label_font(style.label_font()).border_style(BLACK).draw()?;
+        Ok(())
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::neuron::{test_parameters, NeuronState};
+
+    #[test]
+    fn scalar_riccati_has_the_closed_form_solution() {
+        // 2ap - p²b²/r + q = 0, with a = b = q = r = 1: p = 1 + √2
+        let one = DMatrix::from_element(1, 1, 1.0);
+        let p = solve_riccati(&one, &one, &one, &one).unwrap();
+        assert!((p[(0, 0)] - (1.0 + 2f64.sqrt())).abs() < 1e-9);
+    }
+
+    #[test]
+    fn lqr_gain_solves_the_riccati_equation_and_stabilizes() {
+        let params = test_parameters();
+        let lqr = LqrController::new(&params, NeuronState::default(), 1.0, 0.01).unwrap();
+        let drift = params.drift(&lqr.fixed_point, params.coupling_observable(&lqr.fixed_point));
+        assert!((0..3).all(|k| drift[k].abs() < 1e-10));
+
+        // AᵀP + PA - PBR⁻¹BᵀP + Q, where PBR⁻¹BᵀP = λ KᵀK
+        let (a, p) = (&lqr.jacobian, &lqr.riccati);
+        let mut residual = a.transpose() * p + p * a;
+        for k in 0..3 {
+            for j in 0..3 {
+                residual[(k, j)] -= lqr.gain[k] * lqr.gain[j] * 0.01;
+            }
+        }
+        residual[(0, 0)] += 1.0;
+        assert!(residual.norm() < 1e-8 * p.norm(), "Riccati residual {}", residual.norm());
+        assert!(lqr.closed_loop_eigenvalues().iter().all(|z| z.re < 0.0));
+    }
+}
@@ -1 +1,4 @@
-pub mod gradient;
\ No newline at end of file
+pub mod gradient;
+pub mod feedback;
+pub mod mpc;
+pub mod lqr;
@@ -0,0 +1,201 @@
+// src/optim/mpc.rs
+
+use plotters::prelude::*;
+use crate::draw_figure;
+use crate::models::model::{ModelState, NeuronModel};
+use crate::models::population::Population;
+use crate::optim::gradient::{compute_control_gradient, evaluate_tracking_cost, gradient_step};
+use crate::plotting::PlotStyle;
+use crate::simulations::adjoint::compute_tracking_adjoint;
+use crate::simulations::forward::{simulate_controlled_population, simulate_with_control};
+use crate::simulations::initial::InitialCondition;
+
+/// Settings of the receding-horizon controller
+#[derive(Debug, Clone, Copy)]
+pub struct MpcSettings {
+    /// Length of the prediction window, in time steps
+    pub horizon: usize,
+    /// Number of steps of each plan applied before solving again
+    pub chunk: usize,
+    /// Gradient steps per window
+    pub iterations: usize,
+    pub step_size: f64,
+    pub gamma: f64,
+    pub lambda2: f64,
+    pub c_t: f64,
+}
+
+/// Closed loop of the receding-horizon controller
+#[derive(Debug, Clone)]
+pub struct MpcRun<S: ModelState> {
+    /// Trajectories of the noisy population, M steps each
+    pub sim: Vec<Vec<S>>,
+    /// Control applied at each step
+    pub control: Vec<f64>,
+    /// Predicted cost of the plan of each window, before and after its optimization
+    pub window_costs: Vec<(f64, f64)>,
+}
+
+/// Model predictive control of the mean potential towards `reference` (one value per step).
+/// From the current states of the population, the open-loop problem over the next `horizon`
+/// steps is solved by gradient descent on the noiseless model (keeping the best plan found),
+/// the first `chunk` steps of the plan are applied to the noisy population, and the rest of
+/// the plan warm-starts the next window.
+/// Each window starts without synaptic history: delayed inputs see the states at its start.
+pub fn receding_horizon<N: NeuronModel>(
+    population: &Population<N>,
+    initial: &InitialCondition<N::State>,
+    sigma_ext: f64,
+    dt: f64,
+    reference: &[f64],
+    settings: &MpcSettings,
+) -> MpcRun<N::State> {
+    let m = reference.len();
+    let mut rng = rand::thread_rng();
+    let mut states = initial.sample(population.len(), &mut rng);
+    let mut sim: Vec<Vec<N::State>> = states.iter().map(|state| vec![*state]).collect();
+    let mut control = Vec::with_capacity(m);
+    let mut window_costs = Vec::new();
+    let mut plan: Vec<f64> = Vec::new();
+
+    let mut t0 = 0;
+    while t0 + 1 < m {
+        let h = settings.horizon.max(2).min(m - t0);
+        let window = population.starting_at(t0 as f64 * dt);
+        let start = InitialCondition::Samples(states.clone());
+        let target = &reference[t0..t0 + h];
+        let last = plan.last().copied().unwrap_or(0.0);
+        plan.resize(h, last);
+
+        // Solve the finite-horizon problem from the current states, keeping the best plan seen
+        let cost_of = |plan: &Vec<f64>| -> (f64, Vec<Vec<N::State>>) {
+            let predicted = simulate_with_control(&window, h, dt, plan, &start);
+            let cost = evaluate_tracking_cost(&predicted, plan, target, settings.gamma, settings.lambda2, settings.c_t, dt);
+            (cost, predicted)
+        };
+        let (first_cost, mut predicted) = cost_of(&plan);
+        let mut best = (first_cost, plan.clone());
+        for _ in 0..settings.iterations {
+            let adj = compute_tracking_adjoint(&predicted, &window, target, settings.gamma, settings.c_t, dt);
+            let grad = compute_control_gradient(&adj, &plan, settings.lambda2, dt);
+            plan = gradient_step(&plan, &grad, settings.step_size);
+            let (cost, next) = cost_of(&plan);
+            if cost < best.0 {
+                best = (cost, plan.clone());
+            }
+            predicted = next;
+        }
+        let (best_cost, best_plan) = best;
+        plan = best_plan;
+        window_costs.push((first_cost, best_cost));
+
+        // Apply the first chunk to the noisy population
+        let k = settings.chunk.max(1).min(h - 1);
+        let applied = simulate_controlled_population(&window, k + 1, dt, sigma_ext, &plan[..k + 1], &start);
+        for (traj, run) in sim.iter_mut().zip(applied.iter()) {
+            traj.extend_from_slice(&run[1..]);
+        }
+        states = applied.iter().map(|run| run[k]).collect();
+        control.extend(plan.drain(..k));
+        t0 += k;
+    }
+    // The control of the last step acts after the horizon
+    control.push(plan.first().copied().unwrap_or(0.0));
+
+    MpcRun { sim, control, window_costs }
+}
+
+
+/// Writes the closed loop: time, mean potential, reference and applied control
+pub fn save_mpc_to_csv<S: ModelState>(
+    run: &MpcRun<S>,
+    reference: &[f64],
+    dt: f64,
+    path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    wtr.write_record(["time", "mean_v", "reference", "alpha"])?;
+
+    let l = run.sim.len() as f64;
+    for (t, (reference, alpha)) in reference.iter().zip(run.control.iter()).enumerate() {
+        let mean_v = run.sim.iter().map(|traj| traj[t].potential()).sum::<f64>() / l;
+        wtr.write_record([(t as f64 * dt).to_string(), mean_v.to_string(), reference.to_string(), alpha.to_string()])?;
+    }
+
+    wtr.flush()?;
+    Ok(())
+}
+
+
+/// Plots the closed-loop mean potential against the reference
+pub fn plot_tracking<S: ModelState>(
+    sim: &[Vec<S>],
+    reference: &[f64],
+    dt: f64,
+    style: &PlotStyle,
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let m = reference.len();
+    let l = sim.len() as f64;
+    let mean_v: Vec<f64> = (0..m)
+        .map(|t| sim.iter().map(|traj| traj[t].potential()).sum::<f64>() / l)
+        .collect();
+
+    let t_max = dt * m as f64;
+    let v_min = mean_v.iter().chain(reference.iter()).cloned().fold(f64::INFINITY, f64::min);
+    let v_max = mean_v.iter().chain(reference.iter()).cloned().fold(f64::NEG_INFINITY, f64::max);
+
+    draw_figure!(style, filename, |root| {
+        let mut chart = style
+            .chart(&root, "Model Predictive Control")
+            .build_cartesian_2d(0.0..t_max, v_min..v_max)?;
+
+        chart.configure_mesh().label_style(style.label_font()).draw()?;
+
+        let (closed_loop, reference_line) = (style.line(1, 0), style.line(2, 0));
+        chart.draw_series(LineSeries::new(
+            mean_v.iter().enumerate().map(|(t, v)| (t as f64 * dt, *v)),
+            closed_loop,
+        ))?
+        .label("v̄(t), closed loop")
+        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], closed_loop));
+
+        chart.draw_series(LineSeries::new(
+            reference.iter().enumerate().map(|(t, v)| (t as f64 * dt, *v)),
+            reference_line,
+        ))?
+        .label("v_ref(t)")
+        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], reference_line));
+
+        chart.configure_series_labels().label_font(style.label_font()).border_style(BLACK).draw()?;
+        Ok(())
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::neuron::{test_parameters, test_states};
+
+    #[test]
+    fn closed_loop_is_the_run_under_the_applied_control() {
+        let population = Population::homogeneous(&test_parameters(), 4);
+        let initial = InitialCondition::Samples(test_states());
+        let (m, dt) = (301, 0.01);
+        let settings = MpcSettings { horizon: 100, chunk: 40, iterations: 3, step_size: 0.05, gamma: 1.0, lambda2: 0.01, c_t: 1.0 };
+        let reference = vec![-0.5; m];
+        let run = receding_horizon(&population, &initial, 0.0, dt, &reference, &settings);
+
+        assert_eq!(run.control.len(), m);
+        assert_eq!(run.window_costs.len(), 8);
+        assert!(run.window_costs.iter().all(|(before, after)| after <= before));
+
+        // Without noise, stitching the windows together gives back a single run from the start
+        let replay = simulate_with_control(&population, m, dt, &run.control, &initial);
+        for (traj, replayed) in run.sim.iter().zip(replay.iter()) {
+            assert_eq!(traj.len(), m);
+            assert!(traj.iter().zip(replayed.iter()).all(|(a, b)| (a.v - b.v).abs() < 1e-12));
+        }
+    }
+}
@@ -0,0 +1,181 @@
+// src/plotting/mod.rs
+
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::str::FromStr;
+
+/// File format of the figures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    /// Vector graphics, with the text kept as text
+    Svg,
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Svg => "svg",
+        }
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "png" => Ok(ImageFormat::Png),
+            "svg" => Ok(ImageFormat::Svg),
+            _ => Err(format!("unknown format '{}', expected png or svg", s)),
+        }
+    }
+}
+
+/// Colors of the successive series of a figure, as given on the command line: `default`,
+/// `colorblind` (Okabe–Ito), `grayscale` or a comma-separated list of hex colors (`#1f77b4,...`)
+#[derive(Debug, Clone)]
+pub struct Palette(pub Vec<RGBColor>);
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette(vec![RED, BLUE, GREEN, MAGENTA, CYAN, BLACK])
+    }
+}
+
+impl FromStr for Palette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Palette::default()),
+            "colorblind" => Ok(Palette(vec![
+                RGBColor(213, 94, 0),
+                RGBColor(0, 114, 178),
+                RGBColor(0, 158, 115),
+                RGBColor(204, 121, 167),
+                RGBColor(86, 180, 233),
+                RGBColor(230, 159, 0),
+                RGBColor(0, 0, 0),
+            ])),
+            "grayscale" => Ok(Palette(vec![RGBColor(0, 0, 0), RGBColor(90, 90, 90), RGBColor(150, 150, 150), RGBColor(200, 200, 200)])),
+            _ => s
+                .split(',')
+                .map(|hex| {
+                    let digits = hex.trim().trim_start_matches('#');
+                    let channel = |k: usize| digits.get(2 * k..2 * k + 2).and_then(|c| u8::from_str_radix(c, 16).ok());
+                    match (digits.len(), channel(0), channel(1), channel(2)) {
+                        (6, Some(r), Some(g), Some(b)) => Ok(RGBColor(r, g, b)),
+                        _ => Err(format!("invalid color '{}', expected #RRGGBB", hex)),
+                    }
+                })
+                .collect::<Result<Vec<RGBColor>, String>>()
+                .map(Palette),
+        }
+    }
+}
+
+/// Appearance of the figures, shared by every plot
+#[derive(Debug, Clone)]
+pub struct PlotStyle {
+    pub format: ImageFormat,
+    /// Width and height in pixels (in user units for SVG)
+    pub size: (u32, u32),
+    /// Font family of all the text
+    pub font: String,
+    pub title_size: u32,
+    /// Size of the tick labels, axis descriptions and legends
+    pub label_size: u32,
+    pub line_width: u32,
+    pub palette: Palette,
+    /// Whether the figures have a title
+    pub titles: bool,
+}
+
+impl Default for PlotStyle {
+    fn default() -> Self {
+        PlotStyle {
+            format: ImageFormat::Png,
+            size: (800, 600),
+            font: "sans-serif".to_string(),
+            title_size: 30,
+            label_size: 12,
+            line_width: 1,
+            palette: Palette::default(),
+            titles: true,
+        }
+    }
+}
+
+impl PlotStyle {
+    /// Color of the k-th series, cycling through the palette
+    pub fn color(&self, k: usize) -> RGBColor {
+        let colors = &self.palette.0;
+        if colors.is_empty() { BLACK } else { colors[k % colors.len()] }
+    }
+
+    /// Line of the k-th series, `extra` pixels wider than the configured width
+    pub fn line(&self, k: usize, extra: u32) -> ShapeStyle {
+        self.color(k).stroke_width(self.line_width + extra)
+    }
+
+    pub fn title_font(&self) -> FontDesc<'_> {
+        (self.font.as_str(), self.title_size).into_font()
+    }
+
+    /// Font of the titles of the panels of a figure
+    pub fn panel_font(&self) -> FontDesc<'_> {
+        (self.font.as_str(), (2 * self.title_size / 3).max(self.label_size)).into_font()
+    }
+
+    pub fn label_font(&self) -> FontDesc<'_> {
+        (self.font.as_str(), self.label_size).into_font()
+    }
+
+    /// Chart on the area with the usual margins, titled `caption` (if not empty) unless titles
+    /// are disabled
+    pub fn chart<'a, 'b, DB: DrawingBackend>(&'b self, area: &'a DrawingArea<DB, Shift>, caption: &str) -> ChartBuilder<'a, 'b, DB> {
+        let mut builder = ChartBuilder::on(area);
+        builder.margin(20).x_label_area_size(40).y_label_area_size(50);
+        if self.titles && !caption.is_empty() {
+            builder.caption(caption, self.title_font());
+        }
+        builder
+    }
+
+    /// Chart on a panel of a figure, with tighter margins and a smaller title
+    pub fn panel<'a, 'b, DB: DrawingBackend>(&'b self, area: &'a DrawingArea<DB, Shift>, caption: &str) -> ChartBuilder<'a, 'b, DB> {
+        let mut builder = ChartBuilder::on(area);
+        builder.margin(10).x_label_area_size(30).y_label_area_size(40);
+        if self.titles && !caption.is_empty() {
+            builder.caption(caption, self.panel_font());
+        }
+        builder
+    }
+
+    /// Path of the figure `name` in `directory`, with the extension of the format
+    pub fn path(&self, directory: &str, name: &str) -> String {
+        format!("{}/{}.{}", directory, name, self.format.extension())
+    }
+}
+
+/// Draws a figure on a backend of the style's format: binds the root drawing area of the file
+/// `filename` to `root`, filled in white, and evaluates the body, once per backend type
+#[macro_export]
+macro_rules! draw_figure {
+    ($style:expr, $filename:expr, |$root:ident| $body:block) => {
+        match $style.format {
+            $crate::plotting::ImageFormat::Png => {
+                let $root = plotters::prelude::IntoDrawingArea::into_drawing_area(plotters::prelude::BitMapBackend::new($filename, $style.size));
+                $root.fill(&plotters::prelude::WHITE)?;
+                $body
+            }
+            $crate::plotting::ImageFormat::Svg => {
+                let $root = plotters::prelude::IntoDrawingArea::into_drawing_area(plotters::prelude::SVGBackend::new($filename, $style.size));
+                $root.fill(&plotters::prelude::WHITE)?;
+                $body
+            }
+        }
+    };
+}
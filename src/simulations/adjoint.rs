@@ -1,54 +1,267 @@
 // src/simulations/adjoint.rs
 
-use crate::models::neuron::{FhnParameters, NeuronState};
+use crate::analysis::synchrony::{geometric_phases, DesyncObjective};
+use crate::draw_figure;
+use crate::models::model::{ModelState, NeuronModel};
+use crate::models::population::Population;
+use crate::plotting::PlotStyle;
+use crate::simulations::control::ControlChannel;
+use crate::simulations::forward::synaptic_inputs;
 use plotters::prelude::*;
 
 /// One adjoint trajectory corresponding to a neuron
-pub type AdjointTrajectory = Vec<[f64; 3]>;
+pub type AdjointTrajectory<S> = Vec<S>;
 
-/// Solves the adjoint equation backward in time
-pub fn compute_adjoint(
-    sim: &Vec<Vec<NeuronState>>,
-    params: &FhnParameters,
-    y_target: [f64; 3],
+/// Closed-loop control α(t) = π(v̄(t)) of the mean potential, acting through `channel`,
+/// linearized along a trajectory: `slopes[t]` = π'(v̄(t)) and `controls[t]` = α(t)
+pub struct FeedbackSensitivity<'a> {
+    pub channel: &'a ControlChannel,
+    pub slopes: Vec<f64>,
+    pub controls: Vec<f64>,
+    /// Weight of the control cost λ ∫ α² dt, which also depends on the state in closed loop
+    pub lambda2: f64,
+}
+
+/// Solves the adjoint equation backward in time.
+///
+/// The adjoint of neuron i solves
+///   -dp_i/dt = Df(x_i)^T p_i + ∇obs(x_i) Σ_j W_ji ∂f/∂u(x_j) · p_j + ∂ℓ/∂x_i,
+/// where u_j = Σ_i W_ji obs(x_i) is the synaptic input of neuron j (W_ji = 1/L for the
+/// mean field), so the second term comes from the dependence of the inputs on x_i
+/// (summed over the input channels when there are several populations, and taken
+/// at the later times t + τ when the synapses are delayed by τ), and
+/// ℓ is the running cost of `evaluate_cost`. The adjoints are scaled by L, so that
+/// the gradient of the cost is the population average of p_i⁰.
+pub fn compute_adjoint<N: NeuronModel>(
+    sim: &[Vec<N::State>],
+    population: &Population<N>,
+    y_target: N::State,
+    gamma: f64,
+    c_t: f64,
+    dt: f64,
+) -> Vec<AdjointTrajectory<N::State>> {
+    let reference = vec![y_target.potential(); sim[0].len()];
+    mean_tracking_adjoint(sim, population, &reference, gamma, c_t, dt, None)
+}
+
+
+/// Adjoint of `evaluate_tracking_cost`, where the mean potential follows a reference v_ref(t)
+pub fn compute_tracking_adjoint<N: NeuronModel>(
+    sim: &[Vec<N::State>],
+    population: &Population<N>,
+    reference: &[f64],
+    gamma: f64,
+    c_t: f64,
+    dt: f64,
+) -> Vec<AdjointTrajectory<N::State>> {
+    mean_tracking_adjoint(sim, population, reference, gamma, c_t, dt, None)
+}
+
+
+/// Adjoint of `evaluate_cost` under the closed-loop control described by `feedback`.
+/// Since α depends on every v_j through v̄, each adjoint p_j⁰ receives in addition
+/// π'(v̄) ((1/L) Σ_i g_i p_iᵏ + 2λα), the sensitivity of the cost to the control
+/// (k and g_i being the component and gains of the channel).
+pub fn compute_feedback_adjoint<N: NeuronModel>(
+    sim: &[Vec<N::State>],
+    population: &Population<N>,
+    y_target: N::State,
+    gamma: f64,
+    c_t: f64,
+    dt: f64,
+    feedback: &FeedbackSensitivity,
+) -> Vec<AdjointTrajectory<N::State>> {
+    let reference = vec![y_target.potential(); sim[0].len()];
+    mean_tracking_adjoint(sim, population, &reference, gamma, c_t, dt, Some(feedback))
+}
+
+
+fn mean_tracking_adjoint<N: NeuronModel>(
+    sim: &[Vec<N::State>],
+    population: &Population<N>,
+    reference: &[f64],
+    gamma: f64,
+    c_t: f64,
+    dt: f64,
+    feedback: Option<&FeedbackSensitivity>,
+) -> Vec<AdjointTrajectory<N::State>> {
+    let l = sim.len();
+    let m = sim[0].len();
+    let mean_v: Vec<f64> = (0..m)
+        .map(|t| sim.iter().map(|traj| traj[t].potential()).sum::<f64>() / l as f64)
+        .collect();
+
+    adjoint_sweep(
+        sim,
+        population,
+        dt,
+        |_| on_potential(2.0 * c_t * gamma * (mean_v[m - 1] - reference[m - 1])),
+        |_, r| on_potential(2.0 * gamma * (mean_v[r] - reference[r])),
+        feedback,
+    )
+}
+
+
+/// Adjoint of the pattern-tracking cost of `evaluate_pattern_cost`, where every neuron
+/// follows its own target potential v*_i(t)
+pub fn compute_pattern_adjoint<N: NeuronModel>(
+    sim: &[Vec<N::State>],
+    population: &Population<N>,
+    pattern: &[Vec<f64>],
+    gamma: f64,
+    c_t: f64,
+    dt: f64,
+) -> Vec<AdjointTrajectory<N::State>> {
+    let m = sim[0].len();
+    adjoint_sweep(
+        sim,
+        population,
+        dt,
+        |i| on_potential(2.0 * c_t * gamma * (sim[i][m - 1].potential() - pattern[i][m - 1])),
+        |i, r| on_potential(2.0 * gamma * (sim[i][r].potential() - pattern[i][r])),
+        None,
+    )
+}
+
+
+/// Adjoint of `evaluate_desync_cost`, the running cost γ D(t) having no terminal part.
+/// For the variance of the mean potential, the source is that of tracking its own time
+/// average (the derivative of the average cancels out). For R² = C² + S², with
+/// C = (1/L) Σ cos θ_i and S = (1/L) Σ sin θ_i, the source of neuron i is
+/// 2γ (S cos θ_i - C sin θ_i) ∇θ_i, on the two components of the phase frame.
+pub fn compute_desync_adjoint<N: NeuronModel>(
+    sim: &[Vec<N::State>],
+    population: &Population<N>,
+    objective: &DesyncObjective,
     gamma: f64,
-    cT: f64,
     dt: f64,
-) -> Vec<AdjointTrajectory> {
-    let L = sim.len();
-    let M = sim[0].len();
+) -> Vec<AdjointTrajectory<N::State>> {
+    let l = sim.len() as f64;
+    let m = sim[0].len();
+    match objective {
+        DesyncObjective::LfpVariance => {
+            let average = (0..m).map(|t| sim.iter().map(|traj| traj[t].potential()).sum::<f64>()).sum::<f64>() / (l * m as f64);
+            mean_tracking_adjoint(sim, population, &vec![average; m], gamma, 0.0, dt, None)
+        }
+        DesyncObjective::Kuramoto(frame) => {
+            let phases = geometric_phases(sim, frame);
+            let (cos_mean, sin_mean): (Vec<f64>, Vec<f64>) = (0..m)
+                .map(|t| {
+                    let c = phases.iter().map(|p| p[t].cos()).sum::<f64>() / l;
+                    let s = phases.iter().map(|p| p[t].sin()).sum::<f64>() / l;
+                    (c, s)
+                })
+                .unzip();
+            adjoint_sweep(
+                sim,
+                population,
+                dt,
+                |_| N::State::default(),
+                |i, r| {
+                    let theta = phases[i][r];
+                    let weight = 2.0 * gamma * (sin_mean[r] * theta.cos() - cos_mean[r] * theta.sin());
+                    let gradient = frame.phase_gradient(&sim[i][r]);
+                    let mut source = N::State::default();
+                    source[0] = weight * gradient[0];
+                    source[1] = weight * gradient[1];
+                    source
+                },
+                None,
+            )
+        }
+    }
+}
+
+
+/// State with the given potential and all other components zero
+fn on_potential<S: ModelState>(value: f64) -> S {
+    let mut state = S::default();
+    state[0] = value;
+    state
+}
+
+
+/// Backward Euler sweep shared by the adjoint solvers: `terminal(i)` is p_i(T) and
+/// `source(i, r)` the gradient of the running cost w.r.t. x_i at step r (both scaled by L)
+fn adjoint_sweep<N: NeuronModel>(
+    sim: &[Vec<N::State>],
+    population: &Population<N>,
+    dt: f64,
+    terminal: impl Fn(usize) -> N::State,
+    source: impl Fn(usize, usize) -> N::State,
+    feedback: Option<&FeedbackSensitivity>,
+) -> Vec<AdjointTrajectory<N::State>> {
+    let l = sim.len();
+    let m = sim[0].len();
+    let dim = N::State::DIM;
 
-    let mut adjoints = vec![vec![[0.0; 3]; M]; L];
+    let mut adjoints = vec![vec![N::State::default(); m]; l];
+    let taps = population.delay.taps(dt);
+    let mut population = population.clone();
+    // Coupling terms of the inputs at step r + τ, to be applied at step r
+    let mut delayed_coupling = vec![vec![0.0; l]; m];
 
     // Terminal condition
-    for i in 0..L {
-        let x_final = sim[i][M - 1];
-        adjoints[i][M - 1][0] = 2.0 * cT * gamma * (x_final.v - y_target[0]);
-        // [1] and [2] remain 0
+    for (i, adj) in adjoints.iter_mut().enumerate() {
+        adj[m - 1] = terminal(i);
     }
 
     // Backward loop
-    for r in (0..M - 1).rev() {
-        // Compute mean field quantities at step r+1
-        let mean_v: f64 = sim.iter().map(|traj| traj[r + 1].v).sum::<f64>() / (L as f64);
-        let mean_y: f64 = sim.iter().map(|traj| traj[r + 1].y).sum::<f64>() / (L as f64);
+    for r in (0..m - 1).rev() {
+        population.apply_schedules(r as f64 * dt);
+        let inputs = synaptic_inputs(&population, sim, r, &taps);
+
+        // Sensitivity of each neuron to its synaptic inputs, sent back to the presynaptic neurons
+        let sensitivities: Vec<Vec<f64>> = (0..l)
+            .map(|j| {
+                let p_next = &adjoints[j][r + 1];
+                population
+                    .drift_input_derivatives(j, &sim[j][r], &inputs[j])
+                    .iter()
+                    .map(|dfdu| (0..dim).map(|k| dfdu[k] * p_next[k]).sum::<f64>())
+                    .collect()
+            })
+            .collect();
+        let coupling = population.connectivity.transpose_inputs(&sensitivities);
+        for &(delay, weight) in &taps {
+            let target = &mut delayed_coupling[r.saturating_sub(delay)];
+            for (d, c) in target.iter_mut().zip(coupling.iter()) {
+                *d += weight * c;
+            }
+        }
+        let coupling = std::mem::take(&mut delayed_coupling[r]);
 
-        for i in 0..L {
+        // Sensitivity of the cost to the closed-loop control, sent back through v̄
+        let loop_term = feedback.map_or(0.0, |fb| {
+            let k = fb.channel.component;
+            let mean_p: f64 = adjoints
+                .iter()
+                .zip(fb.channel.gains.iter())
+                .map(|(p, g)| g * p[r + 1][k])
+                .sum::<f64>()
+                / l as f64;
+            fb.slopes[r] * (mean_p + 2.0 * fb.lambda2 * fb.controls[r])
+        });
+
+        for i in 0..l {
             let x = sim[i][r];
             let p_next = adjoints[i][r + 1];
+            let neuron = &population.neurons[i];
+            let jac = population.drift_jacobian(i, &x, &inputs[i]);
+            let grad_obs = neuron.coupling_observable_gradient(&x);
 
             // Backward Euler step for p(t)
-            let dp0 = dt * (
-                (1.0 - x.v.powi(2)) * p_next[0]
-                - p_next[1]
-                - 2.0 * gamma * (x.v - mean_v)
-            );
-            let dp1 = dt * (params.c * p_next[0] - params.c * params.b * p_next[1]);
-            let dp2 = 0.0; // no running cost on y
-
-            adjoints[i][r][0] = p_next[0] + dp0;
-            adjoints[i][r][1] = p_next[1] + dp1;
-            adjoints[i][r][2] = p_next[2] + dp2;
+            let mut p = p_next;
+            for j in 0..dim {
+                let jt_p: f64 = (0..dim).map(|k| jac[(k, j)] * p_next[k]).sum();
+                p[j] += dt * (jt_p + grad_obs[j] * coupling[i]);
+            }
+            let source = source(i, r);
+            for j in 0..dim {
+                p[j] += dt * source[j];
+            }
+            p[0] += dt * loop_term;
+            adjoints[i][r] = p;
         }
     }
 
@@ -56,9 +269,10 @@ pub fn compute_adjoint(
 }
 
 
-pub fn plot_adjoint_trajectories(
-    adj: &Vec<Vec<[f64; 3]>>,
+pub fn plot_adjoint_trajectories<S: ModelState>(
+    adj: &[Vec<S>],
     dt: f64,
+    style: &PlotStyle,
     filename: &str,
     count: usize,
 ) -> Result<(), Box<dyn std::error::Error>> {
@@ -66,9 +280,6 @@ pub fn plot_adjoint_trajectories(
     let l = adj.len();
     let count = count.min(l);
 
-    let root = BitMapBackend::new(filename, (800, 600)).into_drawing_area();
-    root.fill(&WHITE)?;
-
     let t_max = m as f64 * dt;
 
     let p_min = adj.iter()
@@ -81,30 +292,178 @@ pub fn plot_adjoint_trajectories(
         .flat_map(|traj| traj.iter().map(|p| p[0]))
         .fold(f64::NEG_INFINITY, f64::max);
 
-    let mut chart = ChartBuilder::on(&root)
-        .caption("Adjoint State p⁰(t) for Select Neurons", ("sans-serif", 30))
-        .margin(20)
-        .x_label_area_size(40)
-        .y_label_area_size(50)
-        .build_cartesian_2d(0.0..t_max, p_min..p_max)?;
+    draw_figure!(style, filename, |root| {
+        let mut chart = style
+            .chart(&root, "Adjoint State p⁰(t) for Select Neurons")
+            .build_cartesian_2d(0.0..t_max, p_min..p_max)?;
+
+        chart.configure_mesh().label_style(style.label_font()).draw()?;
+
+        for (i, trajectory) in adj.iter().enumerate().take(count) {
+            let line = style.line(i, 0);
+            chart
+                .draw_series(LineSeries::new(
+                    trajectory.iter().enumerate().map(|(t, p)| (t as f64 * dt, p[0])),
+                    line,
+                ))?
+                .label(format!("Neuron {}", i))
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], line));
+        }
+
+        chart.configure_series_labels().label_font(style.label_font()).border_style(BLACK).draw()?;
+        Ok(())
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::connectivity::Connectivity;
+    use crate::models::delay::SynapticDelay;
+    use crate::models::neuron::{test_parameters, test_states, FhnParameters, NeuronState};
+    use crate::models::population::Subpopulation;
+    use crate::models::spatial::{KernelShape, Lattice, SpatialKernel};
+    use crate::analysis::synchrony::DesyncSpec;
+    use crate::optim::gradient::{compute_channel_gradients, compute_control_gradient, evaluate_cost, evaluate_desync_cost, evaluate_pattern_cost};
+    use crate::simulations::forward::{simulate_with_control, simulate_with_controls};
+    use crate::simulations::initial::InitialCondition;
+    use std::f64::consts::PI;
+
+    const STEPS: usize = 2000;
+    const DT: f64 = 0.01;
+    const LAMBDA2: f64 = 0.01;
 
-    chart.configure_mesh().draw()?;
+    fn initial() -> InitialCondition<NeuronState> {
+        InitialCondition::Samples(test_states())
+    }
+
+    /// Steep synaptic release and strong coupling, so that the coupling terms of the adjoint
+    /// weigh in the gradient
+    fn coupled_parameters() -> FhnParameters {
+        FhnParameters { J: 1.0, lambda: 4.0, VT: 0.0, ..test_parameters() }
+    }
+
+    /// Neuron 3 drives all the others and only hears neuron 0
+    fn star_population() -> Population<FhnParameters> {
+        let edges = [(3, 0, 1.0), (3, 1, 1.0), (3, 2, 1.0), (0, 3, 1.0)];
+        Population::homogeneous(&coupled_parameters(), 4).with_connectivity(Connectivity::from_edges(4, &edges))
+    }
 
-    let colors = [
-        &RED, &BLUE, &GREEN, &CYAN, &MAGENTA, &BLACK, &YELLOW,
-    ];
+    fn control() -> Vec<f64> {
+        (0..STEPS).map(|t| 0.3 * (t as f64 * DT).sin()).collect()
+    }
+
+    /// Compares the derivative of `cost` at `control()` along a smooth direction, by central
+    /// differences, with the one predicted by `gradient` (a density in time)
+    fn check_directional_derivative(gradient: &[f64], cost: impl Fn(&Vec<f64>) -> f64) {
+        let control = control();
+        let direction: Vec<f64> = (0..STEPS).map(|t| (0.5 * t as f64 * DT).cos()).collect();
+        let predicted: f64 = gradient.iter().zip(direction.iter()).map(|(g, d)| g * d * DT).sum();
+
+        let eps = 1e-4;
+        let shifted = |sign: f64| control.iter().zip(direction.iter()).map(|(a, d)| a + sign * eps * d).collect();
+        let measured = (cost(&shifted(1.0)) - cost(&shifted(-1.0))) / (2.0 * eps);
+
+        assert!(
+            (predicted - measured).abs() < 0.01 * measured.abs(),
+            "adjoint {} vs finite differences {}",
+            predicted,
+            measured
+        );
+    }
 
-    for i in 0..count {
-        let color = colors[i % colors.len()];
-        chart
-            .draw_series(LineSeries::new(
-                adj[i].iter().enumerate().map(|(t, p)| (t as f64 * dt, p[0])),
-                color,
-            ))?
-            .label(format!("Neuron {}", i))
-            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], *color));
+    /// Gradient of the cost of `evaluate_cost` given by `compute_adjoint`
+    fn check_tracking_gradient(population: &Population<FhnParameters>) {
+        let target = NeuronState::default();
+        let control = control();
+        let sim = simulate_with_control(population, STEPS, DT, &control, &initial());
+        let adjoints = compute_adjoint(&sim, population, target, 1.0, 1.0, DT);
+        let gradient = compute_control_gradient(&adjoints, &control, LAMBDA2, DT);
+        check_directional_derivative(&gradient, |control| {
+            let sim = simulate_with_control(population, STEPS, DT, control, &initial());
+            evaluate_cost(&sim, control, target, 1.0, LAMBDA2, 1.0, DT)
+        });
     }
 
-    chart.configure_series_labels().border_style(&BLACK).draw()?;
-    Ok(())
+    #[test]
+    fn tracking_gradient_matches_finite_differences() {
+        check_tracking_gradient(&Population::homogeneous(&test_parameters(), 4));
+    }
+
+    #[test]
+    fn sparse_gradient_matches_finite_differences() {
+        // The weights are far from symmetric, so that sending the sensitivities back through W
+        // instead of Wᵀ shows
+        check_tracking_gradient(&star_population());
+    }
+
+    #[test]
+    fn fixed_delay_gradient_matches_finite_differences() {
+        check_tracking_gradient(&star_population().with_delay(SynapticDelay::Fixed(0.5)));
+    }
+
+    #[test]
+    fn gamma_delay_gradient_matches_finite_differences() {
+        check_tracking_gradient(&star_population().with_delay(SynapticDelay::Gamma { mean: 0.5, shape: 3.0 }));
+    }
+
+    #[test]
+    fn population_gradient_matches_finite_differences() {
+        // Excitatory and inhibitory blocks with their own reversal potentials and asymmetric strengths
+        let excitatory = Subpopulation { name: "E".to_string(), params: coupled_parameters(), size: 2 };
+        let inhibitory = Subpopulation {
+            name: "I".to_string(),
+            params: FhnParameters { Vrev: -1.5, ..coupled_parameters() },
+            size: 2,
+        };
+        let weights = vec![vec![0.5, 1.2], vec![0.9, 0.3]];
+        let population = Population::from_subpopulations(&[excitatory, inhibitory], Some(weights)).unwrap();
+        check_tracking_gradient(&population);
+    }
+
+    #[test]
+    fn kernel_pattern_gradient_matches_finite_differences() {
+        let kernel = SpatialKernel::new(Lattice::Ring(4), KernelShape::MexicanHat { sigma_e: 0.8, sigma_i: 2.0, ratio: 0.5 });
+        let population = Population::homogeneous(&coupled_parameters(), 4).with_connectivity(Connectivity::Kernel(kernel));
+        // A wave travelling around the ring
+        let pattern: Vec<Vec<f64>> = (0..4)
+            .map(|i| (0..STEPS).map(|t| (0.5 * t as f64 * DT - 0.5 * PI * i as f64).sin()).collect())
+            .collect();
+
+        let control = control();
+        let sim = simulate_with_control(&population, STEPS, DT, &control, &initial());
+        let adjoints = compute_pattern_adjoint(&sim, &population, &pattern, 1.0, 1.0, DT);
+        let gradient = compute_control_gradient(&adjoints, &control, LAMBDA2, DT);
+        check_directional_derivative(&gradient, |control| {
+            let sim = simulate_with_control(&population, STEPS, DT, control, &initial());
+            evaluate_pattern_cost(&sim, control, &pattern, 1.0, LAMBDA2, 1.0, DT)
+        });
+    }
+
+    /// Gradient of the cost of `evaluate_desync_cost` given by `compute_desync_adjoint`
+    fn check_desync_gradient(spec: DesyncSpec) {
+        let population = Population::homogeneous(&test_parameters(), 4);
+        let channels = [ControlChannel::uniform(population.len())];
+        let simulate = |control: &[f64]| simulate_with_controls(&population, STEPS, DT, 0.0, &channels, &[control.to_vec()], &initial());
+
+        let control = control();
+        let sim = simulate(&control);
+        let objective = spec.build(&sim);
+        let adjoints = compute_desync_adjoint(&sim, &population, &objective, 1.0, DT);
+        let gradient = &compute_channel_gradients(&adjoints, &channels, std::slice::from_ref(&control), LAMBDA2)[0];
+        check_directional_derivative(gradient, |control| {
+            evaluate_desync_cost(&simulate(control), control, &objective, 1.0, LAMBDA2, DT)
+        });
+    }
+
+    #[test]
+    fn lfp_variance_gradient_matches_finite_differences() {
+        check_desync_gradient(DesyncSpec::Variance);
+    }
+
+    #[test]
+    fn kuramoto_gradient_matches_finite_differences() {
+        check_desync_gradient(DesyncSpec::Kuramoto);
+    }
 }
@@ -0,0 +1,175 @@
+// src/simulations/control.rs
+
+use std::str::FromStr;
+use crate::models::connectivity::Connectivity;
+use crate::models::model::{ModelState, NeuronModel};
+use crate::models::population::Population;
+
+/// One scalar control α_c(t) entering the drift of state component `component` of every
+/// neuron i with weight `gains[i]`:  dx_i^k = ... + gains[i] α_c(t) dt.
+/// The columns of the control-to-neuron gain matrix are the `gains` of the channels.
+#[derive(Debug, Clone)]
+pub struct ControlChannel {
+    pub name: String,
+    pub component: usize,
+    pub gains: Vec<f64>,
+}
+
+impl ControlChannel {
+    /// The control of the paper: the same input to the membrane potential of all neurons
+    pub fn uniform(size: usize) -> Self {
+        ControlChannel { name: "alpha".to_string(), component: 0, gains: vec![1.0; size] }
+    }
+}
+
+/// Neurons reached by a control channel
+#[derive(Debug, Clone)]
+pub enum ControlTarget {
+    All,
+    /// A subpopulation, by name
+    Population(String),
+    /// Neurons A..B (B excluded)
+    Range(usize, usize),
+    /// Gaussian profile of the given width (lattice units) around a site of the lattice
+    Bump { center: usize, width: f64 },
+    /// Gain matrix read from a CSV with one row per neuron and one column per channel
+    Gains(String),
+}
+
+/// Control channels as given on the command line, `COMPONENT[@TARGET]`, where COMPONENT is a
+/// state component of the model (e.g. `v`, `w` or `y` for FHN) and TARGET is `all` (default),
+/// the name of a subpopulation, a range of neurons `A..B`, `bump:CENTER,WIDTH` (Gaussian gains
+/// around a site of the lattice) or `gains:PATH` (one channel per column of the CSV),
+/// e.g. `v@E`, `w@0..50`, `v@bump:25,4`.
+#[derive(Debug, Clone)]
+pub struct ControlSpec {
+    pub component: String,
+    pub target: ControlTarget,
+}
+
+impl FromStr for ControlSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (component, target) = s.split_once('@').unwrap_or((s, "all"));
+        let component = component.trim().to_string();
+        if component.is_empty() {
+            return Err(format!("missing state component in control '{}'", s));
+        }
+        let (kind, args) = target.split_once(':').unwrap_or((target, ""));
+
+        let target = match kind {
+            "all" => ControlTarget::All,
+            "bump" => {
+                let numbers: Vec<&str> = args.split(',').map(|a| a.trim()).collect();
+                match numbers.as_slice() {
+                    &[center, width] => ControlTarget::Bump {
+                        center: center.parse().map_err(|e| format!("invalid site '{}': {}", center, e))?,
+                        width: width.parse().map_err(|e| format!("invalid width '{}': {}", width, e))?,
+                    },
+                    _ => return Err(format!("expected bump:CENTER,WIDTH, got '{}'", target)),
+                }
+            }
+            "gains" if !args.is_empty() => ControlTarget::Gains(args.to_string()),
+            _ => match target.split_once("..") {
+                Some((a, b)) => {
+                    let index = |x: &str| x.trim().parse::<usize>().map_err(|e| format!("invalid neuron index '{}': {}", x, e));
+                    ControlTarget::Range(index(a)?, index(b)?)
+                }
+                None => ControlTarget::Population(target.to_string()),
+            },
+        };
+
+        Ok(ControlSpec { component, target })
+    }
+}
+
+impl ControlSpec {
+    /// Channels of this control for the given population
+    pub fn build<N: NeuronModel>(&self, population: &Population<N>) -> Result<Vec<ControlChannel>, String> {
+        let size = population.len();
+        let component = N::State::COMPONENTS
+            .iter()
+            .position(|&c| c == self.component)
+            .ok_or_else(|| format!(
+                "unknown state component '{}', expected one of {:?}",
+                self.component,
+                N::State::COMPONENTS
+            ))?;
+        let channel = |target: String, gains: Vec<f64>| ControlChannel {
+            name: format!("{}@{}", self.component, target),
+            component,
+            gains,
+        };
+
+        match &self.target {
+            ControlTarget::All => Ok(vec![channel("all".to_string(), vec![1.0; size])]),
+            ControlTarget::Population(name) => {
+                let blocks = match &population.connectivity {
+                    Connectivity::Populations(blocks) => blocks,
+                    _ => return Err(format!("no population '{}': the network has a single population", name)),
+                };
+                let l = blocks
+                    .names
+                    .iter()
+                    .position(|n| n == name)
+                    .ok_or_else(|| format!("unknown population '{}', expected one of {:?}", name, blocks.names))?;
+                let range = blocks.range(l);
+                let gains = (0..size).map(|i| if range.contains(&i) { 1.0 } else { 0.0 }).collect();
+                Ok(vec![channel(name.clone(), gains)])
+            }
+            &ControlTarget::Range(a, b) => {
+                if a >= b || b > size {
+                    return Err(format!("invalid range {}..{} for {} neurons", a, b, size));
+                }
+                let gains = (0..size).map(|i| if (a..b).contains(&i) { 1.0 } else { 0.0 }).collect();
+                Ok(vec![channel(format!("{}..{}", a, b), gains)])
+            }
+            &ControlTarget::Bump { center, width } => {
+                if center >= size || width <= 0.0 {
+                    return Err(format!("invalid bump around site {} of width {}", center, width));
+                }
+                let lattice = population.lattice();
+                let gains = (0..size)
+                    .map(|i| {
+                        let d = lattice.distance(i, center);
+                        (-d * d / (2.0 * width * width)).exp()
+                    })
+                    .collect();
+                Ok(vec![channel(format!("bump:{},{}", center, width), gains)])
+            }
+            ControlTarget::Gains(path) => {
+                let columns = load_gains_from_csv(path)
+                    .map_err(|e| format!("failed to load gains from {}: {}", path, e))?;
+                if columns.iter().any(|column| column.gains.len() != size) {
+                    return Err(format!("{} must have one row per neuron ({})", path, size));
+                }
+                Ok(columns.into_iter().map(|column| channel(column.name, column.gains)).collect())
+            }
+        }
+    }
+}
+
+/// Columns of a gain matrix stored as a CSV with one row per neuron, as channels named after
+/// the header (on the first component); a `neuron_id` column is skipped
+fn load_gains_from_csv(path: &str) -> Result<Vec<ControlChannel>, Box<dyn std::error::Error>> {
+    let mut rdr = csv::Reader::from_path(path)?;
+
+    let header = rdr.headers()?.clone();
+    let kept: Vec<usize> = (0..header.len()).filter(|&col| &header[col] != "neuron_id").collect();
+    if kept.is_empty() {
+        return Err("no gain column".into());
+    }
+
+    let mut columns: Vec<ControlChannel> = kept
+        .iter()
+        .map(|&col| ControlChannel { name: header[col].to_string(), component: 0, gains: Vec::new() })
+        .collect();
+    for record in rdr.records() {
+        let record = record?;
+        for (column, &col) in columns.iter_mut().zip(kept.iter()) {
+            column.gains.push(record[col].trim().parse()?);
+        }
+    }
+    Ok(columns)
+}
@@ -1,13 +1,20 @@
 // src/simulations/forward.rs
 
-use plotters::style::full_palette::PURPLE;
 use serde::Serialize;
 use rand_distr::{Distribution, Normal};
-use crate::models::neuron::{NeuronState, FhnParameters};
+use crate::draw_figure;
+use crate::models::connectivity::PopulationBlocks;
+use crate::models::model::{ModelState, NeuronModel};
+use crate::models::neuron::{FhnParameters, NeuronState};
+use crate::models::population::Population;
+use crate::models::spatial::Lattice;
+use crate::plotting::PlotStyle;
+use crate::simulations::control::ControlChannel;
+use crate::simulations::initial::InitialCondition;
 use plotters::prelude::*;
 
 
-/// One row of output (for 1 neuron at 1 time step)
+/// One row of output (for 1 neuron at 1 time step) of an FHN simulation
 #[derive(Serialize)]
 pub struct SimulationRow {
     pub neuron_id: usize,
@@ -17,23 +24,23 @@ pub struct SimulationRow {
     pub y: f64,
 }
 
-pub fn save_simulation_to_csv(
-    sim: &Vec<Vec<NeuronState>>,
+/// Writes one row per neuron and time step, with one column per state component
+pub fn save_simulation_to_csv<S: ModelState>(
+    sim: &[Vec<S>],
     dt: f64,
     path: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let mut wtr = csv::Writer::from_path(path)?;
 
+    let mut header = vec!["neuron_id", "time"];
+    header.extend_from_slice(S::COMPONENTS);
+    wtr.write_record(&header)?;
+
     for (neuron_id, trajectory) in sim.iter().enumerate() {
         for (step, state) in trajectory.iter().enumerate() {
-            let row = SimulationRow {
-                neuron_id,
-                time: step as f64 * dt,
-                v: state.v,
-                w: state.w,
-                y: state.y,
-            };
-            wtr.serialize(row)?;
+            let mut row = vec![neuron_id.to_string(), (step as f64 * dt).to_string()];
+            row.extend((0..S::DIM).map(|k| state[k].to_string()));
+            wtr.write_record(&row)?;
         }
     }
 
@@ -42,54 +49,123 @@ pub fn save_simulation_to_csv(
 }
 
 
-/// Simulates L neurons over M time steps of size dt.
-/// Returns: Vec of trajectories, each of length M.
-pub fn simulate_fhn_population(
-    L: usize,
-    M: usize,
+/// Synaptic inputs of every neuron at time step t: the coupling observables of the
+/// population combined through its connectivity (the plain average for a mean field).
+/// With delays, the observables are averaged over the past steps given by `taps`
+/// (see `SynapticDelay::taps`), those before the start being the initial ones.
+pub fn synaptic_inputs<N: NeuronModel>(
+    population: &Population<N>,
+    sim: &[Vec<N::State>],
+    t: usize,
+    taps: &[(usize, f64)],
+) -> Vec<Vec<f64>> {
+    let observables: Vec<f64> = sim
+        .iter()
+        .zip(population.neurons.iter())
+        .map(|(traj, neuron)| {
+            taps.iter()
+                .map(|&(delay, weight)| weight * neuron.coupling_observable(&traj[t.saturating_sub(delay)]))
+                .sum()
+        })
+        .collect();
+    population.connectivity.inputs(&observables)
+}
+
+
+/// Values of the control channels at a time step, given the trajectories up to that step
+pub type ControlLaw<'a, S> = &'a dyn Fn(usize, &[Vec<S>]) -> Vec<f64>;
+
+
+/// Euler–Maruyama scheme shared by the forward solvers.
+/// The controls, if any, are added to the drifts of their channels: `law(t, sim)` gives the
+/// value of each channel at step t, knowing the trajectories up to t (a lookup for open-loop
+/// controls). Scheduled parameters (and noise) take their value at the start of each step;
+/// a run with `sigma_ext` = 0 is deterministic and ignores the noise schedule.
+fn euler_maruyama<N: NeuronModel>(
+    population: &Population<N>,
+    m: usize,
     dt: f64,
-    params: &FhnParameters,
     sigma_ext: f64,
-    initial: NeuronState,
-) -> Vec<Vec<NeuronState>> {
+    controls: Option<(&[ControlChannel], ControlLaw<N::State>)>,
+    initial: &InitialCondition<N::State>,
+) -> Vec<Vec<N::State>> {
     let sqrt_dt = dt.sqrt();
     let normal = Normal::new(0.0, 1.0).unwrap();
+    let mut rng = rand::thread_rng();
+    let taps = population.delay.taps(dt);
+    let mut current = population.clone();
+
+    // Initialize each neuron with a draw from the initial condition
+    let mut trajectories: Vec<Vec<N::State>> = initial
+        .sample(population.len(), &mut rng)
+        .into_iter()
+        .map(|state| vec![state; m])
+        .collect();
 
-    // Initialize all neurons with the same state
-    let mut trajectories = vec![vec![initial; M]; L];
+    for t in 1..m {
+        let time = (t - 1) as f64 * dt;
+        current.apply_schedules(time);
+        let sigma_ext = if sigma_ext == 0.0 { 0.0 } else { population.sigma_ext_at(time, sigma_ext) };
+        let inputs = synaptic_inputs(&current, &trajectories, t - 1, &taps);
+        let alphas = controls.map(|(_, law)| law(t - 1, &trajectories));
 
-    for t in 1..M {
-        // Compute mean gate value across population
-        let mean_y: f64 = trajectories.iter()
-            .map(|traj| traj[t - 1].y)
-            .sum::<f64>() / (L as f64);
+        for (i, (traj, neuron)) in trajectories.iter_mut().zip(current.neurons.iter()).enumerate() {
+            let prev = traj[t - 1];
+            let drift = current.drift(i, &prev, &inputs[i]);
+            let diffusion = neuron.diffusion(&prev);
+
+            let mut next = prev;
+            for k in 0..N::State::DIM {
+                next[k] += dt * drift[k];
+                if sigma_ext != 0.0 && diffusion[k] != 0.0 {
+                    next[k] += sigma_ext * diffusion[k] * sqrt_dt * normal.sample(&mut rng);
+                }
+            }
+            if let (Some((channels, _)), Some(alphas)) = (controls, &alphas) {
+                for (channel, alpha) in channels.iter().zip(alphas.iter()) {
+                    next[channel.component] += dt * channel.gains[i] * alpha;
+                }
+            }
+            traj[t] = next;
+        }
+    }
 
-        // Update each neuron
-        for traj in trajectories.iter_mut() {
-            let prev = *traj.get(t - 1).unwrap(); // idiomatic way of cloning
+    trajectories
+}
 
-            // Drift part
-            let drift = prev.drift(mean_y, params);
 
-            // Diffusion part (only on v)
-            let noise = sigma_ext * sqrt_dt * normal.sample(&mut rand::thread_rng());
+/// Simulates the L neurons of the population over M time steps of size dt.
+/// Returns: Vec of trajectories, each of length M.
+pub fn simulate_population<N: NeuronModel>(
+    population: &Population<N>,
+    m: usize,
+    dt: f64,
+    sigma_ext: f64,
+    initial: &InitialCondition<N::State>,
+) -> Vec<Vec<N::State>> {
+    euler_maruyama(population, m, dt, sigma_ext, None, initial)
+}
 
-            traj[t] = NeuronState {
-                v: prev.v + dt * drift.v + noise,
-                w: prev.w + dt * drift.w,
-                y: prev.y + dt * drift.y,
-            };
-        }
-    }
 
-    trajectories
+/// Simulates L identical FHN neurons coupled through their mean field, all starting from
+/// `initial`, over M time steps of size dt (see `simulate_population` for other populations)
+pub fn simulate_fhn_population(
+    l: usize,
+    m: usize,
+    dt: f64,
+    params: &FhnParameters,
+    sigma_ext: f64,
+    initial: NeuronState,
+) -> Vec<Vec<NeuronState>> {
+    simulate_population(&Population::homogeneous(params, l), m, dt, sigma_ext, &InitialCondition::Fixed(initial))
 }
 
 
 /// Plots the average membrane potential (v) over time
-pub fn plot_local_field_potential(
-    sim: &Vec<Vec<NeuronState>>,
+pub fn plot_local_field_potential<S: ModelState>(
+    sim: &[Vec<S>],
     dt: f64,
+    style: &PlotStyle,
     filename: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let M = sim[0].len();
@@ -98,43 +174,41 @@ pub fn plot_local_field_potential(
     // Compute mean voltage at each time step
     let mean_v: Vec<f64> = (0..M)
         .map(|t| {
-            sim.iter().map(|traj| traj[t].v).sum::<f64>() / (L as f64)
+            sim.iter().map(|traj| traj[t].potential()).sum::<f64>() / (L as f64)
         })
         .collect();
 
-    let root = BitMapBackend::new(filename, (800, 600)).into_drawing_area();
-    root.fill(&WHITE)?;
-
     let time_max = M as f64 * dt;
     let v_min = mean_v.iter().cloned().fold(f64::INFINITY, f64::min);
     let v_max = mean_v.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
 
-    let mut chart = ChartBuilder::on(&root)
-        .caption("Local Field Potential", ("sans-serif", 30))
-        .margin(20)
-        .x_label_area_size(40)
-        .y_label_area_size(40)
-        .build_cartesian_2d(0.0..time_max, v_min..v_max)?;
+    draw_figure!(style, filename, |root| {
+        let mut chart = style
+            .chart(&root, "Local Field Potential")
+            .build_cartesian_2d(0.0..time_max, v_min..v_max)?;
 
-    chart.configure_mesh().draw()?;
+        chart.configure_mesh().label_style(style.label_font()).draw()?;
 
-    chart.draw_series(LineSeries::new(
-        mean_v.iter().enumerate().map(|(i, v)| (i as f64 * dt, *v)),
-        &RED,
-    ))?
-    .label("mean(v)")
-    .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &RED));
+        let line = style.line(0, 0);
+        chart.draw_series(LineSeries::new(
+            mean_v.iter().enumerate().map(|(i, v)| (i as f64 * dt, *v)),
+            line,
+        ))?
+        .label("mean(v)")
+        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], line));
 
-    chart.configure_series_labels().border_style(&BLACK).draw()?;
+        chart.configure_series_labels().label_font(style.label_font()).border_style(BLACK).draw()?;
 
-    Ok(())
+        Ok(())
+    })
 }
 
 
 /// Plots the v(t) trajectory of a few individual neurons
-pub fn plot_individual_neurons(
-    sim: &Vec<Vec<NeuronState>>,
+pub fn plot_individual_neurons<S: ModelState>(
+    sim: &[Vec<S>],
     dt: f64,
+    style: &PlotStyle,
     filename: &str,
     count: usize,
 ) -> Result<(), Box<dyn std::error::Error>> {
@@ -142,115 +216,319 @@ pub fn plot_individual_neurons(
     let L = sim.len();
     let count = count.min(L); // clamp to number of available neurons
 
-    let root = BitMapBackend::new(filename, (800, 600)).into_drawing_area();
-    root.fill(&WHITE)?;
-
     let time_max = M as f64 * dt;
 
     let v_min = sim.iter()
         .take(count)
-        .flat_map(|traj| traj.iter().map(|s| s.v))
+        .flat_map(|traj| traj.iter().map(|s| s.potential()))
         .fold(f64::INFINITY, f64::min);
     
     let v_max = sim.iter()
         .take(count)
-        .flat_map(|traj| traj.iter().map(|s| s.v))
+        .flat_map(|traj| traj.iter().map(|s| s.potential()))
         .fold(f64::NEG_INFINITY, f64::max);
 
-    let mut chart = ChartBuilder::on(&root)
-        .caption("Neuron Voltages", ("sans-serif", 30))
-        .margin(20)
-        .x_label_area_size(40)
-        .y_label_area_size(40)
-        .build_cartesian_2d(0.0..time_max, v_min..v_max)?;
+    draw_figure!(style, filename, |root| {
+        let mut chart = style
+            .chart(&root, "Neuron Voltages")
+            .build_cartesian_2d(0.0..time_max, v_min..v_max)?;
+
+        chart.configure_mesh().label_style(style.label_font()).draw()?;
+
+        for (i, trajectory) in sim.iter().enumerate().take(count) {
+            let line = style.line(i, 0);
+            chart
+                .draw_series(LineSeries::new(
+                    trajectory.iter().enumerate().map(|(t, s)| (t as f64 * dt, s.potential())),
+                    line,
+                ))?
+                .label(format!("Neuron {}", i))
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], line));
+        }
 
-    chart.configure_mesh().draw()?;
+        chart.configure_series_labels().label_font(style.label_font()).border_style(BLACK).draw()?;
+        Ok(())
+    })
+}
 
-    let colors = [
-        &RED, &BLUE, &GREEN, &MAGENTA, &CYAN, &BLACK, &YELLOW, &PURPLE,
-    ];
 
-    for i in 0..count {
-        let color = colors[i % colors.len()];
-        chart
-            .draw_series(LineSeries::new(
-                sim[i].iter().enumerate().map(|(t, s)| (t as f64 * dt, s.v)),
-                color,
-            ))?
-            .label(format!("Neuron {}", i))
-            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
-    }
-
-    chart.configure_series_labels().border_style(&BLACK).draw()?;
-    Ok(())
+/// Simulate the population using a fixed control α(t), without noise (even when the
+/// noise intensity is scheduled)
+pub fn simulate_with_control<N: NeuronModel>(
+    population: &Population<N>,
+    m: usize,
+    dt: f64,
+    control: &[f64],
+    initial: &InitialCondition<N::State>,
+) -> Vec<Vec<N::State>> {
+    let channels = [ControlChannel::uniform(population.len())];
+    let law = |t: usize, _: &[Vec<N::State>]| vec![control[t]];
+    euler_maruyama(population, m, dt, 0.0, Some((&channels, &law)), initial)
 }
 
 
-/// Simulate the population using a fixed control α(t)
-pub fn simulate_with_control(
-    L: usize,
-    M: usize,
+/// Simulate the noisy population under a fixed control α(t)
+pub fn simulate_controlled_population<N: NeuronModel>(
+    population: &Population<N>,
+    m: usize,
     dt: f64,
-    params: &FhnParameters,
-    control: &Vec<f64>,
-    initial: NeuronState,
-) -> Vec<Vec<NeuronState>> {
-    let mut trajectories = vec![vec![initial; M]; L];
+    sigma_ext: f64,
+    control: &[f64],
+    initial: &InitialCondition<N::State>,
+) -> Vec<Vec<N::State>> {
+    let channels = [ControlChannel::uniform(population.len())];
+    let law = |t: usize, _: &[Vec<N::State>]| vec![control[t]];
+    euler_maruyama(population, m, dt, sigma_ext, Some((&channels, &law)), initial)
+}
 
-    for t in 1..M {
-        let mean_y = trajectories.iter().map(|traj| traj[t - 1].y).sum::<f64>() / L as f64;
-        let alpha = control[t - 1]; // assume control length = M
 
-        for traj in trajectories.iter_mut() {
-            let prev = traj[t - 1];
-            let drift = prev.drift(mean_y, params);
-            traj[t] = NeuronState {
-                v: prev.v + dt * (drift.v + alpha),
-                w: prev.w + dt * drift.w,
-                y: prev.y + dt * drift.y,
-            };
-        }
-    }
+/// Simulate the noisy population under fixed controls α_c(t), one per channel
+/// (`sigma_ext` = 0 for a deterministic run)
+pub fn simulate_with_controls<N: NeuronModel>(
+    population: &Population<N>,
+    m: usize,
+    dt: f64,
+    sigma_ext: f64,
+    channels: &[ControlChannel],
+    controls: &[Vec<f64>],
+    initial: &InitialCondition<N::State>,
+) -> Vec<Vec<N::State>> {
+    let law = |t: usize, _: &[Vec<N::State>]| controls.iter().map(|control| control[t]).collect();
+    euler_maruyama(population, m, dt, sigma_ext, Some((channels, &law)), initial)
+}
 
-    trajectories
+
+/// Simulate the noisy population in closed loop: the controls at each step are computed
+/// by `law` from the trajectories so far (e.g. from the observed mean potential)
+pub fn simulate_with_feedback<N: NeuronModel>(
+    population: &Population<N>,
+    m: usize,
+    dt: f64,
+    sigma_ext: f64,
+    channels: &[ControlChannel],
+    law: ControlLaw<N::State>,
+    initial: &InitialCondition<N::State>,
+) -> Vec<Vec<N::State>> {
+    euler_maruyama(population, m, dt, sigma_ext, Some((channels, law)), initial)
 }
 
 
-pub fn plot_average_potential(
-    sim: &Vec<Vec<NeuronState>>,
+pub fn plot_average_potential<S: ModelState>(
+    sim: &[Vec<S>],
     dt: f64,
+    style: &PlotStyle,
     filename: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let m = sim[0].len();
     let l = sim.len();
-    let root = BitMapBackend::new(filename, (800, 600)).into_drawing_area();
-    root.fill(&WHITE)?;
 
     let t_max = dt * m as f64;
 
     let mean_v: Vec<f64> = (0..m)
-        .map(|t| sim.iter().map(|traj| traj[t].v).sum::<f64>() / l as f64)
+        .map(|t| sim.iter().map(|traj| traj[t].potential()).sum::<f64>() / l as f64)
         .collect();
 
     let v_min = mean_v.iter().cloned().fold(f64::INFINITY, f64::min);
     let v_max = mean_v.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
 
-    let mut chart = ChartBuilder::on(&root)
-        .caption("Average Voltage under Optimal Control", ("sans-serif", 30))
-        .margin(20)
-        .x_label_area_size(40)
-        .y_label_area_size(50)
-        .build_cartesian_2d(0.0..t_max, v_min..v_max)?;
+    draw_figure!(style, filename, |root| {
+        let mut chart = style
+            .chart(&root, "Average Voltage under Optimal Control")
+            .build_cartesian_2d(0.0..t_max, v_min..v_max)?;
+
+        chart.configure_mesh().label_style(style.label_font()).draw()?;
+
+        let line = style.line(1, 0);
+        chart.draw_series(LineSeries::new(
+            mean_v.iter().enumerate().map(|(t, v)| (t as f64 * dt, *v)),
+            line,
+        ))?
+        .label("v̄(t)")
+        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], line));
+
+        chart.configure_series_labels().label_font(style.label_font()).border_style(BLACK).draw()?;
+        Ok(())
+    })
+}
+
+
+/// Mean of every state component within each population, at time step t
+fn population_means<S: ModelState>(sim: &[Vec<S>], blocks: &PopulationBlocks, t: usize) -> Vec<S> {
+    (0..blocks.len())
+        .map(|l| {
+            let range = blocks.range(l);
+            let mut mean = S::default();
+            for traj in &sim[range.clone()] {
+                for k in 0..S::DIM {
+                    mean[k] += traj[t][k] / range.len() as f64;
+                }
+            }
+            mean
+        })
+        .collect()
+}
+
+
+/// Writes one row per time step with the mean of each component within each population,
+/// in columns `<component>_<population>` (e.g. `v_E`, `w_E`, ..., `y_I`)
+pub fn save_population_means_to_csv<S: ModelState>(
+    sim: &[Vec<S>],
+    blocks: &PopulationBlocks,
+    dt: f64,
+    path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut wtr = csv::Writer::from_path(path)?;
 
-    chart.configure_mesh().draw()?;
+    let mut header = vec!["time".to_string()];
+    for name in &blocks.names {
+        header.extend(S::COMPONENTS.iter().map(|component| format!("{}_{}", component, name)));
+    }
+    wtr.write_record(&header)?;
 
-    chart.draw_series(LineSeries::new(
-        mean_v.iter().enumerate().map(|(t, v)| (t as f64 * dt, *v)),
-        &BLUE,
-    ))?
-    .label("v̄(t)")
-    .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &BLUE));
+    for t in 0..sim[0].len() {
+        let mut row = vec![(t as f64 * dt).to_string()];
+        for mean in population_means(sim, blocks, t) {
+            row.extend((0..S::DIM).map(|k| mean[k].to_string()));
+        }
+        wtr.write_record(&row)?;
+    }
 
-    chart.configure_series_labels().border_style(&BLACK).draw()?;
+    wtr.flush()?;
     Ok(())
 }
+
+
+/// Plots the average membrane potential of each population over time
+pub fn plot_population_potentials<S: ModelState>(
+    sim: &[Vec<S>],
+    blocks: &PopulationBlocks,
+    dt: f64,
+    style: &PlotStyle,
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let m = sim[0].len();
+    let means: Vec<Vec<S>> = (0..m).map(|t| population_means(sim, blocks, t)).collect();
+
+    let t_max = dt * m as f64;
+    let v_min = means.iter().flatten().map(|s| s.potential()).fold(f64::INFINITY, f64::min);
+    let v_max = means.iter().flatten().map(|s| s.potential()).fold(f64::NEG_INFINITY, f64::max);
+
+    draw_figure!(style, filename, |root| {
+        let mut chart = style
+            .chart(&root, "Population Potentials")
+            .build_cartesian_2d(0.0..t_max, v_min..v_max)?;
+
+        chart.configure_mesh().label_style(style.label_font()).draw()?;
+
+        for (l, name) in blocks.names.iter().enumerate() {
+            let line = style.line(l, 0);
+            chart
+                .draw_series(LineSeries::new(
+                    means.iter().enumerate().map(|(t, mean)| (t as f64 * dt, mean[l].potential())),
+                    line,
+                ))?
+                .label(format!("mean(v) {}", name))
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], line));
+        }
+
+        chart.configure_series_labels().label_font(style.label_font()).border_style(BLACK).draw()?;
+        Ok(())
+    })
+}
+
+
+/// Membrane potential of every neuron at every time step
+pub fn potentials<S: ModelState>(sim: &[Vec<S>]) -> Vec<Vec<f64>> {
+    sim.iter().map(|traj| traj.iter().map(|s| s.potential()).collect()).collect()
+}
+
+
+/// Blue (low) to red (high) colour of a value within [min, max]
+pub fn heat_color(value: f64, min: f64, max: f64) -> HSLColor {
+    let frac = if max > min { (value - min) / (max - min) } else { 0.5 };
+    HSLColor(0.66 * (1.0 - frac), 0.9, 0.5)
+}
+
+
+/// Heatmap of v_i(t), with the neurons (lattice sites) on the vertical axis,
+/// e.g. to follow traveling waves on a ring
+pub fn plot_space_time(
+    values: &[Vec<f64>],
+    dt: f64,
+    caption: &str,
+    style: &PlotStyle,
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let l = values.len();
+    let m = values[0].len();
+    // At most ~400 columns
+    let stride = (m / 400).max(1);
+
+    let v_min = values.iter().flatten().cloned().fold(f64::INFINITY, f64::min);
+    let v_max = values.iter().flatten().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+    draw_figure!(style, filename, |root| {
+        let mut chart = style
+            .chart(&root, caption)
+            .build_cartesian_2d(0.0..m as f64 * dt, 0.0..l as f64)?;
+
+        chart
+            .configure_mesh()
+            .x_desc("t")
+            .y_desc("neuron")
+            .label_style(style.label_font())
+            .axis_desc_style(style.label_font())
+            .disable_mesh()
+            .draw()?;
+
+        chart.draw_series((0..l).flat_map(|i| {
+            (0..m).step_by(stride).map(move |t| {
+                Rectangle::new(
+                    [(t as f64 * dt, i as f64), ((t + stride) as f64 * dt, (i + 1) as f64)],
+                    heat_color(values[i][t], v_min, v_max).filled(),
+                )
+            })
+        }))?;
+
+        Ok(())
+    })
+}
+
+
+/// Heatmap of one value per site of a grid (e.g. v at a given time)
+pub fn plot_field_snapshot(
+    values: &[f64],
+    lattice: &Lattice,
+    caption: &str,
+    style: &PlotStyle,
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (nx, ny) = lattice.dims();
+    let v_min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let v_max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+    draw_figure!(style, filename, |root| {
+        let mut chart = style
+            .chart(&root, caption)
+            .build_cartesian_2d(0.0..nx as f64, 0.0..ny as f64)?;
+
+        chart
+            .configure_mesh()
+            .x_desc("x")
+            .y_desc("y")
+            .label_style(style.label_font())
+            .axis_desc_style(style.label_font())
+            .disable_mesh()
+            .draw()?;
+
+        chart.draw_series(values.iter().enumerate().map(|(i, v)| {
+            let (x, y) = lattice.position(i);
+            Rectangle::new(
+                [(x as f64, y as f64), ((x + 1) as f64, (y + 1) as f64)],
+                heat_color(*v, v_min, v_max).filled(),
+            )
+        }))?;
+
+        Ok(())
+    })
+}
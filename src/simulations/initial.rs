@@ -0,0 +1,231 @@
+// src/simulations/initial.rs
+
+use rand::distributions::Uniform;
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use crate::models::model::{ModelState, NeuronModel};
+use crate::models::population::Population;
+use crate::simulations::forward::simulate_population;
+
+/// Law of the initial states of the neurons, sampled anew by every forward simulation
+#[derive(Debug, Clone)]
+pub enum InitialCondition<S: ModelState> {
+    /// All neurons start from the same state
+    Fixed(S),
+    /// Independent Gaussian perturbations of each component around `center`
+    Gaussian { center: S, std: S },
+    /// Uniform in the box [low, high]
+    Uniform { low: S, high: S },
+    /// Empirical states: neuron i starts from `states[i]` when there is one state per neuron,
+    /// otherwise the states are resampled with replacement
+    Samples(Vec<S>),
+}
+
+impl<S: ModelState> InitialCondition<S> {
+    /// Initial states of a population of `size` neurons
+    pub fn sample<R: Rng + ?Sized>(&self, size: usize, rng: &mut R) -> Vec<S> {
+        match self {
+            InitialCondition::Fixed(state) => vec![*state; size],
+            InitialCondition::Gaussian { center, std } => {
+                let normal = Normal::new(0.0, 1.0).unwrap();
+                (0..size)
+                    .map(|_| {
+                        let mut state = *center;
+                        for k in 0..S::DIM {
+                            state[k] += std[k] * normal.sample(rng);
+                        }
+                        state
+                    })
+                    .collect()
+            }
+            InitialCondition::Uniform { low, high } => (0..size)
+                .map(|_| {
+                    let mut state = *low;
+                    for k in 0..S::DIM {
+                        state[k] = Uniform::new_inclusive(low[k], high[k]).sample(rng);
+                    }
+                    state
+                })
+                .collect(),
+            InitialCondition::Samples(states) if states.len() == size => states.clone(),
+            InitialCondition::Samples(states) => (0..size)
+                .map(|_| states[rng.gen_range(0..states.len())])
+                .collect(),
+        }
+    }
+
+    /// Approximately stationary states: the uncontrolled population is run for `steps`
+    /// time steps from `start` and its final states are kept.
+    pub fn burn_in<N: NeuronModel<State = S>>(
+        population: &Population<N>,
+        start: &InitialCondition<S>,
+        steps: usize,
+        dt: f64,
+        sigma_ext: f64,
+    ) -> Self {
+        let sim = simulate_population(population, steps + 1, dt, sigma_ext, start);
+        InitialCondition::Samples(sim.iter().map(|traj| traj[steps]).collect())
+    }
+}
+
+/// States of all neurons at the last time step of a CSV written by `save_simulation_to_csv`
+pub fn load_final_states_from_csv<S: ModelState>(path: &str) -> Result<Vec<S>, Box<dyn std::error::Error>> {
+    let mut rdr = csv::Reader::from_path(path)?;
+
+    let header = rdr.headers()?.clone();
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|h| h == name)
+            .ok_or_else(|| format!("column '{}' missing in {}", name, path))
+    };
+    let id_col = column("neuron_id")?;
+    let time_col = column("time")?;
+    let state_cols = S::COMPONENTS
+        .iter()
+        .map(|name| column(name))
+        .collect::<Result<Vec<usize>, String>>()?;
+
+    // Latest (time, state) of every neuron
+    let mut latest: BTreeMap<usize, (f64, S)> = BTreeMap::new();
+    for record in rdr.records() {
+        let record = record?;
+        let neuron_id: usize = record[id_col].parse()?;
+        let time: f64 = record[time_col].parse()?;
+        let mut state = S::default();
+        for (k, &col) in state_cols.iter().enumerate() {
+            state[k] = record[col].parse()?;
+        }
+        if latest.get(&neuron_id).is_none_or(|(t, _)| time >= *t) {
+            latest.insert(neuron_id, (time, state));
+        }
+    }
+
+    if latest.is_empty() {
+        return Err(format!("no states found in {}", path).into());
+    }
+    Ok(latest.into_values().map(|(_, state)| state).collect())
+}
+
+/// Model-independent description of an initial condition, as given on the command line:
+/// `fixed`, `gaussian:STD[,STD...]`, `uniform:LOW,HIGH[,LOW,HIGH...]`, `csv:PATH`, `burn-in:STEPS`
+/// or `pulse:RADIUS,AMPLITUDE`.
+/// Gaussian draws are centred on the model's default initial state; a single standard
+/// deviation (or box) applies to every component. A pulse raises the potential of the neurons
+/// within RADIUS of the first one on the lattice (neuron indices otherwise), e.g. to launch a wave.
+#[derive(Debug, Clone)]
+pub enum InitialConditionSpec {
+    Fixed,
+    Gaussian(Vec<f64>),
+    Uniform(Vec<f64>),
+    Csv(String),
+    BurnIn(usize),
+    Pulse(f64, f64),
+}
+
+impl FromStr for InitialConditionSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, args) = s.split_once(':').unwrap_or((s, ""));
+        let numbers = || {
+            args.split(',')
+                .map(|a| a.trim().parse::<f64>().map_err(|e| format!("invalid number '{}': {}", a, e)))
+                .collect::<Result<Vec<f64>, String>>()
+        };
+
+        match kind {
+            "fixed" => Ok(InitialConditionSpec::Fixed),
+            "gaussian" => Ok(InitialConditionSpec::Gaussian(numbers()?)),
+            "uniform" => Ok(InitialConditionSpec::Uniform(numbers()?)),
+            "csv" if !args.is_empty() => Ok(InitialConditionSpec::Csv(args.to_string())),
+            "burn-in" => args
+                .parse()
+                .map(InitialConditionSpec::BurnIn)
+                .map_err(|e| format!("invalid number of burn-in steps '{}': {}", args, e)),
+            "pulse" => match numbers()?.as_slice() {
+                &[radius, amplitude] => Ok(InitialConditionSpec::Pulse(radius, amplitude)),
+                _ => Err(format!("expected pulse:RADIUS,AMPLITUDE, got '{}'", s)),
+            },
+            _ => Err(format!(
+                "unknown initial condition '{}', expected fixed, gaussian:STD, uniform:LOW,HIGH, csv:PATH, burn-in:STEPS or pulse:RADIUS,AMPLITUDE",
+                s
+            )),
+        }
+    }
+}
+
+impl InitialConditionSpec {
+    /// Initial condition for the given population; `default` is the model's reference initial state
+    pub fn build<N: NeuronModel>(
+        &self,
+        population: &Population<N>,
+        default: N::State,
+        dt: f64,
+        sigma_ext: f64,
+    ) -> Result<InitialCondition<N::State>, String> {
+        let dim = N::State::DIM;
+        let per_component = |values: &[f64], width: usize| -> Result<Vec<N::State>, String> {
+            let values = if values.len() == width { values.repeat(dim) } else { values.to_vec() };
+            if values.len() != width * dim {
+                return Err(format!(
+                    "expected {} or {} values for the components {:?}, got {}",
+                    width, width * dim, N::State::COMPONENTS, values.len()
+                ));
+            }
+            Ok((0..width)
+                .map(|j| {
+                    let mut state = N::State::default();
+                    for k in 0..dim {
+                        state[k] = values[k * width + j];
+                    }
+                    state
+                })
+                .collect())
+        };
+
+        match self {
+            InitialConditionSpec::Fixed => Ok(InitialCondition::Fixed(default)),
+            InitialConditionSpec::Gaussian(std) => {
+                let std = per_component(std, 1)?[0];
+                Ok(InitialCondition::Gaussian { center: default, std })
+            }
+            InitialConditionSpec::Uniform(bounds) => {
+                let bounds = per_component(bounds, 2)?;
+                let (low, high) = (bounds[0], bounds[1]);
+                for (k, name) in N::State::COMPONENTS.iter().enumerate() {
+                    if !(low[k].is_finite() && high[k].is_finite() && low[k] <= high[k]) {
+                        return Err(format!("invalid box for {}: expected finite LOW <= HIGH, got {},{}", name, low[k], high[k]));
+                    }
+                }
+                Ok(InitialCondition::Uniform { low, high })
+            }
+            InitialConditionSpec::Csv(path) => load_final_states_from_csv(path)
+                .map(InitialCondition::Samples)
+                .map_err(|e| format!("failed to load initial states from {}: {}", path, e)),
+            InitialConditionSpec::BurnIn(steps) => Ok(InitialCondition::burn_in(
+                population,
+                &InitialCondition::Fixed(default),
+                *steps,
+                dt,
+                sigma_ext,
+            )),
+            InitialConditionSpec::Pulse(radius, amplitude) => {
+                let lattice = population.lattice();
+                Ok(InitialCondition::Samples(
+                    (0..population.len())
+                        .map(|i| {
+                            let mut state = default;
+                            if lattice.distance(i, 0) <= *radius {
+                                state[0] += amplitude;
+                            }
+                            state
+                        })
+                        .collect(),
+                ))
+            }
+        }
+    }
+}
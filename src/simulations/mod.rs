@@ -1,2 +1,4 @@
 pub mod forward;
-pub mod adjoint;
\ No newline at end of file
+pub mod adjoint;
+pub mod initial;
+pub mod control;